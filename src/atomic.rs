@@ -0,0 +1,216 @@
+//! # Atomic File Writes
+//!
+//! Objects, refs, `HEAD` and the index are never written in place. The new
+//! content goes to a temporary file, which is renamed over the target once
+//! it is complete. A rename within a filesystem is atomic, so a reader sees
+//! either the old file or the new one, never a truncated mix. A crash or
+//! Ctrl-C leaves at worst a stray `tmp_*` file behind.
+//!
+//! ## Durability
+//!
+//! By default the data is left in the OS page cache. With fsync enabled
+//! (`RIT_FSYNC=1` in the environment, or [`set_fsync`]), the temporary file
+//! is flushed to disk before the rename and the directory after it, so the
+//! write also survives a power loss.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use rit::atomic;
+//!
+//! atomic::write(Path::new(".rit/refs/heads/main"), Path::new(".rit"), b"abc123...\n").unwrap();
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use anyhow::{Context, Result};
+
+/// Environment variable that turns on fsync
+pub const FSYNC_ENV: &str = "RIT_FSYNC";
+
+/// Fsync setting: not decided yet (read the environment)
+const FSYNC_UNSET: u8 = 0;
+const FSYNC_OFF: u8 = 1;
+const FSYNC_ON: u8 = 2;
+
+static FSYNC: AtomicU8 = AtomicU8::new(FSYNC_UNSET);
+
+/// Counter to keep temporary file names unique within a process
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Turn fsync on or off for every later write in this process
+///
+/// Overrides the `RIT_FSYNC` environment variable.
+pub fn set_fsync(enabled: bool) {
+    FSYNC.store(if enabled { FSYNC_ON } else { FSYNC_OFF }, Ordering::Relaxed);
+}
+
+/// Check whether writes are flushed to disk before being renamed into place
+pub fn fsync_enabled() -> bool {
+    match FSYNC.load(Ordering::Relaxed) {
+        FSYNC_UNSET => {
+            let enabled = std::env::var(FSYNC_ENV)
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+                .unwrap_or(false);
+            set_fsync(enabled);
+            enabled
+        }
+        setting => setting == FSYNC_ON,
+    }
+}
+
+/// A temporary file that becomes visible only when persisted
+///
+/// Dropping it without calling [`AtomicFile::persist`] deletes the
+/// temporary file.
+#[derive(Debug)]
+pub struct AtomicFile {
+    /// Where the content is written until it is complete
+    tmp_path: PathBuf,
+    /// The open temporary file (taken when persisting)
+    writer: Option<BufWriter<File>>,
+}
+
+impl AtomicFile {
+    /// Create a new temporary file in `tmp_dir`
+    ///
+    /// `tmp_dir` must be on the same filesystem as the final path, and
+    /// should not be a directory that is listed for content (such as
+    /// `refs/heads`), so a leftover file is never mistaken for real data.
+    pub fn new(tmp_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(tmp_dir)
+            .context(format!("Failed to create directory: {}", tmp_dir.display()))?;
+
+        let tmp_path = tmp_dir.join(format!(
+            "tmp_{}_{}",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .context(format!("Failed to create temporary file: {}", tmp_path.display()))?;
+
+        Ok(Self {
+            tmp_path,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Move the completed file to `path`, replacing anything already there
+    pub fn persist(mut self, path: &Path) -> Result<()> {
+        let writer = self.writer.take().expect("temporary file already persisted");
+        let file = writer.into_inner()
+            .map_err(|e| e.into_error())
+            .context(format!("Failed to write {}", path.display()))?;
+
+        let fsync = fsync_enabled();
+        if fsync {
+            file.sync_all()
+                .context(format!("Failed to sync {}", path.display()))?;
+        }
+        drop(file);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        fs::rename(&self.tmp_path, path)
+            .context(format!("Failed to move {} into place", path.display()))?;
+
+        if fsync {
+            sync_dir(path.parent())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.as_mut().expect("temporary file already persisted").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().expect("temporary file already persisted").flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // Not persisted: throw the partial file away
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// Flush a directory entry to disk (so a rename into it is durable)
+#[cfg(unix)]
+fn sync_dir(dir: Option<&Path>) -> Result<()> {
+    if let Some(dir) = dir {
+        File::open(dir)
+            .and_then(|d| d.sync_all())
+            .context(format!("Failed to sync directory: {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Directories can't be opened for syncing on this platform
+#[cfg(not(unix))]
+fn sync_dir(_dir: Option<&Path>) -> Result<()> {
+    Ok(())
+}
+
+/// Write a whole file atomically
+///
+/// # Arguments
+///
+/// * `path` - The final path
+/// * `tmp_dir` - Where to create the temporary file (see [`AtomicFile::new`])
+/// * `contents` - The new file contents
+pub fn write(path: &Path, tmp_dir: &Path, contents: &[u8]) -> Result<()> {
+    let mut file = AtomicFile::new(tmp_dir)?;
+    file.write_all(contents)
+        .context(format!("Failed to write {}", path.display()))?;
+    file.persist(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_replaces_file() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("refs/heads/main");
+
+        write(&path, temp.path(), b"first\n").unwrap();
+        write(&path, temp.path(), b"second\n").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second\n");
+        // Only the refs directory is left; no temporary files
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_dropped_file_is_removed() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("HEAD");
+        fs::write(&path, b"old\n").unwrap();
+
+        let mut file = AtomicFile::new(temp.path()).unwrap();
+        file.write_all(b"partial").unwrap();
+        drop(file);
+
+        assert_eq!(fs::read(&path).unwrap(), b"old\n");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}
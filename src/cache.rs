@@ -0,0 +1,368 @@
+//! # Object Cache
+//!
+//! History walks (`log`, `graph`) and tree walks (`status`, `diff`,
+//! `checkout`) ask for the same commits and trees again and again. Without
+//! a cache every lookup re-opens the object file (or pack), re-inflates it
+//! and re-parses it.
+//!
+//! [`ObjectCache`] keeps the most recently used parsed [`Commit`]s and
+//! [`Tree`]s, up to a fixed number of entries, and evicts the least
+//! recently used one when it is full. Objects are immutable, so a cached
+//! entry never goes stale. Every [`crate::Repository`] owns one; read
+//! through [`crate::Repository::read_commit`] and
+//! [`crate::Repository::read_tree`].
+//!
+//! ## Tracing
+//!
+//! With tracing enabled (`RIT_TRACE_CACHE=1` in the environment, or
+//! [`set_trace`]) every lookup is logged to stderr as a hit or a miss, and
+//! a summary is printed when the cache is dropped:
+//!
+//! ```text
+//! cache: miss commit 3f2a...
+//! cache: hit tree 91bc...
+//! cache: commits 120 hits / 30 misses, trees 870 hits / 45 misses, 0 evictions
+//! ```
+//!
+//! ## Example
+//!
+//! ```
+//! use rit::Repository;
+//! use rit::objects::{Tree, TreeEntry};
+//!
+//! let repo = Repository::in_memory();
+//! let mut tree = Tree::new();
+//! tree.add_entry(TreeEntry::file("a.txt".to_string(), "e".repeat(40)));
+//! let hash = repo.objects.write("tree", &tree.serialize().unwrap()).unwrap();
+//!
+//! repo.read_tree(&hash).unwrap();
+//! repo.read_tree(&hash).unwrap();
+//!
+//! let stats = repo.cache.stats();
+//! assert_eq!((stats.tree_misses, stats.tree_hits), (1, 1));
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use anyhow::Result;
+
+use crate::objects::{Commit, Tree};
+
+/// Default number of parsed objects kept in memory
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// Environment variable that turns on cache tracing
+pub const TRACE_ENV: &str = "RIT_TRACE_CACHE";
+
+/// Trace setting: not decided yet (read the environment)
+const TRACE_UNSET: u8 = 0;
+const TRACE_OFF: u8 = 1;
+const TRACE_ON: u8 = 2;
+
+static TRACE: AtomicU8 = AtomicU8::new(TRACE_UNSET);
+
+/// Turn cache tracing on or off for this process
+///
+/// Overrides the `RIT_TRACE_CACHE` environment variable.
+pub fn set_trace(enabled: bool) {
+    TRACE.store(if enabled { TRACE_ON } else { TRACE_OFF }, Ordering::Relaxed);
+}
+
+/// Check whether cache lookups are logged to stderr
+pub fn trace_enabled() -> bool {
+    match TRACE.load(Ordering::Relaxed) {
+        TRACE_UNSET => {
+            let enabled = std::env::var(TRACE_ENV)
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+                .unwrap_or(false);
+            set_trace(enabled);
+            enabled
+        }
+        setting => setting == TRACE_ON,
+    }
+}
+
+/// Hit and miss counters, per object kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Commit lookups answered from the cache
+    pub commit_hits: u64,
+    /// Commit lookups that had to read the object store
+    pub commit_misses: u64,
+    /// Tree lookups answered from the cache
+    pub tree_hits: u64,
+    /// Tree lookups that had to read the object store
+    pub tree_misses: u64,
+    /// Entries dropped to stay within capacity
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Total number of lookups
+    pub fn lookups(&self) -> u64 {
+        self.commit_hits + self.commit_misses + self.tree_hits + self.tree_misses
+    }
+}
+
+/// A parsed object held by the cache
+#[derive(Debug, Clone)]
+enum Entry {
+    Commit(Arc<Commit>),
+    Tree(Arc<Tree>),
+}
+
+impl Entry {
+    fn kind(&self) -> &'static str {
+        match self {
+            Entry::Commit(_) => "commit",
+            Entry::Tree(_) => "tree",
+        }
+    }
+}
+
+/// LRU bookkeeping, guarded by the cache's mutex
+#[derive(Debug, Default)]
+struct Lru {
+    /// Hash to entry and the tick it was last used at
+    entries: HashMap<String, (Entry, u64)>,
+    /// Last-used tick to hash; the first key is the least recently used
+    order: BTreeMap<u64, String>,
+    /// Monotonic use counter
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl Lru {
+    /// Look up an entry and mark it as most recently used
+    fn get(&mut self, hash: &str) -> Option<Entry> {
+        let tick = self.tick + 1;
+        let (entry, used) = self.entries.get_mut(hash)?;
+        self.order.remove(used);
+        *used = tick;
+        self.order.insert(tick, hash.to_string());
+        self.tick = tick;
+        Some(entry.clone())
+    }
+
+    /// Add an entry, evicting the least recently used ones if needed
+    fn insert(&mut self, hash: &str, entry: Entry, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+
+        if let Some((_, used)) = self.entries.remove(hash) {
+            self.order.remove(&used);
+        }
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.order.pop_first() else { break };
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+
+        self.tick += 1;
+        self.order.insert(self.tick, hash.to_string());
+        self.entries.insert(hash.to_string(), (entry, self.tick));
+    }
+
+    fn record(&mut self, kind: &str, hit: bool) {
+        let counter = match (kind, hit) {
+            ("commit", true) => &mut self.stats.commit_hits,
+            ("commit", false) => &mut self.stats.commit_misses,
+            (_, true) => &mut self.stats.tree_hits,
+            (_, false) => &mut self.stats.tree_misses,
+        };
+        *counter += 1;
+    }
+}
+
+/// A bounded LRU cache of parsed commits and trees, keyed by hash
+#[derive(Debug)]
+pub struct ObjectCache {
+    /// Maximum number of entries (0 disables caching)
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+
+impl Default for ObjectCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ObjectCache {
+    /// Create a cache holding at most `capacity` parsed objects
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Maximum number of entries
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of entries currently cached
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Check whether the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every entry (the counters are kept)
+    pub fn clear(&self) {
+        let mut lru = self.lock();
+        lru.entries.clear();
+        lru.order.clear();
+    }
+
+    /// Hit and miss counters so far
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    /// Get a parsed commit, calling `load` on a miss
+    pub fn commit<F>(&self, hash: &str, load: F) -> Result<Arc<Commit>>
+    where
+        F: FnOnce() -> Result<Commit>,
+    {
+        let entry = self.lookup(hash, "commit", || Ok(Entry::Commit(Arc::new(load()?))))?;
+        match entry {
+            Entry::Commit(commit) => Ok(commit),
+            other => anyhow::bail!("object {} is a {}, not a commit", hash, other.kind()),
+        }
+    }
+
+    /// Get a parsed tree, calling `load` on a miss
+    pub fn tree<F>(&self, hash: &str, load: F) -> Result<Arc<Tree>>
+    where
+        F: FnOnce() -> Result<Tree>,
+    {
+        let entry = self.lookup(hash, "tree", || Ok(Entry::Tree(Arc::new(load()?))))?;
+        match entry {
+            Entry::Tree(tree) => Ok(tree),
+            other => anyhow::bail!("object {} is a {}, not a tree", hash, other.kind()),
+        }
+    }
+
+    /// Shared hit/miss path for [`ObjectCache::commit`] and [`ObjectCache::tree`]
+    fn lookup<F>(&self, hash: &str, kind: &str, load: F) -> Result<Entry>
+    where
+        F: FnOnce() -> Result<Entry>,
+    {
+        let cached = {
+            let mut lru = self.lock();
+            let cached = lru.get(hash);
+            lru.record(kind, cached.is_some());
+            cached
+        };
+
+        if trace_enabled() {
+            let outcome = if cached.is_some() { "hit" } else { "miss" };
+            eprintln!("cache: {} {} {}", outcome, kind, hash);
+        }
+
+        if let Some(entry) = cached {
+            return Ok(entry);
+        }
+
+        // Load without holding the lock; readers of other objects proceed
+        let entry = load()?;
+        self.lock().insert(hash, entry.clone(), self.capacity);
+        Ok(entry)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // A panic while holding the lock can't leave the map inconsistent
+        // in a way that matters for a cache
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for ObjectCache {
+    fn drop(&mut self) {
+        if !trace_enabled() {
+            return;
+        }
+
+        let stats = self.stats();
+        if stats.lookups() > 0 {
+            eprintln!(
+                "cache: commits {} hits / {} misses, trees {} hits / {} misses, {} evictions",
+                stats.commit_hits, stats.commit_misses,
+                stats.tree_hits, stats.tree_misses,
+                stats.evictions
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::commit::Author;
+
+    fn commit(message: &str) -> Commit {
+        let author = Author::with_timestamp("Test", "test@test.com", 1000, "+0000");
+        Commit::simple(&"a".repeat(40), None, author, message)
+    }
+
+    #[test]
+    fn test_hits_and_misses() {
+        let cache = ObjectCache::new(8);
+
+        let first = cache.commit("c1", || Ok(commit("one"))).unwrap();
+        let second = cache.commit("c1", || panic!("should be cached")).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        cache.tree("t1", || Ok(Tree::new())).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!((stats.commit_hits, stats.commit_misses), (1, 1));
+        assert_eq!((stats.tree_hits, stats.tree_misses), (0, 1));
+
+        // Asking for a cached commit as a tree is an error, not a reload
+        assert!(cache.tree("c1", || Ok(Tree::new())).is_err());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = ObjectCache::new(2);
+
+        cache.commit("c1", || Ok(commit("one"))).unwrap();
+        cache.commit("c2", || Ok(commit("two"))).unwrap();
+        // Touch c1 so c2 becomes the oldest
+        cache.commit("c1", || panic!("should be cached")).unwrap();
+        cache.commit("c3", || Ok(commit("three"))).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 1);
+        cache.commit("c1", || panic!("c1 should still be cached")).unwrap();
+
+        let mut reloaded = false;
+        cache.commit("c2", || { reloaded = true; Ok(commit("two")) }).unwrap();
+        assert!(reloaded);
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_cache() {
+        let cache = ObjectCache::new(0);
+        cache.commit("c1", || Ok(commit("one"))).unwrap();
+        cache.commit("c1", || Ok(commit("one"))).unwrap();
+
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().commit_misses, 2);
+    }
+
+    #[test]
+    fn test_load_error_is_not_cached() {
+        let cache = ObjectCache::new(4);
+        assert!(cache.commit("c1", || anyhow::bail!("missing")).is_err());
+        assert!(cache.is_empty());
+    }
+}
@@ -13,33 +13,118 @@
 //!
 //! # Stage all files in current directory
 //! rit add .
+//!
+//! # Stage modifications and deletions of tracked files only
+//! rit add -u
+//!
+//! # Stage every change, including new and deleted files
+//! rit add -A
 //! ```
+//!
+//! Adding a path also stages the deletion of tracked files under it that
+//! no longer exist, so `rit add .` records removals too.
 
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use anyhow::{Context, Result};
 use walkdir::WalkDir;
 
 use crate::Repository;
 use crate::index::{Index, IndexEntry};
-use crate::commands::hash_object;
 use crate::ignore;
+use crate::lfs::{self, LfsAttributes};
+
+/// Options for [`add_paths`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AddOptions {
+    /// Only stage changes to paths already in the index (`-u`)
+    pub update: bool,
+    /// Stage changes in the whole working tree when no paths are given (`-A`)
+    pub all: bool,
+}
+
+/// Check whether a path is covered by a pathspec
+///
+/// A pathspec names a file, or a directory and everything under it (`""`
+/// is the repository root).
+pub fn matches_pathspec(path: &str, pathspec: &str) -> bool {
+    let spec = pathspec.trim_end_matches('/');
+    spec.is_empty()
+        || path == spec
+        || path.strip_prefix(spec).is_some_and(|rest| rest.starts_with('/'))
+}
 
-/// Get file metadata
-fn get_file_metadata(file_path: &Path) -> Result<(u64, u64)> {
+/// Hash and store a file, and record it in the index
+///
+/// Files whose stat data shows them unchanged since they were staged are
+/// not read again.
+fn stage_file(
+    repo: &Repository,
+    index: &mut Index,
+    relative_path: String,
+    file_path: &Path,
+    lfs_attributes: &LfsAttributes,
+) -> Result<()> {
+    // Get file metadata before hashing, so a change made while hashing is seen later
     let metadata = fs::metadata(file_path)
         .context(format!("Failed to get metadata for: {}", file_path.display()))?;
-    
-    let size = metadata.len();
-    let mtime = metadata
-        .modified()
-        .or_else(|_| metadata.created())
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-
-    Ok((size, mtime))
+
+    // Unchanged since it was last staged: nothing to hash
+    if let Some(entry) = index.get_entry(&relative_path) {
+        if !index.needs_rehash(entry, &metadata) {
+            index.refresh_entry(&relative_path, &metadata);
+            return Ok(());
+        }
+    }
+
+    // Hash and store the file (as a pointer, for LFS paths)
+    let blob_hash = lfs::store_file(repo, lfs_attributes, &relative_path, file_path)?;
+
+    // Add to index
+    index.add_entry(IndexEntry::from_metadata(relative_path, blob_hash, &metadata));
+
+    Ok(())
+}
+
+/// Stage modifications and deletions of tracked paths (`add -u`)
+///
+/// Every index path matching a pathspec is re-staged from the working
+/// tree, or removed from the index if its file is gone. Conflicted paths
+/// count as tracked, so this also resolves them. Untracked files are left
+/// alone.
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `index` - The index to update
+/// * `pathspecs` - Paths relative to the repository root (`""` for all)
+/// * `lfs_attributes` - Which paths are stored as LFS pointers
+pub fn update_tracked(
+    repo: &Repository,
+    index: &mut Index,
+    pathspecs: &[String],
+    lfs_attributes: &LfsAttributes,
+) -> Result<()> {
+    let mut tracked: Vec<String> = index.entries.keys()
+        .chain(index.conflicts.keys())
+        .filter(|path| pathspecs.iter().any(|spec| matches_pathspec(path, spec)))
+        .cloned()
+        .collect();
+    tracked.sort();
+    tracked.dedup();
+
+    for path in tracked {
+        let file_path = repo.root.join(&path);
+        match fs::metadata(&file_path) {
+            Ok(metadata) if metadata.is_file() => {
+                stage_file(repo, index, path, &file_path, lfs_attributes)?;
+            }
+            // Deleted, or replaced by a directory
+            _ => index.remove_entry(&path),
+        }
+    }
+
+    Ok(())
 }
 
 /// Add a file to the index
@@ -49,6 +134,7 @@ fn add_file_to_index(
     file_path: &Path,
     repo_root: &Path,
     ignore_rules: &ignore::IgnoreRules,
+    lfs_attributes: &LfsAttributes,
 ) -> Result<()> {
     // Get relative path from repo root
     let relative_path = file_path.strip_prefix(repo_root)
@@ -56,8 +142,8 @@ fn add_file_to_index(
         .to_string_lossy()
         .to_string();
 
-    // Skip .rit directory
-    if relative_path.starts_with(".rit/") {
+    // Skip the repository directory (.rit, or .git for Git repositories)
+    if relative_path.starts_with(".rit/") || relative_path.starts_with(".git/") {
         return Ok(());
     }
     
@@ -71,27 +157,7 @@ fn add_file_to_index(
         return Ok(());
     }
 
-    // Hash and store the file
-    let content = fs::read(file_path)
-        .context(format!("Failed to read file: {}", file_path.display()))?;
-    
-    let blob_hash = hash_object::store_object(repo, "blob", &content)?;
-
-    // Get file metadata
-    let (size, mtime) = get_file_metadata(file_path)?;
-
-    // Create index entry
-    let entry = IndexEntry {
-        path: relative_path,
-        hash: blob_hash,
-        size,
-        mtime,
-    };
-
-    // Add to index
-    index.add_entry(entry);
-
-    Ok(())
+    stage_file(repo, index, relative_path, file_path, lfs_attributes)
 }
 
 /// Add files matching a pattern to the index
@@ -101,10 +167,11 @@ fn add_path(
     path: &Path,
     repo_root: &Path,
     ignore_rules: &ignore::IgnoreRules,
+    lfs_attributes: &LfsAttributes,
 ) -> Result<()> {
     if path.is_file() {
         // Single file
-        add_file_to_index(repo, index, path, repo_root, ignore_rules)?;
+        add_file_to_index(repo, index, path, repo_root, ignore_rules, lfs_attributes)?;
     } else if path.is_dir() {
         // Directory - walk recursively
         for entry in WalkDir::new(path)
@@ -127,7 +194,7 @@ fn add_path(
             }
             
             if entry_path.is_file() {
-                add_file_to_index(repo, index, entry_path, repo_root, ignore_rules)?;
+                add_file_to_index(repo, index, entry_path, repo_root, ignore_rules, lfs_attributes)?;
             }
         }
     } else {
@@ -137,55 +204,94 @@ fn add_path(
     Ok(())
 }
 
+/// Stage the files under some paths
+///
+/// Tracked files under the paths that were deleted are removed from the
+/// index. Unless `options.update` is set, new files are added too,
+/// following `.ritignore`.
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `pathspecs` - Paths relative to the repository root (`""` for all)
+/// * `options` - `-u` and `-A`
+pub fn add_paths(repo: &Repository, pathspecs: &[String], options: AddOptions) -> Result<()> {
+    // Lock and load the existing index
+    let (mut index, lock) = Index::load_for_update(&repo.index_path(), repo.object_format())?;
+
+    // Load ignore rules
+    let ignore_rules = ignore::load_ignore_rules(&repo.root)?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
+
+    // Check every path before changing anything
+    for pathspec in pathspecs {
+        let exists = repo.root.join(pathspec).exists();
+        let tracked = index.entries.keys()
+            .chain(index.conflicts.keys())
+            .any(|path| matches_pathspec(path, pathspec));
+        if !exists && !tracked {
+            anyhow::bail!("fatal: pathspec '{}' did not match any files", pathspec);
+        }
+    }
+
+    // Modified and deleted tracked files
+    update_tracked(repo, &mut index, pathspecs, &lfs_attributes)?;
+
+    // New files
+    if !options.update {
+        for pathspec in pathspecs {
+            let path = repo.root.join(pathspec);
+            if path.exists() {
+                add_path(repo, &mut index, &path, &repo.root, &ignore_rules, &lfs_attributes)?;
+            }
+        }
+    }
+
+    // Save updated index
+    index.save_locked(lock)?;
+
+    Ok(())
+}
+
 /// Execute the add command
 ///
 /// # Arguments
 ///
-/// * `paths` - Paths to files or directories to stage
+/// * `paths` - Paths to files or directories to stage (the whole working
+///   tree if empty and `-u` or `-A` is given)
+/// * `options` - `-u` and `-A`
 ///
 /// # Example
 ///
 /// ```no_run
-/// use rit::commands::add::run;
+/// use rit::commands::add::{run, AddOptions};
 ///
 /// // Stage a file
-/// run(vec!["file.txt".to_string()]).unwrap();
+/// run(vec!["file.txt".to_string()], AddOptions::default()).unwrap();
 ///
 /// // Stage current directory
-/// run(vec![".".to_string()]).unwrap();
+/// run(vec![".".to_string()], AddOptions::default()).unwrap();
+///
+/// // Stage changes to tracked files everywhere
+/// run(vec![], AddOptions { update: true, ..AddOptions::default() }).unwrap();
 /// ```
-pub fn run(paths: Vec<String>) -> Result<()> {
+pub fn run(paths: Vec<String>, options: AddOptions) -> Result<()> {
     let repo = Repository::find()?;
-    let repo_root = repo.root.clone();
-    let index_path = repo.index_path();
 
-    // Load existing index
-    let mut index = Index::load(&index_path)?;
-    
-    // Load ignore rules
-    let ignore_rules = ignore::load_ignore_rules(&repo_root)?;
-
-    // Add each path
-    for path_str in paths {
-        let path = PathBuf::from(&path_str);
-        
-        // Resolve relative to current directory
-        let full_path = if path.is_absolute() {
-            path
-        } else {
-            std::env::current_dir()?
-                .join(&path)
-                .canonicalize()
-                .context(format!("Path does not exist: {}", path_str))?
-        };
-
-        add_path(&repo, &mut index, &full_path, &repo_root, &ignore_rules)?;
+    if paths.is_empty() && !options.update && !options.all {
+        anyhow::bail!("Nothing specified, nothing added.\nhint: Maybe you wanted to say 'rit add .'?");
     }
 
-    // Save updated index
-    index.save(&index_path)?;
+    // Resolve relative to current directory
+    let pathspecs = if paths.is_empty() {
+        vec![String::new()]
+    } else {
+        paths.iter()
+            .map(|path| repo.relative_path(Path::new(path)))
+            .collect::<Result<Vec<_>>>()?
+    };
 
-    Ok(())
+    add_paths(&repo, &pathspecs, options)
 }
 
 #[cfg(test)]
@@ -193,6 +299,7 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     use crate::Repository;
+    use crate::hash::ObjectFormat;
 
     #[test]
     fn test_add_file() {
@@ -206,14 +313,72 @@ mod tests {
         std::env::set_current_dir(temp.path()).unwrap();
 
         // Add file
-        run(vec!["test.txt".to_string()]).unwrap();
+        run(vec!["test.txt".to_string()], AddOptions::default()).unwrap();
 
         // Verify index was updated
-        let index = Index::load(&repo.index_path()).unwrap();
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
         assert!(index.contains("test.txt"));
         assert_eq!(index.get_entry("test.txt").unwrap().size, 12);
 
         std::env::set_current_dir("/").unwrap();
     }
-}
 
+    fn staged_paths(repo: &Repository) -> Vec<String> {
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        let mut paths: Vec<String> = index.entries.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_add_update_and_all() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::create_dir(temp.path().join("dir")).unwrap();
+        for path in ["keep.txt", "gone.txt", "dir/edit.txt", "dir/gone.txt"] {
+            fs::write(temp.path().join(path), path).unwrap();
+        }
+        let all = AddOptions { all: true, ..AddOptions::default() };
+        add_paths(&repo, &["".to_string()], all).unwrap();
+
+        fs::remove_file(temp.path().join("gone.txt")).unwrap();
+        fs::remove_file(temp.path().join("dir/gone.txt")).unwrap();
+        fs::write(temp.path().join("dir/edit.txt"), "edited").unwrap();
+        fs::write(temp.path().join("new.txt"), "new").unwrap();
+        fs::write(temp.path().join(".ritignore"), "*.log\n").unwrap();
+        fs::write(temp.path().join("debug.log"), "log").unwrap();
+
+        // A pathspec limits -u to one directory
+        let update = AddOptions { update: true, ..AddOptions::default() };
+        add_paths(&repo, &["dir".to_string()], update).unwrap();
+        assert_eq!(staged_paths(&repo), vec!["dir/edit.txt", "gone.txt", "keep.txt"]);
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(index.get_entry("dir/edit.txt").unwrap().size, 6);
+
+        // Adding a deleted path stages its removal
+        add_paths(&repo, &["gone.txt".to_string()], AddOptions::default()).unwrap();
+        assert_eq!(staged_paths(&repo), vec!["dir/edit.txt", "keep.txt"]);
+        assert!(add_paths(&repo, &["gone.txt".to_string()], AddOptions::default()).is_err());
+
+        // -A picks up new files, except ignored ones
+        add_paths(&repo, &["".to_string()], all).unwrap();
+        assert_eq!(staged_paths(&repo), vec!["dir/edit.txt", "keep.txt", "new.txt"]);
+    }
+
+    #[test]
+    fn test_sha256_index_starts_empty() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init_with_format(temp.path(), ObjectFormat::Sha256).unwrap();
+
+        // `add -u` with nothing tracked writes an empty index
+        let update = AddOptions { update: true, ..AddOptions::default() };
+        add_paths(&repo, &["".to_string()], update).unwrap();
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(index.format, ObjectFormat::Sha256);
+
+        fs::write(temp.path().join("file.txt"), "content").unwrap();
+        add_paths(&repo, &["file.txt".to_string()], AddOptions::default()).unwrap();
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(index.get_entry("file.txt").unwrap().hash.len(), 64);
+    }
+}
@@ -0,0 +1,486 @@
+//! # Interactive Staging (`add -p`)
+//!
+//! Walk the hunks between each file's staged content and its working copy,
+//! asking which to stage. Only the accepted hunks go into the blob written
+//! to the index, so part of a file's changes can be committed while the
+//! rest stay in the working tree.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Choose hunks from every modified file
+//! rit add -p
+//!
+//! # Only look at some paths
+//! rit add -p src/ README.md
+//! ```
+//!
+//! ## Answers
+//!
+//! | Key | Meaning |
+//! |-----|---------|
+//! | `y` | Stage this hunk |
+//! | `n` | Do not stage this hunk |
+//! | `q` | Quit; do not stage this hunk or any remaining ones |
+//! | `s` | Split the hunk into smaller hunks |
+//! | `e` | Edit the hunk by hand in `$RIT_EDITOR`, `$VISUAL` or `$EDITOR` |
+//! | `?` | Print help |
+//!
+//! Answers are read line by line from stdin, so the session can be
+//! scripted: `printf 'y\nn\n' | rit add -p`. End of input counts as `q`.
+//! Deleted, binary and LFS-tracked files are left for plain `rit add`.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::Command;
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::commands::{add, cat_file};
+use crate::commands::diff::{group_hunks, myers_diff, Edit, Hunk};
+use crate::index::{Index, IndexEntry};
+use crate::lfs::LfsAttributes;
+
+/// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// File the hunk is written to for `e`, inside the repository directory
+const EDIT_FILE: &str = "ADD_EDIT.patch";
+
+/// Help printed for `?` and unknown answers
+const HELP: &str = "\
+y - stage this hunk
+n - do not stage this hunk
+q - quit; do not stage this hunk or any of the remaining ones
+s - split the current hunk into smaller hunks
+e - manually edit the current hunk
+? - print help
+";
+
+/// Guide appended to the hunk in the editor
+const EDIT_GUIDE: &str = "\
+# ---
+# To remove '-' lines, make them ' ' lines (context).
+# To remove '+' lines, delete them.
+# Lines starting with # will be removed.
+# If the edited hunk does not apply, you will be asked again.
+";
+
+/// What to do with one hunk
+enum Decision {
+    /// Stage the hunk as shown
+    Accept,
+    /// Stage the hunk as edited by hand
+    Edited(Vec<Edit>),
+}
+
+/// Split content into lines, keeping each line's `\n`
+///
+/// Keeping the line endings means a missing newline at the end of the file
+/// shows up as a change, and the staged content can be rebuilt byte for
+/// byte.
+fn split_lines_inclusive(content: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(content)
+        .split_inclusive('\n')
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Check whether content can't be shown as lines of text
+fn is_binary(content: &[u8]) -> bool {
+    content.contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+/// Format one line of a hunk, with its `+`, `-` or ` ` prefix
+fn format_line(prefix: char, line: &str) -> String {
+    match line.strip_suffix('\n') {
+        Some(line) => format!("{}{}\n", prefix, line),
+        None => format!("{}{}\n\\ No newline at end of file\n", prefix, line),
+    }
+}
+
+/// Format a list of edits as the body of a hunk
+fn format_edits(edits: &[Edit]) -> String {
+    edits.iter()
+        .map(|edit| match edit {
+            Edit::Keep(line) => format_line(' ', line),
+            Edit::Delete(line) => format_line('-', line),
+            Edit::Insert(line) => format_line('+', line),
+        })
+        .collect()
+}
+
+/// The old (`keep_insert = false`) or new side of some edits
+fn side(edits: &[Edit], keep_insert: bool) -> impl Iterator<Item = &String> {
+    edits.iter().filter_map(move |edit| match edit {
+        Edit::Keep(line) => Some(line),
+        Edit::Delete(line) if !keep_insert => Some(line),
+        Edit::Insert(line) if keep_insert => Some(line),
+        _ => None,
+    })
+}
+
+/// Parse a hunk edited by hand
+///
+/// # Arguments
+///
+/// * `text` - The edited hunk, as saved by the editor
+/// * `original` - The hunk's edits before editing
+///
+/// # Returns
+///
+/// The new edits, or `None` if the hunk was emptied or no longer applies
+/// (its context and `-` lines must still match the staged content).
+fn parse_edited_hunk(text: &str, original: &[Edit]) -> Option<Vec<Edit>> {
+    let mut edits: Vec<Edit> = Vec::new();
+
+    for line in text.split_inclusive('\n') {
+        let (prefix, rest) = match line.chars().next() {
+            Some(c) => (c, &line[c.len_utf8()..]),
+            None => continue,
+        };
+        match prefix {
+            ' ' => edits.push(Edit::Keep(rest.to_string())),
+            '-' => edits.push(Edit::Delete(rest.to_string())),
+            '+' => edits.push(Edit::Insert(rest.to_string())),
+            // Editors often strip the space from blank context lines
+            '\n' => edits.push(Edit::Keep("\n".to_string())),
+            '\\' => {
+                // "\ No newline at end of file" applies to the line above
+                if let Some(Edit::Keep(l) | Edit::Delete(l) | Edit::Insert(l)) = edits.last_mut() {
+                    if l.ends_with('\n') {
+                        l.pop();
+                    }
+                }
+            }
+            _ => {} // '#' comments and the @@ header
+        }
+    }
+
+    if edits.is_empty() || !side(&edits, false).eq(side(original, false)) {
+        return None;
+    }
+    Some(edits)
+}
+
+/// Open the hunk in the user's editor and read back the result
+fn edit_hunk(repo: &Repository, header: &str, edits: &[Edit]) -> Result<Option<Vec<Edit>>> {
+    let edit_path = repo.rit_dir.join(EDIT_FILE);
+    let text = format!(
+        "# Manual hunk edit mode -- see bottom for a quick guide.\n{}\n{}{}",
+        header,
+        format_edits(edits),
+        EDIT_GUIDE
+    );
+    fs::write(&edit_path, text)
+        .context(format!("Failed to write {}", edit_path.display()))?;
+
+    let editor = ["RIT_EDITOR", "VISUAL", "EDITOR"].iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+
+    // Run through the shell so editors with arguments (`code --wait`) work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(&edit_path)
+        .status()
+        .context(format!("Failed to run editor '{}'", editor))?;
+    if !status.success() {
+        let _ = fs::remove_file(&edit_path);
+        anyhow::bail!("fatal: there was a problem with the editor '{}'", editor);
+    }
+
+    let edited = fs::read_to_string(&edit_path)
+        .context(format!("Failed to read {}", edit_path.display()))?;
+    let _ = fs::remove_file(&edit_path);
+    Ok(parse_edited_hunk(&edited, edits))
+}
+
+/// Rebuild the content to stage from the accepted hunks
+///
+/// Lines outside the accepted hunks keep their staged (old) version.
+fn apply_hunks(edits: &[Edit], accepted: &[(Hunk, Decision)]) -> Vec<u8> {
+    let mut content = String::new();
+    let mut position = 0;
+
+    for (hunk, decision) in accepted {
+        content.extend(side(&edits[position..hunk.range.start], false).map(String::as_str));
+        match decision {
+            Decision::Accept => content.extend(side(&edits[hunk.range.clone()], true).map(String::as_str)),
+            Decision::Edited(edited) => content.extend(side(edited, true).map(String::as_str)),
+        }
+        position = hunk.range.end;
+    }
+    content.extend(side(&edits[position..], false).map(String::as_str));
+
+    content.into_bytes()
+}
+
+/// Ask about each hunk of one file
+///
+/// # Returns
+///
+/// The accepted hunks in file order, and whether the user quit
+fn select_hunks<R: BufRead, W: Write>(
+    repo: &Repository,
+    path: &str,
+    edits: &[Edit],
+    input: &mut R,
+    output: &mut W,
+) -> Result<(Vec<(Hunk, Decision)>, bool)> {
+    let mut hunks = group_hunks(edits, CONTEXT_LINES);
+    let mut accepted = Vec::new();
+    let mut i = 0;
+
+    writeln!(output, "diff --git a/{} b/{}", path, path)?;
+    writeln!(output, "--- a/{}", path)?;
+    writeln!(output, "+++ b/{}", path)?;
+
+    while i < hunks.len() {
+        let hunk = &hunks[i];
+        let header = hunk.header(edits);
+        let pieces = hunk.split(edits);
+        let options = if pieces.len() > 1 { "y,n,q,s,e,?" } else { "y,n,q,e,?" };
+
+        write!(output, "{}\n{}", header, format_edits(&edits[hunk.range.clone()]))?;
+        write!(output, "({}/{}) Stage this hunk [{}]? ", i + 1, hunks.len(), options)?;
+        output.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(output)?;
+            return Ok((accepted, true));
+        }
+
+        match answer.trim().chars().next() {
+            Some('y') => {
+                accepted.push((hunk.clone(), Decision::Accept));
+                i += 1;
+            }
+            Some('n') => i += 1,
+            Some('q') => return Ok((accepted, true)),
+            Some('s') if pieces.len() > 1 => {
+                writeln!(output, "Split into {} hunks.", pieces.len())?;
+                hunks.splice(i..=i, pieces);
+            }
+            Some('e') => match edit_hunk(repo, &header, &edits[hunk.range.clone()])? {
+                Some(edited) => {
+                    accepted.push((hunk.clone(), Decision::Edited(edited)));
+                    i += 1;
+                }
+                None => writeln!(output, "Your edited hunk does not apply.")?,
+            },
+            _ => write!(output, "{}", HELP)?,
+        }
+    }
+
+    Ok((accepted, false))
+}
+
+/// Interactively stage hunks of tracked files
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `pathspecs` - Paths relative to the repository root (`""` for all)
+/// * `input` - Where answers are read from, one per line
+/// * `output` - Where hunks and prompts are written
+///
+/// # Returns
+///
+/// The paths whose staged content changed
+pub fn add_patch<R: BufRead, W: Write>(
+    repo: &Repository,
+    pathspecs: &[String],
+    input: &mut R,
+    output: &mut W,
+) -> Result<Vec<String>> {
+    let (mut index, lock) = Index::load_for_update(&repo.index_path(), repo.object_format())?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
+
+    let mut paths: Vec<String> = index.entries.keys()
+        .filter(|path| pathspecs.iter().any(|spec| add::matches_pathspec(path, spec)))
+        .filter(|path| !lfs_attributes.is_tracked(path))
+        .cloned()
+        .collect();
+    paths.sort();
+
+    let mut staged = Vec::new();
+    for path in paths {
+        let entry = index.get_entry(&path).expect("path comes from the index");
+        let file_path = repo.root.join(&path);
+        let Ok(metadata) = fs::symlink_metadata(&file_path) else {
+            continue; // Deleted: stage with `rit rm`
+        };
+        if !metadata.is_file() || !index.needs_rehash(entry, &metadata) {
+            continue;
+        }
+
+        let new_content = fs::read(&file_path)
+            .context(format!("Failed to read file: {}", path))?;
+        let old_content = cat_file::read_object(repo, &entry.hash)?.content;
+        if old_content == new_content || is_binary(&old_content) || is_binary(&new_content) {
+            continue;
+        }
+
+        let old_lines = split_lines_inclusive(&old_content);
+        let new_lines = split_lines_inclusive(&new_content);
+        let edits = myers_diff(&old_lines, &new_lines);
+
+        let (accepted, quit) = select_hunks(repo, &path, &edits, input, output)?;
+        if !accepted.is_empty() {
+            let content = apply_hunks(&edits, &accepted);
+            let hash = repo.objects.write("blob", &content)?;
+            // No stat data: the working file now differs from the index,
+            // so status must hash it rather than trust its stat
+            let mode = entry.mode;
+            index.add_entry(IndexEntry { path: path.clone(), hash, mode, ..IndexEntry::default() });
+            staged.push(path);
+        }
+        if quit {
+            break;
+        }
+    }
+
+    index.save_locked(lock)?;
+    Ok(staged)
+}
+
+/// Execute `add -p`, reading answers from stdin
+///
+/// # Arguments
+///
+/// * `paths` - Files or directories to look at, relative to the current
+///   directory (all tracked files if empty)
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::add_patch::run;
+///
+/// run(vec!["src".to_string()]).unwrap();
+/// ```
+pub fn run(paths: Vec<String>) -> Result<()> {
+    let repo = Repository::find()?;
+
+    // No paths means the whole working tree, as with `add -u`
+    let pathspecs = if paths.is_empty() {
+        vec![String::new()]
+    } else {
+        paths.iter()
+            .map(|path| repo.relative_path(Path::new(path)))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    add_patch(&repo, &pathspecs, &mut stdin.lock(), &mut stdout.lock())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    /// Commit-free setup: stage `old` for `path`, then write `new` to disk
+    fn setup(old: &str, new: &str) -> (tempfile::TempDir, Repository) {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let file_path = temp.path().join("file.txt");
+
+        let hash = repo.objects.write("blob", old.as_bytes()).unwrap();
+        let mut index = Index::new(repo.object_format());
+        index.add_entry(IndexEntry { path: "file.txt".to_string(), hash, mode: 0o100644, ..IndexEntry::default() });
+        index.save(&repo.index_path()).unwrap();
+
+        fs::write(&file_path, new).unwrap();
+        (temp, repo)
+    }
+
+    fn staged_content(repo: &Repository) -> String {
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        let hash = &index.get_entry("file.txt").unwrap().hash;
+        String::from_utf8(cat_file::read_object(repo, hash).unwrap().content).unwrap()
+    }
+
+    fn answer(repo: &Repository, answers: &str) -> (Vec<String>, String) {
+        let mut output = Vec::new();
+        let staged = add_patch(repo, &["".to_string()], &mut Cursor::new(answers), &mut output).unwrap();
+        (staged, String::from_utf8(output).unwrap())
+    }
+
+    const OLD: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    const NEW: &str = "1\nTWO\n3\n4\n5\n6\n7\nEIGHT\n9\n10\n";
+
+    #[test]
+    fn test_add_patch_stages_chosen_hunks() {
+        let (_temp, repo) = setup(OLD, NEW);
+
+        // One hunk (the changes are within 2 * 3 lines), split, take the second half
+        let (staged, output) = answer(&repo, "s\nn\ny\n");
+        assert!(output.contains("@@ -1,10 +1,10 @@"));
+        assert!(output.contains("(1/1) Stage this hunk [y,n,q,s,e,?]? "));
+        assert!(output.contains("Split into 2 hunks."));
+        assert!(output.contains("(2/2) Stage this hunk [y,n,q,e,?]? "));
+        assert_eq!(staged, vec!["file.txt"]);
+        assert_eq!(staged_content(&repo), "1\n2\n3\n4\n5\n6\n7\nEIGHT\n9\n10\n");
+
+        // Only the first change is left; end of input stages nothing
+        let (staged, output) = answer(&repo, "");
+        assert!(output.contains("-2\n+TWO\n"));
+        assert!(!output.contains("EIGHT"));
+        assert!(staged.is_empty());
+
+        let (_, _) = answer(&repo, "y\n");
+        assert_eq!(staged_content(&repo), NEW);
+
+        // Nothing left to stage: no prompt at all
+        let (_, output) = answer(&repo, "y\n");
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_add_patch_quit_and_help() {
+        let (_temp, repo) = setup(OLD, NEW);
+
+        let (staged, output) = answer(&repo, "x\nq\n");
+        assert!(output.contains("s - split the current hunk"));
+        assert!(staged.is_empty());
+        assert_eq!(staged_content(&repo), OLD);
+
+        // A file without a final newline round-trips
+        let (_temp, repo) = setup("a\nb", "a\nc");
+        let (_, output) = answer(&repo, "y\n");
+        assert!(output.contains("-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"));
+        assert_eq!(staged_content(&repo), "a\nc");
+    }
+
+    #[test]
+    fn test_parse_edited_hunk() {
+        let original = vec![
+            Edit::Keep("a\n".to_string()),
+            Edit::Delete("b\n".to_string()),
+            Edit::Insert("B\n".to_string()),
+            Edit::Insert("extra\n".to_string()),
+        ];
+
+        // Drop one '+' line and change the other
+        let text = "# comment\n@@ -1,2 +1,3 @@\n a\n-b\n+Bee\n# ---\n";
+        let edited = parse_edited_hunk(text, &original).unwrap();
+        assert_eq!(side(&edited, true).cloned().collect::<Vec<_>>(), vec!["a\n", "Bee\n"]);
+
+        // Turning '-' into context keeps the line
+        let edited = parse_edited_hunk(" a\n b\n+B\n", &original).unwrap();
+        assert_eq!(side(&edited, true).cloned().collect::<Vec<_>>(), vec!["a\n", "b\n", "B\n"]);
+
+        // Removing a '-' line changes the old side, so it no longer applies
+        assert!(parse_edited_hunk(" a\n+B\n", &original).is_none());
+        assert!(parse_edited_hunk("# everything deleted\n", &original).is_none());
+    }
+}
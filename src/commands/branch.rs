@@ -20,6 +20,9 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 
 use crate::Repository;
+use crate::refs;
+use crate::commit_graph;
+use crate::atomic;
 use crate::commands::log;
 
 /// Get the current branch name from HEAD
@@ -56,37 +59,22 @@ fn branch_ref_path(repo: &Repository, branch_name: &str) -> PathBuf {
 
 /// List all branches
 fn list_branches(repo: &Repository) -> Result<()> {
-    let heads_dir = repo.rit_dir.join("refs").join("heads");
-    
-    if !heads_dir.exists() {
-        // No branches directory, just show current branch if any
-        if let Some(current) = get_current_branch(repo)? {
+    let current_branch = get_current_branch(repo)?;
+
+    // Loose and packed branches, sorted by name
+    let branches: Vec<String> = refs::list_refs(repo, "refs/heads/")?
+        .into_iter()
+        .map(|(name, _)| name.trim_start_matches("refs/heads/").to_string())
+        .collect();
+
+    if branches.is_empty() {
+        // No commits yet, just show current branch if any
+        if let Some(current) = current_branch {
             println!("* {}", current);
         }
         return Ok(());
     }
 
-    let current_branch = get_current_branch(repo)?;
-    let mut branches = Vec::new();
-
-    // Read all branch files
-    if heads_dir.is_dir() {
-        for entry in fs::read_dir(&heads_dir)
-            .context("Failed to read refs/heads directory")? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() {
-                if let Some(branch_name) = path.file_name().and_then(|n| n.to_str()) {
-                    branches.push(branch_name.to_string());
-                }
-            }
-        }
-    }
-
-    // Sort branches
-    branches.sort();
-
     // Print branches, marking current one with *
     for branch in branches {
         if current_branch.as_ref().map(|b| b == &branch).unwrap_or(false) {
@@ -116,7 +104,7 @@ fn create_branch(repo: &Repository, branch_name: &str) -> Result<()> {
     let branch_path = branch_ref_path(repo, branch_name);
 
     // Check if branch already exists
-    if branch_path.exists() {
+    if refs::ref_exists(repo, &format!("refs/heads/{}", branch_name))? {
         anyhow::bail!("fatal: a branch named '{}' already exists", branch_name);
     }
 
@@ -129,7 +117,7 @@ fn create_branch(repo: &Repository, branch_name: &str) -> Result<()> {
     };
 
     // Create the branch file
-    fs::write(&branch_path, format!("{}\n", current_commit))
+    atomic::write(&branch_path, &repo.rit_dir, format!("{}\n", current_commit).as_bytes())
         .context(format!("Failed to create branch file: {}", branch_path.display()))?;
 
     println!("Created branch '{}'", branch_name);
@@ -138,46 +126,34 @@ fn create_branch(repo: &Repository, branch_name: &str) -> Result<()> {
 
 /// Delete a branch
 fn delete_branch(repo: &Repository, branch_name: &str, force: bool) -> Result<()> {
-    let branch_path = branch_ref_path(repo, branch_name);
+    let ref_name = format!("refs/heads/{}", branch_name);
 
-    if !branch_path.exists() {
-        anyhow::bail!("error: branch '{}' not found", branch_name);
-    }
+    // Read the branch commit
+    let branch_commit = match refs::read_ref(repo, &ref_name)? {
+        Some(hash) => hash,
+        None => anyhow::bail!("error: branch '{}' not found", branch_name),
+    };
 
     // Check if trying to delete current branch
     if let Some(current) = get_current_branch(repo)? {
-        if current == branch_name {
-            if !force {
-                anyhow::bail!("error: Cannot delete branch '{}' checked out at '{}'", branch_name, repo.rit_dir.join("HEAD").display());
-            }
+        if current == branch_name && !force {
+            anyhow::bail!("error: Cannot delete branch '{}' checked out at '{}'", branch_name, repo.rit_dir.join("HEAD").display());
         }
     }
 
-    // Read the branch commit
-    let branch_commit = fs::read_to_string(&branch_path)
-        .context("Failed to read branch file")?
-        .trim()
-        .to_string();
-
     // Get current HEAD commit
     let current_commit = log::read_head(repo)?;
 
-    // Check if branch is merged (points to same commit as HEAD)
-    // If it points to the same commit, it IS merged, so we can delete it
-    // If it points to a different commit, it's NOT merged
+    // The branch is merged if its tip is reachable from HEAD (the
+    // commit-graph's generation numbers keep this walk short)
     if let Some(current) = current_commit {
-        if branch_commit != current {
-            // Branch points to different commit - not merged
-            if !force {
-                anyhow::bail!("error: The branch '{}' is not fully merged.\nIf you are sure you want to delete it, run 'rit branch -D {}'.", branch_name, branch_name);
-            }
+        if !force && !commit_graph::is_ancestor(repo, &branch_commit, &current)? {
+            anyhow::bail!("error: The branch '{}' is not fully merged.\nIf you are sure you want to delete it, run 'rit branch -D {}'.", branch_name, branch_name);
         }
-        // If branch_commit == current, it's merged, so we can delete it
     }
 
-    // Delete the branch file
-    fs::remove_file(&branch_path)
-        .context(format!("Failed to delete branch file: {}", branch_path.display()))?;
+    // Delete the branch (loose file and any packed-refs entry)
+    refs::delete_ref(repo, &ref_name)?;
 
     println!("Deleted branch '{}' (was {})", branch_name, &branch_commit[..7.min(branch_commit.len())]);
     Ok(())
@@ -228,6 +204,7 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     use crate::Repository;
+    use crate::commands::hash_object;
 
     #[test]
     fn test_list_branches_empty() {
@@ -294,6 +271,35 @@ mod tests {
         assert!(!branch_path.exists());
     }
 
+    #[test]
+    fn test_delete_merged_branch_checks_ancestry() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let commit = |parent: Option<&str>, message: &str| {
+            let parent = parent.map(|p| format!("parent {}\n", p)).unwrap_or_default();
+            let content = format!(
+                "tree {}\n{}author T <t@example.com> 0 +0000\ncommitter T <t@example.com> 0 +0000\n\n{}\n",
+                "b".repeat(40), parent, message
+            );
+            hash_object::store_object(&repo, "commit", content.as_bytes()).unwrap()
+        };
+        let base = commit(None, "base");
+        let main = commit(Some(&base), "main");
+        let side = commit(Some(&base), "side");
+
+        std::fs::write(repo.rit_dir.join("refs/heads/main"), &main).unwrap();
+        std::fs::write(repo.rit_dir.join("refs/heads/old"), &base).unwrap();
+        std::fs::write(repo.rit_dir.join("refs/heads/side"), &side).unwrap();
+        std::fs::write(repo.head_path(), "ref: refs/heads/main\n").unwrap();
+        crate::commit_graph::write(&repo).unwrap();
+
+        // An ancestor of HEAD is merged; a diverged branch needs -D
+        delete_branch(&repo, "old", false).unwrap();
+        assert!(delete_branch(&repo, "side", false).is_err());
+        delete_branch(&repo, "side", true).unwrap();
+    }
+
     #[test]
     fn test_get_current_branch() {
         let temp = tempdir().unwrap();
@@ -10,6 +10,33 @@
 //! 4. Parse header to get type and size
 //! 5. Return the content
 //!
+//! If there is no loose file, the packfiles in `.rit/objects/pack/` are
+//! searched instead (see [`crate::pack`]). Reads go through the
+//! repository's [`crate::objects::ObjectStore`].
+//!
+//! Abbreviated hashes (4 or more hex characters) are expanded by
+//! [`resolve_object`], which fails with a list of candidates when the
+//! prefix is ambiguous.
+//!
+//! Revision expressions like `HEAD`, `main`, `HEAD:src/main.rs` or
+//! `:README.md` (the staged version) are handled by [`resolve_revision`].
+//!
+//! ## Batch mode
+//!
+//! `--batch` and `--batch-check` read one object name per line from stdin
+//! and answer each on stdout, so a script can look up thousands of objects
+//! with a single process (see [`batch`]):
+//!
+//! ```text
+//! <oid> <type> <size>
+//! <contents>            (--batch only, followed by a newline)
+//! ```
+//!
+//! Names that don't resolve are answered with `<name> missing` (or
+//! `<name> ambiguous`). The header can be changed with a format string
+//! using `%(objectname)`, `%(objecttype)`, `%(objectsize)` and `%(rest)`
+//! (whatever follows the name on the input line).
+//!
 //! ## Usage
 //!
 //! ```bash
@@ -21,23 +48,89 @@
 //!
 //! # Show object size
 //! rit cat-file -s abc123def456...
+//!
+//! # Check that a file exists at a revision (exit status only)
+//! rit cat-file -e HEAD:src/main.rs
+//!
+//! # Read a file as of a tag
+//! rit cat-file -p v1.0:README.md
+//!
+//! # Headers and contents for many objects
+//! git rev-list --all --objects | cut -d' ' -f1 | rit cat-file --batch
+//!
+//! # Just the sizes
+//! rit cat-file --batch-check='%(objectsize) %(objectname)' < hashes.txt
 //! ```
 
-use std::io::Read;
+use std::io::{self, BufRead, Write};
 use anyhow::{Context, Result};
-use flate2::read::ZlibDecoder;
 
 use crate::Repository;
+use crate::commands::log;
+use crate::index::Index;
+use crate::objects::{Tag, Tree};
+use crate::refs;
+
+pub use crate::objects::store::GitObject;
+
+/// Minimum length of an abbreviated object ID
+const MIN_ABBREV_LEN: usize = 4;
+
+/// Header written for each object by `--batch` and `--batch-check`
+pub const DEFAULT_BATCH_FORMAT: &str = "%(objectname) %(objecttype) %(objectsize)";
+
+/// Expand a full or abbreviated object ID to a full hash
+///
+/// Candidates come from [`crate::objects::ObjectStore::find_prefix`]; for
+/// the on-disk store that scans the loose fan-out directory for the first
+/// two characters and the index of every pack.
+///
+/// # Errors
+///
+/// Fails if the name is not hex, too short, matches no object, or matches
+/// more than one object (the error lists each candidate and its type).
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::Repository;
+/// use rit::commands::cat_file::resolve_object;
+///
+/// let repo = Repository::find().unwrap();
+/// let hash = resolve_object(&repo, "d670460").unwrap();
+/// assert_eq!(hash.len(), repo.object_format().hex_len());
+/// ```
+pub fn resolve_object(repo: &Repository, name: &str) -> Result<String> {
+    let prefix = name.to_lowercase();
+    let full_len = repo.object_format().hex_len();
+
+    if prefix.len() < MIN_ABBREV_LEN
+        || prefix.len() > full_len
+        || !prefix.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        anyhow::bail!("fatal: not a valid object name: {}", name);
+    }
+
+    if prefix.len() == full_len {
+        return Ok(prefix);
+    }
+
+    let mut candidates = repo.objects.find_prefix(&prefix)?;
 
-/// Represents a parsed git object
-#[derive(Debug)]
-pub struct GitObject {
-    /// The type of object ("blob", "tree", "commit", "tag")
-    pub object_type: String,
-    /// The size of the content in bytes
-    pub size: usize,
-    /// The raw content (after header)
-    pub content: Vec<u8>,
+    match candidates.len() {
+        0 => anyhow::bail!("fatal: not a valid object name: {}", name),
+        1 => Ok(candidates.remove(0)),
+        _ => {
+            let mut message = format!("error: short object ID {} is ambiguous\nhint: The candidates are:", name);
+            for hash in &candidates {
+                let object_type = read_object(repo, hash)
+                    .map(|o| o.object_type)
+                    .unwrap_or_else(|_| "unknown".to_string());
+                message.push_str(&format!("\nhint:   {} {}", hash, object_type));
+            }
+            anyhow::bail!(message);
+        }
+    }
 }
 
 /// Read and parse an object from the repository
@@ -62,108 +155,419 @@ pub struct GitObject {
 /// println!("Type: {}, Size: {}", obj.object_type, obj.size);
 /// ```
 pub fn read_object(repo: &Repository, hash: &str) -> Result<GitObject> {
-    // Validate hash length
-    if hash.len() < 4 {
-        anyhow::bail!("hash too short: {}", hash);
+    // Expand abbreviated hashes
+    if hash.len() == repo.object_format().hex_len() {
+        repo.objects.read(hash)
+    } else {
+        repo.objects.read(&resolve_object(repo, hash)?)
     }
+}
 
-    // Find object file
-    let object_path = repo.objects_dir().join(&hash[..2]).join(&hash[2..]);
+/// One piece of a batch format string
+#[derive(Debug, Clone, PartialEq)]
+enum BatchAtom {
+    Literal(String),
+    ObjectName,
+    ObjectType,
+    ObjectSize,
+    Rest,
+}
 
-    if !object_path.exists() {
-        anyhow::bail!("object not found: {}", hash);
-    }
+/// Split a batch format string into literals and `%(...)` atoms
+fn parse_batch_format(format: &str) -> Result<Vec<BatchAtom>> {
+    let mut atoms = Vec::new();
+    let mut rest = format;
 
-    // Read and decompress
-    let compressed = std::fs::read(&object_path)
-        .context("Failed to read object file")?;
+    while let Some(start) = rest.find("%(") {
+        if start > 0 {
+            atoms.push(BatchAtom::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..].find(')')
+            .context(format!("fatal: unterminated format element: {}", &rest[start..]))?;
 
-    let mut decoder = ZlibDecoder::new(&compressed[..]);
-    let mut decompressed = Vec::new();
-    decoder.read_to_end(&mut decompressed)
-        .context("Failed to decompress object")?;
+        atoms.push(match &rest[start + 2..start + end] {
+            "objectname" => BatchAtom::ObjectName,
+            "objecttype" => BatchAtom::ObjectType,
+            "objectsize" => BatchAtom::ObjectSize,
+            "rest" => BatchAtom::Rest,
+            other => anyhow::bail!("fatal: unknown format element: %({})", other),
+        });
+        rest = &rest[start + end + 1..];
+    }
 
-    // Parse header
-    let null_pos = decompressed.iter()
-        .position(|&b| b == 0)
-        .context("Invalid object format: no null byte found")?;
+    if !rest.is_empty() {
+        atoms.push(BatchAtom::Literal(rest.to_string()));
+    }
 
-    let header = String::from_utf8_lossy(&decompressed[..null_pos]);
-    let mut parts = header.split(' ');
+    Ok(atoms)
+}
 
-    let object_type = parts.next()
-        .context("Invalid object format: no type")?
-        .to_string();
+/// How a batch input name resolved
+enum BatchLookup {
+    Found(String),
+    Missing,
+    Ambiguous,
+}
 
-    let size: usize = parts.next()
-        .context("Invalid object format: no size")?
-        .parse()
-        .context("Invalid object format: size not a number")?;
+/// Resolve a batch input name without failing the whole batch
+fn lookup_batch_name(repo: &Repository, name: &str) -> Result<BatchLookup> {
+    let format = repo.object_format();
+    let prefix = name.to_lowercase();
 
-    let content = decompressed[null_pos + 1..].to_vec();
+    if format.is_valid_hex(&prefix) {
+        return Ok(if repo.objects.exists(&prefix) {
+            BatchLookup::Found(prefix)
+        } else {
+            BatchLookup::Missing
+        });
+    }
 
-    // Verify size
-    if content.len() != size {
-        anyhow::bail!("Size mismatch: header says {} but content is {} bytes",
-            size, content.len());
+    if prefix.len() < MIN_ABBREV_LEN
+        || prefix.len() > format.hex_len()
+        || !prefix.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Ok(BatchLookup::Missing);
     }
 
-    Ok(GitObject {
-        object_type,
-        size,
-        content,
+    let mut candidates = repo.objects.find_prefix(&prefix)?;
+    Ok(match candidates.len() {
+        0 => BatchLookup::Missing,
+        1 => BatchLookup::Found(candidates.remove(0)),
+        _ => BatchLookup::Ambiguous,
     })
 }
 
-/// Execute the cat-file command
+/// Answer `--batch` / `--batch-check` requests
+///
+/// Reads one object name per line from `input`. For each object, writes
+/// the header (expanded from `format`, or [`DEFAULT_BATCH_FORMAT`]) and,
+/// if `contents` is set, the raw object contents followed by a newline.
+/// Output is flushed after every object so callers can interleave
+/// requests and responses over a pipe.
+///
+/// When the format uses `%(rest)`, the name is only the first word of each
+/// line and the remainder is echoed back through `%(rest)`.
 ///
 /// # Arguments
 ///
-/// * `hash` - The object hash to read
-/// * `pretty_print` - If true, format output nicely
+/// * `repo` - The repository
+/// * `input` - Object names, one per line
+/// * `output` - Where the answers are written
+/// * `format` - Header format, or `None` for the default
+/// * `contents` - True for `--batch`, false for `--batch-check`
 ///
 /// # Example
 ///
-/// ```no_run
-/// use rit::commands::cat_file::run;
+/// ```
+/// use rit::Repository;
+/// use rit::commands::cat_file::batch;
+///
+/// let repo = Repository::in_memory();
+/// let hash = repo.objects.write("blob", b"hi\n").unwrap();
+///
+/// let input = format!("{}\n{}\n", hash, "0".repeat(40));
+/// let mut output = Vec::new();
+/// batch(&repo, &mut input.as_bytes(), &mut output, None, false).unwrap();
 ///
-/// run("abc123...", true).unwrap();
+/// let expected = format!("{} blob 3\n{} missing\n", hash, "0".repeat(40));
+/// assert_eq!(String::from_utf8(output).unwrap(), expected);
 /// ```
-pub fn run(hash: &str, pretty_print: bool) -> Result<()> {
-    let repo = Repository::find()?;
-    let object = read_object(&repo, hash)?;
+pub fn batch(
+    repo: &Repository,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    format: Option<&str>,
+    contents: bool,
+) -> Result<()> {
+    let atoms = parse_batch_format(format.unwrap_or(DEFAULT_BATCH_FORMAT))?;
+    let split_rest = atoms.contains(&BatchAtom::Rest);
 
-    if pretty_print {
-        match object.object_type.as_str() {
-            "blob" => {
-                // Print blob content as-is
-                print!("{}", String::from_utf8_lossy(&object.content));
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line).context("Failed to read object names")? == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        let (name, rest) = if split_rest {
+            match line.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, rest.trim_start()),
+                None => (line, ""),
             }
-            "tree" => {
-                // TODO: Parse and format tree entries
-                println!("(tree formatting not yet implemented)");
-                println!("Raw bytes: {:?}", object.content);
+        } else {
+            (line, "")
+        };
+
+        let hash = match lookup_batch_name(repo, name)? {
+            BatchLookup::Found(hash) => hash,
+            BatchLookup::Missing => {
+                writeln!(output, "{} missing", name)?;
+                output.flush()?;
+                continue;
             }
-            "commit" => {
-                // Commit content is text, print as-is
-                print!("{}", String::from_utf8_lossy(&object.content));
+            BatchLookup::Ambiguous => {
+                writeln!(output, "{} ambiguous", name)?;
+                output.flush()?;
+                continue;
             }
-            _ => {
-                println!("Unknown object type: {}", object.object_type);
+        };
+
+        let object = read_object(repo, &hash)?;
+
+        let mut header = String::new();
+        for atom in &atoms {
+            match atom {
+                BatchAtom::Literal(text) => header.push_str(text),
+                BatchAtom::ObjectName => header.push_str(&hash),
+                BatchAtom::ObjectType => header.push_str(&object.object_type),
+                BatchAtom::ObjectSize => header.push_str(&object.size.to_string()),
+                BatchAtom::Rest => header.push_str(rest),
             }
         }
-    } else {
-        print!("{}", String::from_utf8_lossy(&object.content));
+        writeln!(output, "{}", header)?;
+
+        if contents {
+            output.write_all(&object.content)?;
+            writeln!(output)?;
+        }
+        output.flush()?;
     }
 
     Ok(())
 }
 
+/// Execute `cat-file --batch` or `--batch-check` on stdin and stdout
+///
+/// # Arguments
+///
+/// * `format` - Header format, or `None` for the default
+/// * `contents` - True for `--batch` (headers and contents), false for
+///   `--batch-check` (headers only)
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::cat_file::run_batch;
+///
+/// run_batch(Some("%(objectname) %(objectsize)"), false).unwrap();
+/// ```
+pub fn run_batch(format: Option<&str>, contents: bool) -> Result<()> {
+    let repo = Repository::find()?;
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut output = io::BufWriter::new(stdout.lock());
+    batch(&repo, &mut stdin.lock(), &mut output, format, contents)
+}
+
+/// What `cat-file` prints about an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The raw object contents (no flag)
+    Raw,
+    /// Contents formatted for reading (`-p`)
+    Pretty,
+    /// The object type (`-t`)
+    Type,
+    /// The object size in bytes (`-s`)
+    Size,
+}
+
+/// Resolve a revision expression to an object hash
+///
+/// Supports:
+/// - Full or abbreviated object hashes
+/// - `HEAD`, full ref names (`refs/heads/main`), tag and branch names
+/// - `<rev>:<path>` for the entry at `path` in the tree of `rev` (a
+///   commit, tag or tree); `<rev>:` is the root tree itself
+/// - `:<path>` for the blob staged in the index at `path`
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::Repository;
+/// use rit::commands::cat_file::resolve_revision;
+///
+/// let repo = Repository::find().unwrap();
+/// let blob = resolve_revision(&repo, "HEAD:src/main.rs").unwrap();
+/// ```
+pub fn resolve_revision(repo: &Repository, spec: &str) -> Result<String> {
+    let Some((rev, path)) = spec.split_once(':') else {
+        return resolve_name(repo, spec);
+    };
+
+    if rev.is_empty() {
+        let index = Index::load(&repo.index_path(), repo.object_format())?;
+        return index.get_entry(path)
+            .map(|entry| entry.hash.clone())
+            .ok_or_else(|| anyhow::anyhow!("fatal: path '{}' does not exist in the index", path));
+    }
+
+    let mut hash = peel_to_tree(repo, &resolve_name(repo, rev)?)?;
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+
+    for (i, component) in components.iter().enumerate() {
+        let tree = repo.read_tree(&hash)?;
+        let entry = tree.entries.iter()
+            .find(|e| e.name == *component)
+            .filter(|e| i == components.len() - 1 || e.is_tree())
+            .ok_or_else(|| anyhow::anyhow!("fatal: path '{}' does not exist in '{}'", path, rev))?;
+        hash = entry.hash.clone();
+    }
+
+    Ok(hash)
+}
+
+/// Resolve a name without a `:path` part: HEAD, a ref, or a hash
+fn resolve_name(repo: &Repository, name: &str) -> Result<String> {
+    if repo.object_format().is_valid_hex(&name.to_lowercase()) {
+        return Ok(name.to_lowercase());
+    }
+
+    if name == "HEAD" {
+        return log::read_head(repo)?
+            .ok_or_else(|| anyhow::anyhow!("fatal: HEAD does not point to any commit"));
+    }
+
+    for candidate in [name.to_string(), format!("refs/tags/{}", name), format!("refs/heads/{}", name)] {
+        if !candidate.starts_with("refs/") {
+            continue;
+        }
+        if let Some(hash) = refs::read_ref(repo, &candidate)? {
+            return Ok(hash);
+        }
+    }
+
+    resolve_object(repo, name)
+}
+
+/// Follow commits and tags down to a tree
+fn peel_to_tree(repo: &Repository, hash: &str) -> Result<String> {
+    let mut hash = hash.to_string();
+
+    loop {
+        let object = read_object(repo, &hash)?;
+        match object.object_type.as_str() {
+            "tree" => return Ok(hash),
+            "commit" => hash = repo.read_commit(&hash)?.tree.clone(),
+            "tag" => hash = Tag::parse(&String::from_utf8_lossy(&object.content))?.object,
+            other => anyhow::bail!("fatal: object {} is a {}, not a tree-ish", hash, other),
+        }
+    }
+}
+
+/// Follow tags down to a commit
+///
+/// Fails if `hash` is (or a tag points at) a tree or blob.
+pub fn peel_to_commit(repo: &Repository, hash: &str) -> Result<String> {
+    let mut hash = hash.to_string();
+
+    loop {
+        let object = read_object(repo, &hash)?;
+        match object.object_type.as_str() {
+            "commit" => return Ok(hash),
+            "tag" => hash = Tag::parse(&String::from_utf8_lossy(&object.content))?.object,
+            other => anyhow::bail!("fatal: object {} is a {}, not a commit", hash, other),
+        }
+    }
+}
+
+/// Format an object's contents for `-p`
+///
+/// Trees are listed one entry per line as `<mode> <type> <oid>\t<name>`;
+/// blobs, commits and tags are printed as stored (tags are parsed first
+/// so a malformed one is reported).
+fn pretty_contents(repo: &Repository, object: &GitObject) -> Result<Vec<u8>> {
+    match object.object_type.as_str() {
+        "tree" => {
+            let tree = Tree::parse(&object.content, repo.object_format())?;
+            let mut output = String::new();
+            for entry in &tree.entries {
+                output.push_str(&format!(
+                    "{:0>6} {} {}\t{}\n",
+                    entry.mode, entry.object_type(), entry.hash, entry.name
+                ));
+            }
+            Ok(output.into_bytes())
+        }
+        "tag" => {
+            Tag::parse(&String::from_utf8_lossy(&object.content))
+                .context("fatal: malformed tag object")?;
+            Ok(object.content.clone())
+        }
+        "blob" | "commit" => Ok(object.content.clone()),
+        other => anyhow::bail!("fatal: unknown object type: {}", other),
+    }
+}
+
+/// Check whether a revision names an existing, readable object
+///
+/// False if the name doesn't resolve or the object can't be read.
+pub fn exists(repo: &Repository, spec: &str) -> bool {
+    resolve_revision(repo, spec)
+        .and_then(|hash| read_object(repo, &hash))
+        .is_ok()
+}
+
+/// Execute `cat-file -e`
+///
+/// Prints nothing; the caller turns the result into the exit status
+/// (0 if the object exists, 1 if it doesn't).
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::cat_file::run_exists;
+///
+/// if !run_exists("HEAD:README.md").unwrap() {
+///     std::process::exit(1);
+/// }
+/// ```
+pub fn run_exists(spec: &str) -> Result<bool> {
+    let repo = Repository::find()?;
+    Ok(exists(&repo, spec))
+}
+
+/// Execute the cat-file command
+///
+/// # Arguments
+///
+/// * `spec` - The object to read: a hash or revision expression such as
+///   `HEAD:src/main.rs` (see [`resolve_revision`])
+/// * `mode` - What to print about the object
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::cat_file::{run, Mode};
+///
+/// run("HEAD:", Mode::Pretty).unwrap();
+/// run("abc123", Mode::Type).unwrap();
+/// ```
+pub fn run(spec: &str, mode: Mode) -> Result<()> {
+    let repo = Repository::find()?;
+    let hash = resolve_revision(&repo, spec)?;
+    let object = read_object(&repo, &hash)?;
+
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    match mode {
+        Mode::Type => writeln!(output, "{}", object.object_type)?,
+        Mode::Size => writeln!(output, "{}", object.size)?,
+        Mode::Pretty => output.write_all(&pretty_contents(&repo, &object)?)?,
+        Mode::Raw => output.write_all(&object.content)?,
+    }
+    output.flush()?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
     use crate::commands::hash_object;
+    use crate::objects::TreeEntry;
 
     #[test]
     fn test_roundtrip() {
@@ -180,5 +584,126 @@ mod tests {
         assert_eq!(object.size, content.len());
         assert_eq!(object.content, content);
     }
-}
 
+    #[test]
+    fn test_resolve_abbreviated_hash() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let hash = hash_object::store_object(&repo, "blob", b"test content\n").unwrap();
+
+        assert_eq!(resolve_object(&repo, &hash[..7]).unwrap(), hash);
+        assert_eq!(read_object(&repo, &hash[..4]).unwrap().content, b"test content\n");
+        assert!(resolve_object(&repo, "abc").is_err());
+        assert!(resolve_object(&repo, "zzzz").is_err());
+    }
+
+    #[test]
+    fn test_resolve_ambiguous_hash() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        // Find two blobs whose hashes share the first 4 characters
+        let mut seen = std::collections::HashMap::new();
+        let (first, second) = (0..).find_map(|i| {
+            let content = format!("blob {}", i);
+            let hash = hash_object::hash_content("blob", content.as_bytes());
+            seen.insert(hash[..4].to_string(), content.clone())
+                .map(|other| (other, content))
+        }).unwrap();
+
+        let hash = hash_object::store_object(&repo, "blob", first.as_bytes()).unwrap();
+        hash_object::store_object(&repo, "blob", second.as_bytes()).unwrap();
+
+        let err = resolve_object(&repo, &hash[..4]).unwrap_err().to_string();
+        assert!(err.contains("ambiguous"));
+        assert_eq!(err.matches(" blob").count(), 2);
+    }
+
+    #[test]
+    fn test_resolve_revision_paths() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let blob = hash_object::store_object(&repo, "blob", b"fn main() {}\n").unwrap();
+        let mut src = Tree::new();
+        src.add_entry(TreeEntry::file("main.rs".to_string(), blob.clone()));
+        let src_hash = hash_object::store_object(&repo, "tree", &src.serialize().unwrap()).unwrap();
+        let mut root = Tree::new();
+        root.add_entry(TreeEntry::directory("src".to_string(), src_hash.clone()));
+        let root_hash = hash_object::store_object(&repo, "tree", &root.serialize().unwrap()).unwrap();
+
+        let commit = format!(
+            "tree {}\nauthor T <t@example.com> 0 +0000\ncommitter T <t@example.com> 0 +0000\n\nInitial\n",
+            root_hash
+        );
+        let commit_hash = hash_object::store_object(&repo, "commit", commit.as_bytes()).unwrap();
+        std::fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", commit_hash)).unwrap();
+
+        assert_eq!(resolve_revision(&repo, "HEAD").unwrap(), commit_hash);
+        assert_eq!(resolve_revision(&repo, "main:").unwrap(), root_hash);
+        assert_eq!(resolve_revision(&repo, "HEAD:src").unwrap(), src_hash);
+        assert_eq!(resolve_revision(&repo, "HEAD:src/main.rs").unwrap(), blob);
+        assert_eq!(resolve_revision(&repo, &format!("{}:src/main.rs", &root_hash[..7])).unwrap(), blob);
+        assert!(resolve_revision(&repo, "HEAD:src/main.rs/x").is_err());
+        assert!(resolve_revision(&repo, "HEAD:missing").is_err());
+        assert!(resolve_revision(&repo, &format!("{}:x", blob)).is_err());
+
+        assert!(exists(&repo, "HEAD:src/main.rs"));
+        assert!(!exists(&repo, "HEAD:nope"));
+        assert!(!exists(&repo, &"0".repeat(40)));
+    }
+
+    #[test]
+    fn test_pretty_print_tree_and_tag() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let blob = hash_object::store_object(&repo, "blob", b"hi\n").unwrap();
+        let mut tree = Tree::new();
+        tree.add_entry(TreeEntry::file("a.txt".to_string(), blob.clone()));
+        tree.add_entry(TreeEntry::directory("dir".to_string(), "b".repeat(40)));
+        let tree_hash = hash_object::store_object(&repo, "tree", &tree.serialize().unwrap()).unwrap();
+
+        let output = pretty_contents(&repo, &read_object(&repo, &tree_hash).unwrap()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("100644 blob {}\ta.txt\n040000 tree {}\tdir\n", blob, "b".repeat(40))
+        );
+
+        let tag = format!("object {}\ntype tree\ntag v1\ntagger T <t@example.com> 0 +0000\n\nRelease\n", tree_hash);
+        let tag_hash = hash_object::store_object(&repo, "tag", tag.as_bytes()).unwrap();
+        let output = pretty_contents(&repo, &read_object(&repo, &tag_hash).unwrap()).unwrap();
+        assert_eq!(output, tag.as_bytes());
+        std::fs::write(repo.rit_dir.join("refs/tags/v1"), format!("{}\n", tag_hash)).unwrap();
+        assert_eq!(resolve_revision(&repo, "v1:a.txt").unwrap(), blob);
+
+        let bad = hash_object::store_object(&repo, "tag", b"not a tag\n").unwrap();
+        assert!(pretty_contents(&repo, &read_object(&repo, &bad).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_batch_modes() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let hash = hash_object::store_object(&repo, "blob", b"test content\n").unwrap();
+
+        let run = |input: &str, format: Option<&str>, contents: bool| {
+            let mut output = Vec::new();
+            batch(&repo, &mut input.as_bytes(), &mut output, format, contents).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(
+            run(&format!("{}\nnope\n", &hash[..7]), None, true),
+            format!("{} blob 13\ntest content\n\nnope missing\n", hash)
+        );
+        assert_eq!(
+            run(&format!("{} first file\n", hash), Some("%(objectsize) %(rest)|%(objecttype)"), false),
+            "13 first file|blob\n"
+        );
+
+        let mut output = Vec::new();
+        assert!(batch(&repo, &mut "x\n".as_bytes(), &mut output, Some("%(deltabase)"), false).is_err());
+    }
+}
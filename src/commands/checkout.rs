@@ -20,7 +20,9 @@ use std::path::Path;
 use anyhow::{Context, Result};
 
 use crate::Repository;
-use crate::objects::{Tree, Commit};
+use crate::atomic;
+use crate::lfs;
+use crate::refs;
 use crate::commands::{cat_file, log};
 
 /// Resolve a reference to a commit hash
@@ -29,7 +31,7 @@ use crate::commands::{cat_file, log};
 /// - Branch names (e.g., "main", "feature")
 /// - Commit hashes (full or short)
 /// - "HEAD" to get current commit
-fn resolve_reference(repo: &Repository, reference: &str) -> Result<String> {
+pub fn resolve_reference(repo: &Repository, reference: &str) -> Result<String> {
     // Handle HEAD
     if reference == "HEAD" {
         return log::read_head(repo)?
@@ -37,22 +39,13 @@ fn resolve_reference(repo: &Repository, reference: &str) -> Result<String> {
     }
 
     // Try as branch name first
-    let branch_path = repo.rit_dir.join("refs").join("heads").join(reference);
-    if branch_path.exists() {
-        let commit_hash = fs::read_to_string(&branch_path)
-            .context("Failed to read branch file")?
-            .trim()
-            .to_string();
+    if let Some(commit_hash) = refs::read_ref(repo, &format!("refs/heads/{}", reference))? {
         return Ok(commit_hash);
     }
 
-    // Try as commit hash (must be at least 4 chars)
-    if reference.len() >= 4 {
-        // For now, assume it's a full hash or we'll find it
-        // In a real implementation, we'd expand short hashes
-        if reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Ok(reference.to_string());
-        }
+    // Try as a full or abbreviated commit hash
+    if reference.len() >= 4 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
+        return cat_file::resolve_object(repo, reference);
     }
 
     anyhow::bail!("fatal: reference '{}' not found", reference);
@@ -60,17 +53,10 @@ fn resolve_reference(repo: &Repository, reference: &str) -> Result<String> {
 
 /// Get the tree hash from a commit
 fn get_tree_from_commit(repo: &Repository, commit_hash: &str) -> Result<String> {
-    let object = cat_file::read_object(repo, commit_hash)
+    let commit = repo.read_commit(commit_hash)
         .context(format!("Failed to read commit: {}", commit_hash))?;
 
-    if object.object_type != "commit" {
-        anyhow::bail!("Not a commit object: {}", commit_hash);
-    }
-
-    let commit_content = String::from_utf8_lossy(&object.content);
-    let commit = Commit::parse(&commit_content)?;
-
-    Ok(commit.tree)
+    Ok(commit.tree.clone())
 }
 
 /// Recursively write tree contents to working directory
@@ -81,23 +67,16 @@ fn get_tree_from_commit(repo: &Repository, commit_hash: &str) -> Result<String>
 /// * `tree_hash` - Hash of the tree to write
 /// * `base_path` - Base directory path (for recursion)
 /// * `force` - If true, overwrite existing files
-fn write_tree_to_working_dir(
+pub fn write_tree_to_working_dir(
     repo: &Repository,
     tree_hash: &str,
     base_path: &Path,
     force: bool,
 ) -> Result<()> {
-    // Read the tree object
-    let object = cat_file::read_object(repo, tree_hash)
+    // Read and parse the tree object
+    let tree = repo.read_tree(tree_hash)
         .context(format!("Failed to read tree object: {}", tree_hash))?;
 
-    if object.object_type != "tree" {
-        anyhow::bail!("Not a tree object: {}", tree_hash);
-    }
-
-    // Parse the tree
-    let tree = Tree::parse(&object.content)?;
-
     // Ensure base directory exists
     if !base_path.exists() {
         fs::create_dir_all(base_path)
@@ -128,9 +107,9 @@ fn write_tree_to_working_dir(
                 continue;
             }
 
-            // Write the file
-            fs::write(&entry_path, &blob_object.content)
-                .context(format!("Failed to write file: {}", entry_path.display()))?;
+            // Write the file (with LFS content in place of pointers)
+            let relative = entry_path.strip_prefix(&repo.root).unwrap_or(&entry_path);
+            lfs::smudge(repo, &relative.to_string_lossy(), &blob_object.content, &entry_path)?;
 
             // Set executable bit if needed
             if entry.mode == "100755" || entry.mode == crate::objects::tree::MODE_EXEC {
@@ -171,14 +150,13 @@ fn update_head(repo: &Repository, reference: &str, commit_hash: &str) -> Result<
     let head_path = repo.head_path();
 
     // Check if reference is a branch name
-    let branch_path = repo.rit_dir.join("refs").join("heads").join(reference);
-    if branch_path.exists() {
+    if refs::ref_exists(repo, &format!("refs/heads/{}", reference))? {
         // It's a branch - update HEAD to point to the branch
-        fs::write(&head_path, format!("ref: refs/heads/{}\n", reference))
+        atomic::write(&head_path, &repo.rit_dir, format!("ref: refs/heads/{}\n", reference).as_bytes())
             .context("Failed to update HEAD")?;
     } else {
         // It's a commit hash - detached HEAD
-        fs::write(&head_path, format!("{}\n", commit_hash))
+        atomic::write(&head_path, &repo.rit_dir, format!("{}\n", commit_hash).as_bytes())
             .context("Failed to update HEAD")?;
     }
 
@@ -200,9 +178,8 @@ fn checkout_file(repo: &Repository, reference: &str, file_path: &str) -> Result<
     // Navigate through directories
     for (i, component) in components.iter().enumerate() {
         let name = component.as_os_str().to_string_lossy();
-        let object = cat_file::read_object(repo, &current_tree_hash)
+        let tree = repo.read_tree(&current_tree_hash)
             .context("Failed to read tree")?;
-        let tree = Tree::parse(&object.content)?;
 
         let entry = tree.entries.iter()
             .find(|e| e.name == name)
@@ -223,8 +200,7 @@ fn checkout_file(repo: &Repository, reference: &str, file_path: &str) -> Result<
                 fs::create_dir_all(parent)?;
             }
 
-            fs::write(&target_path, &blob_object.content)
-                .context(format!("Failed to write file: {}", target_path.display()))?;
+            lfs::smudge(repo, file_path, &blob_object.content, &target_path)?;
 
             return Ok(());
         } else {
@@ -259,7 +235,7 @@ fn checkout_file(repo: &Repository, reference: &str, file_path: &str) -> Result<
 /// run("abc1234", None, false).unwrap();
 ///
 /// // Checkout a file
-/// run("HEAD", Some("file.txt"), false).unwrap();
+/// run("HEAD", Some("file.txt".to_string()), false).unwrap();
 /// ```
 pub fn run(reference: &str, file_path: Option<String>, force: bool) -> Result<()> {
     let repo = Repository::find()?;
@@ -282,8 +258,7 @@ pub fn run(reference: &str, file_path: Option<String>, force: bool) -> Result<()
     update_head(&repo, reference, &commit_hash)?;
 
     // Determine if we're on a branch or detached HEAD
-    let branch_path = repo.rit_dir.join("refs").join("heads").join(reference);
-    if branch_path.exists() {
+    if refs::ref_exists(&repo, &format!("refs/heads/{}", reference))? {
         println!("Switched to branch '{}'", reference);
     } else {
         println!("Note: checking out '{}'.", &commit_hash[..7]);
@@ -7,6 +7,9 @@
 //! ```bash
 //! # Create commit from staged files
 //! rit commit -m "Commit message"
+//!
+//! # Stage changes to tracked files first, then commit
+//! rit commit -a -m "Commit message"
 //! ```
 
 use std::collections::HashMap;
@@ -14,11 +17,14 @@ use std::path::Path;
 use anyhow::{Context, Result};
 
 use crate::Repository;
+use crate::atomic;
 use crate::index::Index;
 use crate::objects::{Tree, TreeEntry};
+use crate::commands::add;
 use crate::commands::hash_object;
 use crate::commands::commit_tree;
 use crate::commands::log;
+use crate::lfs::LfsAttributes;
 
 /// Build a tree object from index entries
 ///
@@ -40,14 +46,22 @@ fn build_tree_from_index(repo: &Repository, index: &Index) -> Result<String> {
             .context("Invalid file path")?
             .to_string();
         
+        // Every ancestor needs a tree, even with no files of its own
+        let mut ancestor = path.parent();
+        while let Some(dir) = ancestor.and_then(|d| d.parent()) {
+            dir_files.entry(dir.to_string_lossy().to_string()).or_default();
+            ancestor = Some(dir);
+        }
+
         dir_files.entry(parent)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push((file_name, entry.hash.clone()));
     }
     
     // Build trees from deepest directories to root
     let mut dir_levels: Vec<_> = dir_files.keys().collect();
-    dir_levels.sort_by_key(|d| d.matches('/').count());
+    // (the root is shallower than any top-level directory)
+    dir_levels.sort_by_key(|d| if d.is_empty() { 0 } else { d.matches('/').count() + 1 });
     dir_levels.reverse();
     
     let mut built_trees: HashMap<String, String> = HashMap::new();
@@ -87,7 +101,7 @@ fn build_tree_from_index(repo: &Repository, index: &Index) -> Result<String> {
 }
 
 /// Update HEAD or branch ref to point to a commit
-fn update_ref(repo: &Repository, commit_hash: &str) -> Result<()> {
+pub fn update_ref(repo: &Repository, commit_hash: &str) -> Result<()> {
     let head_path = repo.head_path();
     let head_content = std::fs::read_to_string(&head_path)
         .context("Failed to read HEAD")?;
@@ -97,11 +111,11 @@ fn update_ref(repo: &Repository, commit_hash: &str) -> Result<()> {
     if let Some(ref_path) = head_content.strip_prefix("ref: ") {
         // Update branch ref
         let ref_file = repo.rit_dir.join(ref_path.trim());
-        std::fs::write(&ref_file, format!("{}\n", commit_hash))
+        atomic::write(&ref_file, &repo.rit_dir, format!("{}\n", commit_hash).as_bytes())
             .context("Failed to update branch ref")?;
     } else {
         // Detached HEAD - update HEAD directly
-        std::fs::write(&head_path, format!("{}\n", commit_hash))
+        atomic::write(&head_path, &repo.rit_dir, format!("{}\n", commit_hash).as_bytes())
             .context("Failed to update HEAD")?;
     }
 
@@ -116,12 +130,51 @@ fn get_parent_commit(repo: &Repository) -> Result<Option<String>> {
     }
 }
 
+/// Fail if any path in the index has an unresolved conflict
+fn refuse_unmerged(index: &Index) -> Result<()> {
+    // A tree can't be built while paths have unresolved conflicts
+    if index.has_conflicts() {
+        for path in index.unmerged_paths() {
+            eprintln!("U\t{}", path);
+        }
+        anyhow::bail!(
+            "Committing is not possible because you have unmerged files.\n\
+             hint: Fix them up in the work tree, and then use 'rit add <file>'\n\
+             hint: as appropriate to mark resolution and make a commit.\n\
+             fatal: Exiting because of an unresolved conflict."
+        );
+    }
+    Ok(())
+}
+
+/// Load the index to commit, first staging tracked files if `auto_add`
+///
+/// Conflicts are checked before anything is staged, since staging a
+/// conflicted path would mark it resolved.
+fn load_index_to_commit(repo: &Repository, auto_add: bool) -> Result<Index> {
+    let index_path = repo.index_path();
+
+    if !auto_add {
+        let index = Index::load(&index_path, repo.object_format())?;
+        refuse_unmerged(&index)?;
+        return Ok(index);
+    }
+
+    // Stage changes to tracked files, as `rit add -u` would
+    let (mut index, lock) = Index::load_for_update(&index_path, repo.object_format())?;
+    refuse_unmerged(&index)?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
+    add::update_tracked(repo, &mut index, &[String::new()], &lfs_attributes)?;
+    index.save_locked(lock)?;
+    Ok(index)
+}
+
 /// Execute the commit command
 ///
 /// # Arguments
 ///
 /// * `message` - Commit message
-/// * `auto_add` - If true, automatically stage modified files (not yet implemented)
+/// * `auto_add` - If true, first stage modified and deleted tracked files (`-a`)
 ///
 /// # Example
 ///
@@ -131,12 +184,9 @@ fn get_parent_commit(repo: &Repository) -> Result<Option<String>> {
 /// // Create commit from staged files
 /// run("Initial commit", false).unwrap();
 /// ```
-pub fn run(message: &str, _auto_add: bool) -> Result<()> {
+pub fn run(message: &str, auto_add: bool) -> Result<()> {
     let repo = Repository::find()?;
-    let index_path = repo.index_path();
-
-    // Load index
-    let index = Index::load(&index_path)?;
+    let index = load_index_to_commit(&repo, auto_add)?;
 
     // Check if index is empty
     if index.entries().next().is_none() {
@@ -169,30 +219,78 @@ pub fn run(message: &str, _auto_add: bool) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::tempdir;
     use crate::Repository;
-    use crate::index::IndexEntry;
+    use crate::index::{IndexEntry, STAGE_OURS, STAGE_THEIRS};
+    use tempfile::tempdir;
 
     #[test]
     fn test_build_tree_from_index() {
-        let temp = tempdir().unwrap();
-        let repo = Repository::init(temp.path()).unwrap();
+        let repo = Repository::in_memory();
 
-        let mut index = Index::new();
+        let mut index = Index::new(repo.object_format());
         index.add_entry(IndexEntry {
             path: "file1.txt".to_string(),
             hash: "a".repeat(40),
             size: 10,
             mtime: 1000,
+            ..IndexEntry::default()
         });
         index.add_entry(IndexEntry {
             path: "file2.txt".to_string(),
             hash: "b".repeat(40),
             size: 20,
             mtime: 2000,
+            ..IndexEntry::default()
         });
 
         let tree_hash = build_tree_from_index(&repo, &index).unwrap();
         assert_eq!(tree_hash.len(), 40);
+
+        let tree = Tree::parse(&repo.objects.read(&tree_hash).unwrap().content, repo.object_format()).unwrap();
+        assert_eq!(tree.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_build_tree_with_only_nested_files() {
+        let repo = Repository::in_memory();
+
+        // Neither the root nor src/ has files of its own
+        let mut index = Index::new(repo.object_format());
+        for (path, hash) in [("src/cmd/a.rs", "a"), ("src/cmd/b.rs", "b"), ("docs/x.md", "c")] {
+            index.add_entry(IndexEntry { path: path.to_string(), hash: hash.repeat(40), ..IndexEntry::default() });
+        }
+
+        let read_tree = |hash: &str| Tree::parse(&repo.objects.read(hash).unwrap().content, repo.object_format()).unwrap();
+        let root = read_tree(&build_tree_from_index(&repo, &index).unwrap());
+        let names: Vec<&str> = root.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "src"]);
+
+        let src = read_tree(&root.entries[1].hash);
+        assert_eq!(src.entries.len(), 1);
+        assert_eq!(read_tree(&src.entries[0].hash).entries.len(), 2);
+    }
+
+    #[test]
+    fn test_commit_all_refuses_conflicts_before_staging() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        std::fs::write(temp.path().join("file.txt"), "resolved?\n").unwrap();
+        let mut index = Index::new(repo.object_format());
+        for (stage, hash) in [(STAGE_OURS, "a"), (STAGE_THEIRS, "b")] {
+            let entry = IndexEntry { path: "file.txt".to_string(), hash: hash.repeat(40), ..IndexEntry::default() };
+            index.add_conflict_entry(entry, stage).unwrap();
+        }
+        index.save(&repo.index_path()).unwrap();
+
+        let err = load_index_to_commit(&repo, true).unwrap_err();
+        assert!(err.to_string().contains("unmerged files"));
+
+        // The working copy wasn't staged over the recorded stages
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        let conflict = index.get_conflict("file.txt").unwrap();
+        assert_eq!(conflict.ours.as_ref().unwrap().hash, "a".repeat(40));
+        assert_eq!(conflict.theirs.as_ref().unwrap().hash, "b".repeat(40));
+        assert!(index.get_entry("file.txt").is_none());
     }
 }
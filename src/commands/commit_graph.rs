@@ -0,0 +1,35 @@
+//! # Commit-Graph Command
+//!
+//! Write the commit-graph file that speeds up history walks (see
+//! [`crate::commit_graph`]).
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Record every commit reachable from a ref or HEAD
+//! rit commit-graph write
+//! ```
+
+use anyhow::Result;
+
+use crate::Repository;
+use crate::commit_graph;
+
+/// Execute `commit-graph write`
+///
+/// The file is replaced as a whole, so commits made since the last write
+/// are picked up and unreachable ones dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::commit_graph::run_write;
+///
+/// run_write().unwrap();
+/// ```
+pub fn run_write() -> Result<()> {
+    let repo = Repository::find()?;
+    let (_, count) = commit_graph::write(&repo)?;
+    println!("Wrote commit-graph with {} commits", count);
+    Ok(())
+}
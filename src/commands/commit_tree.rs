@@ -16,11 +16,11 @@
 //! ```
 
 use std::env;
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 use crate::Repository;
 use crate::objects::commit::{Commit, Author};
-use crate::commands::hash_object;
+use crate::commands::{cat_file, hash_object};
 
 /// Get author information from environment or use defaults
 ///
@@ -49,8 +49,8 @@ pub fn get_author() -> Author {
 ///
 /// # Arguments
 ///
-/// * `tree_hash` - SHA-1 hash of the tree object
-/// * `parents` - Optional parent commit hashes
+/// * `tree_hash` - SHA-1 hash of the tree object (may be abbreviated)
+/// * `parents` - Optional parent commit hashes (may be abbreviated)
 /// * `message` - Commit message
 ///
 /// # Example
@@ -62,24 +62,30 @@ pub fn get_author() -> Author {
 /// run("abc123...", vec![], "Initial commit").unwrap();
 ///
 /// // With parent
-/// run("def456...", vec!["abc123..."], "Second commit").unwrap();
+/// run("def456...", vec!["abc123...".to_string()], "Second commit").unwrap();
 /// ```
 pub fn run(tree_hash: &str, parents: Vec<String>, message: &str) -> Result<String> {
     let repo = Repository::find()?;
 
-    // Verify tree exists
-    let tree_path = repo.objects_dir().join(&tree_hash[..2]).join(&tree_hash[2..]);
-    if !tree_path.exists() {
-        anyhow::bail!("tree object not found: {}", tree_hash);
+    // Expand abbreviated hashes and verify the tree exists
+    let tree_hash = cat_file::resolve_object(&repo, tree_hash)?;
+    let tree_object = cat_file::read_object(&repo, &tree_hash)
+        .context(format!("tree object not found: {}", tree_hash))?;
+    if tree_object.object_type != "tree" {
+        anyhow::bail!("fatal: {} is not a valid 'tree' object", tree_hash);
     }
 
+    let parents = parents.iter()
+        .map(|p| cat_file::resolve_object(&repo, p))
+        .collect::<Result<Vec<_>>>()?;
+
     // Get author info
     let author = get_author();
     let committer = get_author(); // For now, same as author
 
     // Create commit object
     let commit = Commit::new(
-        tree_hash.to_string(),
+        tree_hash,
         parents,
         author,
         committer,
@@ -16,17 +16,19 @@
 //! ```
 
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
 use anyhow::{Context, Result};
 
 use crate::Repository;
 use crate::index::Index;
-use crate::commands::{cat_file, log};
+use crate::commands::{cat_file, checkout, log, status};
 use crate::ignore;
+use crate::lfs::{self, LfsAttributes};
 
 /// Represents a single edit operation in a diff
 #[derive(Debug, Clone, PartialEq)]
-enum Edit {
+pub enum Edit {
     /// Keep a line (common to both)
     Keep(String),
     /// Delete a line (only in old)
@@ -40,7 +42,7 @@ enum Edit {
 /// Finds the shortest edit script (SES) between two sequences using
 /// the algorithm described by Eugene W. Myers in "An O(ND) Difference
 /// Algorithm and Its Variations".
-fn myers_diff(old: &[String], new: &[String]) -> Vec<Edit> {
+pub fn myers_diff(old: &[String], new: &[String]) -> Vec<Edit> {
     let n = old.len();
     let m = new.len();
     
@@ -83,8 +85,8 @@ fn myers_diff(old: &[String], new: &[String]) -> Vec<Edit> {
             for k in (j + 1)..m.min(j + 10) {
                 if old[i] == new[k] {
                     // Found match ahead - insert lines in between
-                    for l in j..k {
-                        edits.push(Edit::Insert(new[l].clone()));
+                    for line in &new[j..k] {
+                        edits.push(Edit::Insert(line.clone()));
                     }
                     edits.push(Edit::Keep(old[i].clone()));
                     i += 1;
@@ -99,8 +101,8 @@ fn myers_diff(old: &[String], new: &[String]) -> Vec<Edit> {
                 for k in (i + 1)..n.min(i + 10) {
                     if old[k] == new[j] {
                         // Found match ahead - delete lines in between
-                        for l in i..k {
-                            edits.push(Edit::Delete(old[l].clone()));
+                        for line in &old[i..k] {
+                            edits.push(Edit::Delete(line.clone()));
                         }
                         edits.push(Edit::Keep(new[j].clone()));
                         i = k + 1;
@@ -154,7 +156,7 @@ fn format_unified_diff(
                     let new_count = new_line - hunk_start_new;
                     output.push(format!("@@ -{},{} +{},{} @@", 
                         hunk_start_old, old_count, hunk_start_new, new_count));
-                    output.extend(hunk_lines.drain(..));
+                    output.append(&mut hunk_lines);
                     in_hunk = false;
                 }
                 old_line += 1;
@@ -193,8 +195,103 @@ fn format_unified_diff(
     output.join("\n")
 }
 
+/// A group of nearby changes with the unchanged lines around them, as
+/// shown under one `@@` header
+///
+/// A hunk refers to a range of an edit script (from [`myers_diff`]), so
+/// the hunks of one script can be accepted or rejected independently and
+/// the result rebuilt from the script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// The hunk's edits, as a range of the edit script
+    pub range: Range<usize>,
+    /// First old line the hunk covers (1-based)
+    pub old_start: usize,
+    /// First new line the hunk covers (1-based)
+    pub new_start: usize,
+}
+
+impl Hunk {
+    /// Create a hunk for a range of an edit script
+    fn new(edits: &[Edit], range: Range<usize>) -> Self {
+        let old_start = 1 + edits[..range.start].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_start = 1 + edits[..range.start].iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+        Self { range, old_start, new_start }
+    }
+
+    /// The `@@ -<old> +<new> @@` header line
+    pub fn header(&self, edits: &[Edit]) -> String {
+        let lines = &edits[self.range.clone()];
+        let old_count = lines.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_count = lines.iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+        // An empty side is numbered by the line before it, as in diff(1)
+        let old_start = if old_count == 0 { self.old_start - 1 } else { self.old_start };
+        let new_start = if new_count == 0 { self.new_start - 1 } else { self.new_start };
+        format!("@@ -{},{} +{},{} @@", old_start, old_count, new_start, new_count)
+    }
+
+    /// Split into one hunk per run of changes
+    ///
+    /// The unchanged lines between two runs are shared out between them,
+    /// so the pieces don't overlap. A hunk with a single run of changes
+    /// can't be split and is returned as is.
+    pub fn split(&self, edits: &[Edit]) -> Vec<Hunk> {
+        let runs = change_runs(edits, self.range.clone());
+        if runs.len() < 2 {
+            return vec![self.clone()];
+        }
+
+        let mut pieces = Vec::new();
+        let mut start = self.range.start;
+        for pair in runs.windows(2) {
+            let gap_end = pair[1].start;
+            let end = pair[0].end + (gap_end - pair[0].end).div_ceil(2);
+            pieces.push(Hunk::new(edits, start..end));
+            start = end;
+        }
+        pieces.push(Hunk::new(edits, start..self.range.end));
+        pieces
+    }
+}
+
+/// The runs of consecutive changes (deletions and insertions) in a range
+fn change_runs(edits: &[Edit], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for i in range {
+        if matches!(edits[i], Edit::Keep(_)) {
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if run.end == i => run.end = i + 1,
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+/// Group an edit script into hunks with up to `context` unchanged lines
+/// around each change
+///
+/// Changes closer together than twice the context share a hunk, so hunks
+/// never overlap.
+pub fn group_hunks(edits: &[Edit], context: usize) -> Vec<Hunk> {
+    let runs = change_runs(edits, 0..edits.len());
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for run in runs {
+        let start = run.start.saturating_sub(context);
+        let end = (run.end + context).min(edits.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+
+    ranges.into_iter().map(|range| Hunk::new(edits, range)).collect()
+}
+
 /// Split content into lines
-fn split_lines(content: &[u8]) -> Vec<String> {
+pub fn split_lines(content: &[u8]) -> Vec<String> {
     String::from_utf8_lossy(content)
         .lines()
         .map(|s| s.to_string())
@@ -202,13 +299,17 @@ fn split_lines(content: &[u8]) -> Vec<String> {
 }
 
 /// Get file content from working directory
-fn get_working_file(repo: &Repository, path: &str) -> Result<Vec<u8>> {
+///
+/// LFS files are turned into their pointers, so they are compared with
+/// the pointer blobs in the index rather than byte by byte.
+fn get_working_file(repo: &Repository, lfs_attributes: &LfsAttributes, path: &str) -> Result<Vec<u8>> {
     let file_path = repo.root.join(path);
     if !file_path.exists() {
         anyhow::bail!("File not found: {}", path);
     }
-    fs::read(&file_path)
-        .context(format!("Failed to read file: {}", path))
+    let content = fs::read(&file_path)
+        .context(format!("Failed to read file: {}", path))?;
+    Ok(lfs::clean(lfs_attributes, path, content))
 }
 
 /// Get file content from index
@@ -229,28 +330,19 @@ fn get_head_file(repo: &Repository, path: &str) -> Result<Vec<u8>> {
     let head_commit = log::read_head(repo)?
         .ok_or_else(|| anyhow::anyhow!("No HEAD commit"))?;
     
-    let commit_obj = cat_file::read_object(repo, &head_commit)?;
-    if commit_obj.object_type != "commit" {
-        anyhow::bail!("HEAD does not point to a commit");
-    }
-    
-    let commit_content = String::from_utf8_lossy(&commit_obj.content);
-    let commit = crate::objects::commit::Commit::parse(&commit_content)?;
+    // Called once per file, so the commit and trees come from the cache
+    let commit = repo.read_commit(&head_commit)
+        .context("HEAD does not point to a commit")?;
     
     // Navigate tree to find file
     let path_obj = Path::new(path);
     let components: Vec<_> = path_obj.components().collect();
     
-    let mut current_tree_hash = commit.tree;
+    let mut current_tree_hash = commit.tree.clone();
     
     for (idx, component) in components.iter().enumerate() {
         let name = component.as_os_str().to_string_lossy();
-        let tree_obj = cat_file::read_object(repo, &current_tree_hash)?;
-        if tree_obj.object_type != "tree" {
-            anyhow::bail!("Not a tree object");
-        }
-        
-        let tree = crate::objects::Tree::parse(&tree_obj.content)?;
+        let tree = repo.read_tree(&current_tree_hash)?;
         let entry = tree.entries.iter()
             .find(|e| e.name == name)
             .ok_or_else(|| anyhow::anyhow!("Path not found in tree: {}", path))?;
@@ -303,10 +395,11 @@ fn diff_file(
 
 /// Show diff between working directory and index
 fn diff_working_vs_index(repo: &Repository) -> Result<()> {
-    let index = Index::load(&repo.index_path())?;
+    let index = Index::load(&repo.index_path(), repo.object_format())?;
     
     // Load ignore rules
     let ignore_rules = ignore::load_ignore_rules(&repo.root)?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
     
     // Get all files that are in index or working directory
     let mut all_files = std::collections::HashSet::new();
@@ -319,26 +412,30 @@ fn diff_working_vs_index(repo: &Repository) -> Result<()> {
     }
     
     // Add files from working directory
-    use walkdir::WalkDir;
-    for entry in WalkDir::new(&repo.root)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() {
-            if let Ok(relative) = path.strip_prefix(&repo.root) {
-                let rel_str = relative.to_string_lossy().to_string();
-                if !rel_str.starts_with(".rit/") && !ignore_rules.is_ignored(&rel_str, false) {
-                    all_files.insert(rel_str);
-                }
-            }
+    for path in status::get_working_files(&repo.root, &ignore_rules)? {
+        if let Ok(relative) = path.strip_prefix(&repo.root) {
+            all_files.insert(relative.to_string_lossy().to_string());
         }
     }
     
     let mut has_output = false;
     
     for file_path in all_files {
-        let working_content = get_working_file(repo, &file_path).ok();
+        // Conflicted paths have no single index version to compare with
+        if index.get_conflict(&file_path).is_some() {
+            println!("* Unmerged path {}", file_path);
+            has_output = true;
+            continue;
+        }
+
+        // Skip files whose stat data shows them unchanged since staging
+        if let (Some(entry), Ok(metadata)) = (index.get_entry(&file_path), fs::metadata(repo.root.join(&file_path))) {
+            if !index.needs_rehash(entry, &metadata) {
+                continue;
+            }
+        }
+
+        let working_content = get_working_file(repo, &lfs_attributes, &file_path).ok();
         let index_content = get_index_file(repo, &index, &file_path).ok();
         
         match (working_content, index_content) {
@@ -380,8 +477,12 @@ fn diff_working_vs_index(repo: &Repository) -> Result<()> {
 
 /// Show diff between index and HEAD
 fn diff_index_vs_head(repo: &Repository) -> Result<()> {
-    let index = Index::load(&repo.index_path())?;
+    let index = Index::load(&repo.index_path(), repo.object_format())?;
     let head_commit = log::read_head(repo)?;
+
+    for path in index.unmerged_paths() {
+        println!("* Unmerged path {}", path);
+    }
     
     if head_commit.is_none() {
         // No HEAD - show all index files as new
@@ -421,6 +522,53 @@ fn diff_index_vs_head(repo: &Repository) -> Result<()> {
     Ok(())
 }
 
+/// Read the blob at `hash` for diffing
+fn read_blob(repo: &Repository, hash: &str) -> Result<Vec<u8>> {
+    let blob_obj = cat_file::read_object(repo, hash)?;
+    if blob_obj.object_type != "blob" {
+        anyhow::bail!("Expected blob, got {}", blob_obj.object_type);
+    }
+    Ok(blob_obj.content)
+}
+
+/// Show diff between two commits
+///
+/// Both references may be branch names, `HEAD`, or full or abbreviated
+/// commit hashes.
+fn diff_commits(repo: &Repository, commit1: &str, commit2: &str) -> Result<()> {
+    let mut trees = Vec::new();
+    for reference in [commit1, commit2] {
+        let commit_hash = checkout::resolve_reference(repo, reference)?;
+        let commit = repo.read_commit(&commit_hash)
+            .context(format!("fatal: {} is not a commit", reference))?;
+        trees.push(status::get_tree_files(repo, &commit.tree)?);
+    }
+
+    let (old_files, new_files) = (&trees[0], &trees[1]);
+    let mut paths: Vec<&String> = old_files.keys().chain(new_files.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    for path in paths {
+        match (old_files.get(path), new_files.get(path)) {
+            (Some(old_hash), Some(new_hash)) => {
+                if old_hash != new_hash {
+                    diff_file(repo, path, &read_blob(repo, old_hash)?, &read_blob(repo, new_hash)?)?;
+                }
+            }
+            (Some(old_hash), None) => {
+                diff_file(repo, path, &read_blob(repo, old_hash)?, &[])?;
+            }
+            (None, Some(new_hash)) => {
+                diff_file(repo, path, &[], &read_blob(repo, new_hash)?)?;
+            }
+            (None, None) => {}
+        }
+    }
+
+    Ok(())
+}
+
 /// Execute the diff command
 ///
 /// # Arguments
@@ -446,9 +594,9 @@ pub fn run(cached: bool, commit1: Option<String>, commit2: Option<String>) -> Re
     if cached {
         // Show staged changes (index vs HEAD)
         diff_index_vs_head(&repo)?;
-    } else if let (Some(_c1), Some(_c2)) = (commit1, commit2) {
-        // Compare two commits (not yet implemented)
-        anyhow::bail!("Comparing two commits is not yet implemented");
+    } else if let (Some(c1), Some(c2)) = (commit1, commit2) {
+        // Compare two commits
+        diff_commits(&repo, &c1, &c2)?;
     } else {
         // Show working directory vs index
         diff_working_vs_index(&repo)?;
@@ -480,6 +628,29 @@ mod tests {
         assert!(edits.iter().all(|e| matches!(e, Edit::Keep(_))));
     }
 
+    #[test]
+    fn test_group_and_split_hunks() {
+        let lines = |s: &str| s.chars().map(|c| c.to_string()).collect::<Vec<_>>();
+        let old = lines("abcdefghijkl");
+        let new = lines("aBcdefghijKl");
+        let edits = myers_diff(&old, &new);
+
+        // Far apart: two hunks with one line of context each
+        let hunks = group_hunks(&edits, 1);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].header(&edits), "@@ -1,3 +1,3 @@");
+        assert_eq!(hunks[1].header(&edits), "@@ -10,3 +10,3 @@");
+
+        // Close together: one hunk, which splits back into two
+        let hunks = group_hunks(&edits, 4);
+        assert_eq!(hunks.len(), 1);
+        let pieces = hunks[0].split(&edits);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].range.end, pieces[1].range.start);
+        assert_eq!(pieces[1].old_start, pieces[1].new_start);
+        assert_eq!(pieces[0].split(&edits), vec![pieces[0].clone()]);
+    }
+
     #[test]
     fn test_myers_diff_add_lines() {
         let old = vec!["a".to_string()];
@@ -0,0 +1,441 @@
+//! # Fsck Command
+//!
+//! Verify the integrity and connectivity of the object database.
+//!
+//! ## What is checked
+//!
+//! 1. Every loose object decompresses, has a known type and the size in its
+//!    header, and hashes to the name of its file
+//! 2. Every packed object can be read (deltas resolved) and hashes to the
+//!    name in the pack index
+//! 3. Every tree, commit and tag parses cleanly
+//! 4. Starting from all refs, `HEAD` and the index, every object pointed to
+//!    exists
+//!
+//! Objects in alternate object stores (`objects/info/alternates`) are not
+//! checked themselves, but count as present when something links to them.
+//!
+//! Objects that nothing reaches and nothing points to are reported as
+//! dangling. Dangling objects are not an error; corrupt objects, broken
+//! links and missing objects are, and make the command exit non-zero.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Check the repository
+//! rit fsck
+//!
+//! # Don't report dangling objects
+//! rit fsck --no-dangling
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use anyhow::Result;
+
+use crate::Repository;
+use crate::commands::repack;
+use crate::hash::ObjectFormat;
+use crate::objects::{Commit, GitObject, Tag, Tree};
+
+/// Object types that may appear in an object header
+const OBJECT_TYPES: [&str; 4] = ["blob", "tree", "commit", "tag"];
+
+/// A reference from one object (or ref) to an object that doesn't exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// Where the link comes from, e.g. `tree <hash>` or `refs/heads/main`
+    pub from: String,
+    /// The type the target was expected to have
+    pub to_type: String,
+    /// The missing target
+    pub to_hash: String,
+}
+
+/// The findings of an fsck run
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    /// Number of objects checked (loose and packed)
+    pub checked: usize,
+    /// Damaged objects: `(hash, reason)`
+    pub corrupt: Vec<(String, String)>,
+    /// Links whose target doesn't exist
+    pub broken_links: Vec<BrokenLink>,
+    /// Objects that are referenced but not present: `(type, hash)`
+    pub missing: Vec<(String, String)>,
+    /// Unreachable objects nothing points to: `(type, hash)`
+    pub dangling: Vec<(String, String)>,
+}
+
+impl FsckReport {
+    /// True if nothing is corrupt, broken or missing
+    ///
+    /// Dangling objects don't count as errors.
+    pub fn is_ok(&self) -> bool {
+        self.corrupt.is_empty() && self.broken_links.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Verify an object's header and hash, and parse it
+///
+/// # Returns
+///
+/// The `(type, hash)` of every object it points to
+fn check_object(format: ObjectFormat, hash: &str, object: &GitObject) -> Result<Vec<(String, String)>> {
+    if !OBJECT_TYPES.contains(&object.object_type.as_str()) {
+        anyhow::bail!("unknown object type '{}'", object.object_type);
+    }
+
+    let actual = format.hash_object(&object.object_type, &object.content);
+    if actual != hash {
+        anyhow::bail!("hash mismatch (content hashes to {})", actual);
+    }
+
+    let mut links = Vec::new();
+    match object.object_type.as_str() {
+        "tree" => {
+            let tree = Tree::parse(&object.content, format)?;
+            for entry in tree.entries {
+                // Gitlinks point into other repositories
+                if entry.mode == "160000" {
+                    continue;
+                }
+                let kind = if entry.is_tree() { "tree" } else { "blob" };
+                links.push((kind.to_string(), entry.hash));
+            }
+        }
+        "commit" => {
+            let commit = Commit::parse(&String::from_utf8_lossy(&object.content))?;
+            if !format.is_valid_hex(&commit.tree) {
+                anyhow::bail!("invalid tree '{}'", commit.tree);
+            }
+            links.push(("tree".to_string(), commit.tree));
+            for parent in commit.parents {
+                if !format.is_valid_hex(&parent) {
+                    anyhow::bail!("invalid parent '{}'", parent);
+                }
+                links.push(("commit".to_string(), parent));
+            }
+        }
+        "tag" => {
+            let tag = Tag::parse(&String::from_utf8_lossy(&object.content))?;
+            if !format.is_valid_hex(&tag.object) {
+                anyhow::bail!("invalid object '{}'", tag.object);
+            }
+            if !OBJECT_TYPES.contains(&tag.object_type.as_str()) {
+                anyhow::bail!("invalid tagged type '{}'", tag.object_type);
+            }
+            links.push((tag.object_type, tag.object));
+        }
+        _ => {}
+    }
+
+    Ok(links)
+}
+
+/// Check every object in the repository
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::Repository;
+/// use rit::commands::fsck::fsck;
+///
+/// let repo = Repository::find().unwrap();
+/// let report = fsck(&repo).unwrap();
+/// assert!(report.is_ok());
+/// ```
+pub fn fsck(repo: &Repository) -> Result<FsckReport> {
+    let store = repo.loose_store();
+    let format = repo.object_format();
+    let mut report = FsckReport::default();
+
+    // hash -> (type, links) for every object that passed its checks
+    let mut objects: BTreeMap<String, (String, Vec<(String, String)>)> = BTreeMap::new();
+    let mut corrupt: HashSet<String> = HashSet::new();
+
+    let mut record = |hash: String, object: Result<GitObject>, report: &mut FsckReport| {
+        report.checked += 1;
+        if objects.contains_key(&hash) || corrupt.contains(&hash) {
+            return;
+        }
+        match object.and_then(|o| check_object(format, &hash, &o).map(|links| (o.object_type, links))) {
+            Ok(checked) => {
+                objects.insert(hash, checked);
+            }
+            Err(e) => {
+                report.corrupt.push((hash.clone(), e.to_string()));
+                corrupt.insert(hash);
+            }
+        }
+    };
+
+    for hash in store.list_loose()? {
+        let object = store.read_loose(&hash);
+        record(hash, object, &mut report);
+    }
+
+    for pack in store.packs()?.iter() {
+        for hash in pack.index.hashes() {
+            let object = pack.read_object(&store, &hash)
+                .and_then(|o| o.ok_or_else(|| anyhow::anyhow!("not found in pack")));
+            record(hash, object, &mut report);
+        }
+    }
+
+    // Borrowed from alternates: present, but not ours to check
+    let borrowed = store.alternate_hashes()?;
+    let present = |hash: &String| {
+        objects.contains_key(hash) || corrupt.contains(hash) || borrowed.contains(hash)
+    };
+
+    // Links from every intact object, reachable or not
+    let mut referenced: HashSet<&String> = HashSet::new();
+    let mut missing: BTreeMap<String, String> = BTreeMap::new();
+    for (hash, (object_type, links)) in &objects {
+        for (to_type, to_hash) in links {
+            referenced.insert(to_hash);
+            if !present(to_hash) {
+                report.broken_links.push(BrokenLink {
+                    from: format!("{} {}", object_type, hash),
+                    to_type: to_type.clone(),
+                    to_hash: to_hash.clone(),
+                });
+                missing.insert(to_hash.clone(), to_type.clone());
+            }
+        }
+    }
+
+    // Walk from the roots to find what is reachable
+    let mut reachable: HashSet<String> = HashSet::new();
+    let mut stack: Vec<String> = Vec::new();
+    for (name, hash) in repack::collect_named_roots(repo)? {
+        if present(&hash) {
+            stack.push(hash);
+        } else {
+            let to_type = if name.starts_with("index:") { "blob" } else { "commit" };
+            report.broken_links.push(BrokenLink {
+                from: name,
+                to_type: to_type.to_string(),
+                to_hash: hash.clone(),
+            });
+            missing.insert(hash, to_type.to_string());
+        }
+    }
+
+    while let Some(hash) = stack.pop() {
+        if !reachable.insert(hash.clone()) {
+            continue;
+        }
+        if let Some((_, links)) = objects.get(&hash) {
+            for (_, to_hash) in links {
+                if objects.contains_key(to_hash) {
+                    stack.push(to_hash.clone());
+                }
+            }
+        }
+    }
+
+    for (hash, (object_type, _)) in &objects {
+        if !reachable.contains(hash) && !referenced.contains(hash) {
+            report.dangling.push((object_type.clone(), hash.clone()));
+        }
+    }
+
+    report.missing = missing.into_iter().map(|(hash, kind)| (kind, hash)).collect();
+    report.corrupt.sort();
+
+    Ok(report)
+}
+
+/// Print a report in git's format
+fn print_report(report: &FsckReport, show_dangling: bool) {
+    for (hash, reason) in &report.corrupt {
+        println!("error: {}: {}", hash, reason);
+    }
+
+    for link in &report.broken_links {
+        println!("broken link from {}", link.from);
+        println!("              to {} {}", link.to_type, link.to_hash);
+    }
+
+    for (object_type, hash) in &report.missing {
+        println!("missing {} {}", object_type, hash);
+    }
+
+    if show_dangling {
+        for (object_type, hash) in &report.dangling {
+            println!("dangling {} {}", object_type, hash);
+        }
+    }
+}
+
+/// Execute the fsck command
+///
+/// # Arguments
+///
+/// * `show_dangling` - If true, list dangling objects
+///
+/// # Errors
+///
+/// Fails if any object is corrupt, missing or the target of a broken link,
+/// so the process exits non-zero.
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::fsck::run;
+///
+/// run(true).unwrap();
+/// ```
+pub fn run(show_dangling: bool) -> Result<()> {
+    let repo = Repository::find()?;
+    let report = fsck(&repo)?;
+
+    print_report(&report, show_dangling);
+
+    if !report.is_ok() {
+        let problems = report.corrupt.len() + report.missing.len();
+        anyhow::bail!("fsck found {} corrupt or missing objects", problems);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use tempfile::tempdir;
+    use crate::commands::hash_object;
+    use crate::objects::TreeEntry;
+
+    /// Create a commit containing one file and point `main` at it
+    fn commit_file(repo: &Repository, content: &[u8]) -> (String, String) {
+        let blob = hash_object::store_object(repo, "blob", content).unwrap();
+        let mut tree = Tree::new();
+        tree.add_entry(TreeEntry::file("file.txt".to_string(), blob.clone()));
+        let tree_hash = hash_object::store_object(repo, "tree", &tree.serialize().unwrap()).unwrap();
+
+        let commit = format!(
+            "tree {}\nauthor Test <test@example.com> 1000 +0000\ncommitter Test <test@example.com> 1000 +0000\n\nmessage\n",
+            tree_hash
+        );
+        let hash = hash_object::store_object(repo, "commit", commit.as_bytes()).unwrap();
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", hash)).unwrap();
+        (hash, blob)
+    }
+
+    #[test]
+    fn test_fsck_clean_repository() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        commit_file(&repo, b"hello\n");
+
+        let report = fsck(&repo).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked, 3);
+        assert!(report.dangling.is_empty());
+
+        // Packed objects are checked too
+        repack::repack(&repo, true, true).unwrap();
+        let report = fsck(&repo).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked, 3);
+    }
+
+    #[test]
+    fn test_fsck_sha256_repository() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init_with_format(temp.path(), ObjectFormat::Sha256).unwrap();
+        let (commit, blob) = commit_file(&repo, b"hello\n");
+        assert_eq!(commit.len(), 64);
+
+        assert!(fsck(&repo).unwrap().is_ok());
+
+        // Trees and packs carry 32-byte IDs
+        repack::repack(&repo, true, true).unwrap();
+        let report = fsck(&repo).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked, 3);
+        assert_eq!(repo.objects.read(&blob).unwrap().content, b"hello\n");
+    }
+
+    #[test]
+    fn test_fsck_dangling_objects() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        commit_file(&repo, b"hello\n");
+        let orphan = hash_object::store_object(&repo, "blob", b"orphan").unwrap();
+
+        let report = fsck(&repo).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.dangling, vec![("blob".to_string(), orphan)]);
+    }
+
+    #[test]
+    fn test_fsck_missing_object() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let (_, blob) = commit_file(&repo, b"hello\n");
+
+        fs::remove_file(repo.loose_store().object_path(&blob)).unwrap();
+
+        let report = fsck(&repo).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.missing, vec![("blob".to_string(), blob.clone())]);
+        assert_eq!(report.broken_links.len(), 1);
+        assert!(report.broken_links[0].from.starts_with("tree "));
+    }
+
+    #[test]
+    fn test_fsck_corrupt_object() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let (_, blob) = commit_file(&repo, b"hello\n");
+
+        // Replace the blob with a valid object of different content
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"blob 6\0jello\n").unwrap();
+        let path = repo.loose_store().object_path(&blob);
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let report = fsck(&repo).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.corrupt.len(), 1);
+        assert!(report.corrupt[0].1.contains("hash mismatch"));
+
+        // Garbage that doesn't even decompress
+        fs::write(&path, b"not zlib").unwrap();
+        let report = fsck(&repo).unwrap();
+        assert_eq!(report.corrupt.len(), 1);
+        assert!(report.missing.is_empty());
+    }
+
+    #[test]
+    fn test_fsck_alternate_objects_are_present() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("shared")).unwrap();
+        fs::create_dir_all(temp.path().join("clone")).unwrap();
+        let shared = Repository::init(&temp.path().join("shared")).unwrap();
+        let (commit, _) = commit_file(&shared, b"shared\n");
+
+        let clone_root = temp.path().join("clone");
+        Repository::init(&clone_root).unwrap();
+        let info = clone_root.join(".rit/objects/info");
+        fs::create_dir_all(&info).unwrap();
+        fs::write(info.join("alternates"), format!("{}\n", shared.objects_dir().display())).unwrap();
+        let repo = Repository::find_from(&clone_root).unwrap();
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", commit)).unwrap();
+
+        let report = fsck(&repo).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked, 0);
+
+        // Repacking doesn't copy borrowed objects
+        assert!(repack::repack(&repo, true, true).unwrap().is_none());
+        assert!(shared.objects.exists(&commit));
+    }
+}
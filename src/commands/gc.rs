@@ -0,0 +1,56 @@
+//! # GC Command
+//!
+//! Housekeeping for the object database.
+//!
+//! `gc` repacks every reachable object into a single packfile and removes
+//! the loose copies and old packs that became redundant (`repack -d`).
+//! Unreachable objects are left in place unless `--prune` is given.
+//! Finally the commit-graph is rewritten so history walks stay fast.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Pack reachable objects, keep unreachable ones
+//! rit gc
+//!
+//! # Also delete unreachable loose objects
+//! rit gc --prune
+//! ```
+
+use anyhow::Result;
+
+use crate::Repository;
+use crate::commit_graph;
+use crate::commands::repack;
+
+/// Execute the gc command
+///
+/// # Arguments
+///
+/// * `prune` - If true, delete unreachable objects (loose and packed)
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::gc::run;
+///
+/// run(false).unwrap();
+/// ```
+pub fn run(prune: bool) -> Result<()> {
+    let repo = Repository::find()?;
+
+    // When pruning, unreachable packed objects are dropped with the old packs
+    match repack::repack(&repo, true, !prune)? {
+        Some((_, count)) => println!("Packed {} objects", count),
+        None => println!("Nothing to pack"),
+    }
+
+    if prune {
+        let removed = repack::remove_unreachable_loose(&repo)?;
+        println!("Pruned {} unreachable objects", removed);
+    }
+
+    commit_graph::write(&repo)?;
+
+    Ok(())
+}
@@ -23,8 +23,9 @@ use std::fs;
 use anyhow::{Context, Result};
 
 use crate::Repository;
-use crate::objects::commit::Commit;
-use crate::commands::{cat_file, log};
+use crate::refs;
+use crate::commit_graph;
+use crate::commands::log;
 
 /// Represents a commit node in the graph
 #[derive(Debug, Clone)]
@@ -43,24 +44,10 @@ fn collect_all_commits(repo: &Repository) -> Result<HashMap<String, CommitNode>>
     let mut to_visit = VecDeque::new();
     let mut visited = HashSet::new();
     
-    // Start from all branch heads
-    let heads_dir = repo.rit_dir.join("refs").join("heads");
-    if heads_dir.exists() {
-        for entry in fs::read_dir(&heads_dir)
-            .context("Failed to read refs/heads")? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() {
-                if path.file_name().and_then(|n| n.to_str()).is_some() {
-                    if let Ok(commit_hash) = fs::read_to_string(&path) {
-                        let commit_hash = commit_hash.trim().to_string();
-                        if !commit_hash.is_empty() && !visited.contains(&commit_hash) {
-                            to_visit.push_back(commit_hash);
-                        }
-                    }
-                }
-            }
+    // Start from all branch heads (loose or packed)
+    for (_, commit_hash) in refs::list_refs(repo, "refs/heads/")? {
+        if !visited.contains(&commit_hash) {
+            to_visit.push_back(commit_hash);
         }
     }
     
@@ -78,70 +65,57 @@ fn collect_all_commits(repo: &Repository) -> Result<HashMap<String, CommitNode>>
         }
         visited.insert(commit_hash.clone());
         
-        // Read commit
-        let object = match cat_file::read_object(repo, &commit_hash) {
-            Ok(obj) => obj,
-            Err(_) => continue, // Skip if commit doesn't exist
-        };
-        
-        if object.object_type != "commit" {
-            continue;
-        }
-        
-        let commit_content = String::from_utf8_lossy(&object.content);
-        let commit = match Commit::parse(&commit_content) {
+        // Read commit; skip it if it is missing or not a commit
+        let commit = match repo.read_commit(&commit_hash) {
             Ok(c) => c,
             Err(_) => continue,
         };
+
+        // Parents come from the commit-graph when it lists this commit
+        let parents = commit_graph::parents(repo, &commit_hash)?;
         
         let short_hash = commit_hash[..7.min(commit_hash.len())].to_string();
         let message = commit.message.lines().next().unwrap_or("").to_string();
-        let is_merge = commit.parents.len() > 1;
+        let is_merge = parents.len() > 1;
+        
+        // Add parents to visit queue
+        for parent in &parents {
+            if !visited.contains(parent) {
+                to_visit.push_back(parent.clone());
+            }
+        }
         
         // Add to graph
         commits.insert(commit_hash.clone(), CommitNode {
             hash: commit_hash.clone(),
             short_hash,
             message,
-            parents: commit.parents.clone(),
+            parents,
             is_merge,
         });
-        
-        // Add parents to visit queue
-        for parent in commit.parents {
-            if !visited.contains(&parent) {
-                to_visit.push_back(parent);
-            }
-        }
     }
     
     Ok(commits)
 }
 
+/// Map each branch tip in `commits` to its branch name
+fn branch_heads(repo: &Repository, commits: &HashMap<String, CommitNode>) -> Result<HashMap<String, String>> {
+    let mut branch_heads = HashMap::new();
+    for (name, commit_hash) in refs::list_refs(repo, "refs/heads/")? {
+        if commits.contains_key(&commit_hash) {
+            let branch_name = name.trim_start_matches("refs/heads/").to_string();
+            branch_heads.insert(commit_hash, branch_name);
+        }
+    }
+    Ok(branch_heads)
+}
+
 /// Generate ASCII graph output
 fn generate_ascii_graph(commits: &HashMap<String, CommitNode>, repo: &Repository) -> Result<String> {
     let mut output = Vec::new();
     
     // Get all branch heads
-    let mut branch_heads = HashMap::new();
-    let heads_dir = repo.rit_dir.join("refs").join("heads");
-    if heads_dir.exists() {
-        for entry in fs::read_dir(&heads_dir).context("Failed to read refs/heads")? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                if let (Some(branch_name), Ok(commit_hash)) = (
-                    path.file_name().and_then(|n| n.to_str()),
-                    fs::read_to_string(&path)
-                ) {
-                    let commit_hash = commit_hash.trim().to_string();
-                    if commits.contains_key(&commit_hash) {
-                        branch_heads.insert(commit_hash, branch_name.to_string());
-                    }
-                }
-            }
-        }
-    }
+    let branch_heads = branch_heads(repo, commits)?;
     
     // Topological sort (simple BFS from heads)
     let mut visited = HashSet::new();
@@ -149,7 +123,7 @@ fn generate_ascii_graph(commits: &HashMap<String, CommitNode>, repo: &Repository
     let mut levels: HashMap<String, usize> = HashMap::new();
     
     // Initialize with branch heads
-    for (hash, _) in &branch_heads {
+    for hash in branch_heads.keys() {
         queue.push_back(hash.clone());
         levels.insert(hash.clone(), 0);
     }
@@ -230,25 +204,7 @@ fn generate_mermaid_graph(commits: &HashMap<String, CommitNode>, repo: &Reposito
     output.push("graph TD".to_string());
     
     // Get branch heads for labeling
-    let mut branch_heads = HashMap::new();
-    let heads_dir = repo.rit_dir.join("refs").join("heads");
-    if heads_dir.exists() {
-        for entry in fs::read_dir(&heads_dir).context("Failed to read refs/heads")? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                if let (Some(branch_name), Ok(commit_hash)) = (
-                    path.file_name().and_then(|n| n.to_str()),
-                    fs::read_to_string(&path)
-                ) {
-                    let commit_hash = commit_hash.trim().to_string();
-                    if commits.contains_key(&commit_hash) {
-                        branch_heads.insert(commit_hash, branch_name.to_string());
-                    }
-                }
-            }
-        }
-    }
+    let branch_heads = branch_heads(repo, commits)?;
     
     // Generate nodes and edges
     for (hash, node) in commits {
@@ -285,25 +241,7 @@ fn generate_dot_graph(commits: &HashMap<String, CommitNode>, repo: &Repository)
     output.push("    node [shape=box];".to_string());
     
     // Get branch heads for labeling
-    let mut branch_heads = HashMap::new();
-    let heads_dir = repo.rit_dir.join("refs").join("heads");
-    if heads_dir.exists() {
-        for entry in fs::read_dir(&heads_dir).context("Failed to read refs/heads")? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                if let (Some(branch_name), Ok(commit_hash)) = (
-                    path.file_name().and_then(|n| n.to_str()),
-                    fs::read_to_string(&path)
-                ) {
-                    let commit_hash = commit_hash.trim().to_string();
-                    if commits.contains_key(&commit_hash) {
-                        branch_heads.insert(commit_hash, branch_name.to_string());
-                    }
-                }
-            }
-        }
-    }
+    let branch_heads = branch_heads(repo, commits)?;
     
     // Generate nodes
     for (hash, node) in commits {
@@ -367,7 +305,7 @@ pub fn run(format: &str, output_file: Option<String>) -> Result<()> {
     let graph_output = match format {
         "mermaid" => generate_mermaid_graph(&commits, &repo)?,
         "dot" => generate_dot_graph(&commits, &repo)?,
-        "ascii" | _ => generate_ascii_graph(&commits, &repo)?,
+        _ => generate_ascii_graph(&commits, &repo)?,
     };
     
     // Output to file or stdout
@@ -15,10 +15,18 @@
 //! ```
 //!
 //! This is then:
-//! 1. SHA-1 hashed to get the object ID
+//! 1. SHA-1 hashed (SHA-256 in `--object-format=sha256` repositories) to get the object ID
 //! 2. Compressed with zlib
 //! 3. Stored at `.rit/objects/ab/cdef...` (first 2 chars / rest)
 //!
+//! ## Streaming
+//!
+//! Files are never read into memory whole. [`hash_file`] and [`store_file`]
+//! read in [`STREAM_CHUNK_SIZE`] chunks and feed each one to the hash (and, when
+//! storing, the zlib encoder) as it arrives, so hashing a multi-gigabyte file
+//! needs only a fixed-size buffer. The loose object written this way is
+//! byte-identical to one written by [`store_object`].
+//!
 //! ## Usage
 //!
 //! ```bash
@@ -29,16 +37,22 @@
 //! rit hash-object -w README.md
 //! ```
 
-use std::io::Write;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
 use anyhow::{Context, Result};
-use sha1::{Sha1, Digest};
-use flate2::write::ZlibEncoder;
-use flate2::Compression;
 
 use crate::Repository;
+use crate::hash::ObjectFormat;
+
+/// Size of the chunks read when streaming file contents
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
 
 /// Compute the SHA-1 hash of content with the git object header
 ///
+/// For repositories that may use SHA-256, hash with the repository's
+/// format instead: `repo.object_format().hash_object(...)`.
+///
 /// # Arguments
 ///
 /// * `object_type` - The type of object ("blob", "tree", "commit")
@@ -57,19 +71,14 @@ use crate::Repository;
 /// assert_eq!(hash.len(), 40); // SHA-1 produces 40 hex chars
 /// ```
 pub fn hash_content(object_type: &str, content: &[u8]) -> String {
-    // Create header: "blob 13\0"
-    let header = format!("{} {}\0", object_type, content.len());
-
-    // Hash header + content
-    let mut hasher = Sha1::new();
-    hasher.update(header.as_bytes());
-    hasher.update(content);
-
-    hex::encode(hasher.finalize())
+    ObjectFormat::Sha1.hash_object(object_type, content)
 }
 
 /// Store an object in the repository's object database
 ///
+/// The object is written through the repository's
+/// [`ObjectStore`](crate::objects::ObjectStore).
+///
 /// # Arguments
 ///
 /// * `repo` - The repository
@@ -78,32 +87,129 @@ pub fn hash_content(object_type: &str, content: &[u8]) -> String {
 ///
 /// # Returns
 ///
-/// The hex-encoded SHA-1 hash of the stored object
+/// The hex-encoded hash of the stored object
 pub fn store_object(repo: &Repository, object_type: &str, content: &[u8]) -> Result<String> {
-    let hash = hash_content(object_type, content);
-
-    // Create object directory (first 2 chars of hash)
-    let dir = repo.objects_dir().join(&hash[..2]);
-    std::fs::create_dir_all(&dir)?;
-
-    // Object file path (remaining chars)
-    let object_path = dir.join(&hash[2..]);
-
-    // Don't overwrite if already exists (objects are immutable)
-    if !object_path.exists() {
-        // Compress with zlib
-        let header = format!("{} {}\0", object_type, content.len());
-        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(header.as_bytes())?;
-        encoder.write_all(content)?;
-        let compressed = encoder.finish()?;
-
-        // Write to file
-        std::fs::write(&object_path, compressed)
-            .context("Failed to write object file")?;
+    repo.objects.write(object_type, content)
+}
+
+/// Stream an object through the hash, copying the raw (uncompressed) object to `out`
+///
+/// The header is built from `size`, then the content is read in
+/// [`STREAM_CHUNK_SIZE`] chunks. Each chunk goes to the hasher and to `out`
+/// before the next one is read.
+///
+/// # Errors
+///
+/// Fails if the reader yields more or fewer than `size` bytes, which
+/// usually means the file changed while it was being read.
+pub fn stream_object(
+    format: ObjectFormat,
+    object_type: &str,
+    size: u64,
+    reader: &mut dyn Read,
+    out: &mut dyn Write,
+) -> Result<String> {
+    let header = format!("{} {}\0", object_type, size);
+
+    let mut hasher = format.hasher();
+    hasher.update(header.as_bytes());
+    out.write_all(header.as_bytes())?;
+
+    let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+    let mut total: u64 = 0;
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).context("Failed to read object content"),
+        };
+        total += n as u64;
+        if total > size {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        out.write_all(&buffer[..n])?;
     }
 
-    Ok(hash)
+    if total > size {
+        anyhow::bail!("Size mismatch: expected {} bytes but the stream is longer", size);
+    }
+    if total < size {
+        anyhow::bail!("Size mismatch: expected {} bytes but read {}", size, total);
+    }
+
+    Ok(hasher.finalize_hex())
+}
+
+/// Compute the hash of an object read from a stream
+///
+/// Same result as [`ObjectFormat::hash_object`] on the full content, but
+/// only one chunk is held in memory at a time.
+///
+/// # Example
+///
+/// ```
+/// use rit::commands::hash_object::{hash_content, hash_stream};
+/// use rit::hash::ObjectFormat;
+///
+/// let content = b"test content\n";
+/// let hash = hash_stream(ObjectFormat::Sha1, "blob", content.len() as u64, &mut &content[..]).unwrap();
+/// assert_eq!(hash, hash_content("blob", content));
+/// ```
+pub fn hash_stream(
+    format: ObjectFormat,
+    object_type: &str,
+    size: u64,
+    reader: &mut dyn Read,
+) -> Result<String> {
+    stream_object(format, object_type, size, reader, &mut io::sink())
+}
+
+/// Compute the blob hash of a file without reading it into memory
+pub fn hash_file(format: ObjectFormat, path: &Path) -> Result<String> {
+    let (mut file, size) = open_sized(path)?;
+    hash_stream(format, "blob", size, &mut file)
+}
+
+/// Store an object read from a stream
+///
+/// See [`crate::objects::ObjectStore::write_stream`].
+pub fn store_stream(
+    repo: &Repository,
+    object_type: &str,
+    size: u64,
+    reader: &mut dyn Read,
+) -> Result<String> {
+    repo.objects.write_stream(object_type, size, reader)
+}
+
+/// Store a file as a blob without reading it into memory
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::Path;
+/// use rit::Repository;
+/// use rit::commands::hash_object::store_file;
+///
+/// let repo = Repository::find().unwrap();
+/// let hash = store_file(&repo, Path::new("large.bin")).unwrap();
+/// println!("Stored blob: {}", hash);
+/// ```
+pub fn store_file(repo: &Repository, path: &Path) -> Result<String> {
+    let (mut file, size) = open_sized(path)?;
+    store_stream(repo, "blob", size, &mut file)
+}
+
+/// Open a file and get its current size
+fn open_sized(path: &Path) -> Result<(File, u64)> {
+    let file = File::open(path)
+        .context(format!("Failed to read file: {}", path.display()))?;
+    let size = file.metadata()
+        .context(format!("Failed to read file: {}", path.display()))?
+        .len();
+    Ok((file, size))
 }
 
 /// Execute the hash-object command
@@ -125,14 +231,17 @@ pub fn store_object(repo: &Repository, object_type: &str, content: &[u8]) -> Res
 /// run("README.md", true).unwrap();
 /// ```
 pub fn run(file_path: &str, write: bool) -> Result<String> {
-    let content = std::fs::read(file_path)
-        .context(format!("Failed to read file: {}", file_path))?;
+    let path = Path::new(file_path);
 
     let hash = if write {
         let repo = Repository::find()?;
-        store_object(&repo, "blob", &content)?
+        store_file(&repo, path)?
     } else {
-        hash_content("blob", &content)
+        // Outside a repository, hash as SHA-1
+        let format = Repository::find()
+            .map(|repo| repo.object_format())
+            .unwrap_or_default();
+        hash_file(format, path)?
     };
 
     println!("{}", hash);
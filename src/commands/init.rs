@@ -9,6 +9,7 @@
 //! ```text
 //! .rit/
 //! ├── HEAD           # "ref: refs/heads/main\n"
+//! ├── config         # Repository settings, including the object format
 //! ├── objects/       # Object database (empty)
 //! └── refs/
 //!     ├── heads/     # Branch references
@@ -23,28 +24,40 @@
 //!
 //! # Initialize in specific directory
 //! rit init /path/to/repo
+//!
+//! # Use SHA-256 object IDs instead of SHA-1
+//! rit init --object-format=sha256
 //! ```
 
 use std::path::Path;
 use anyhow::Result;
 use crate::Repository;
+use crate::hash::ObjectFormat;
 
 /// Execute the init command
 ///
 /// # Arguments
 ///
 /// * `path` - Optional path where to initialize. Defaults to current directory.
+/// * `object_format` - Optional hash algorithm ("sha1" or "sha256"). Defaults to SHA-1.
 ///
 /// # Example
 ///
 /// ```no_run
 /// use rit::commands::init::run;
 ///
-/// run(None).expect("Failed to initialize repository");
+/// run(None, None).expect("Failed to initialize repository");
 /// ```
-pub fn run(path: Option<&Path>) -> Result<()> {
+pub fn run(path: Option<&Path>, object_format: Option<&str>) -> Result<()> {
     let target = path.unwrap_or(Path::new("."));
-    Repository::init(target)?;
+    match object_format {
+        Some(name) => {
+            Repository::init_with_format(target, ObjectFormat::from_name(name)?)?;
+        }
+        None => {
+            Repository::init(target)?;
+        }
+    }
     Ok(())
 }
 
@@ -56,7 +69,7 @@ mod tests {
     #[test]
     fn test_init_command() {
         let temp = tempdir().unwrap();
-        run(Some(temp.path())).unwrap();
+        run(Some(temp.path()), None).unwrap();
 
         assert!(temp.path().join(".rit").exists());
         assert!(temp.path().join(".rit/objects").exists());
@@ -65,5 +78,15 @@ mod tests {
         let head = std::fs::read_to_string(temp.path().join(".rit/HEAD")).unwrap();
         assert_eq!(head, "ref: refs/heads/main\n");
     }
+
+    #[test]
+    fn test_init_object_format() {
+        let temp = tempdir().unwrap();
+        assert!(run(Some(temp.path()), Some("md5")).is_err());
+
+        run(Some(temp.path()), Some("sha256")).unwrap();
+        let repo = Repository::find_from(temp.path()).unwrap();
+        assert_eq!(repo.object_format(), ObjectFormat::Sha256);
+    }
 }
 
@@ -0,0 +1,205 @@
+//! # LFS Commands
+//!
+//! Inspect and check the large file store (see [`crate::lfs`]).
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # List staged LFS files: oid prefix, '*' if the content is stored, path
+//! rit lfs ls-files
+//!
+//! # Check that every LFS file in history has intact content in the store
+//! rit lfs fsck
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use anyhow::Result;
+
+use crate::Repository;
+use crate::commands::{cat_file, repack};
+use crate::index::Index;
+use crate::lfs::{self, Pointer};
+
+/// An LFS file in the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct LfsFile {
+    /// Path relative to the repository root
+    pub path: String,
+    /// The pointer staged for it
+    pub pointer: Pointer,
+    /// Whether the content is in the local store
+    pub present: bool,
+}
+
+/// Problems found by [`fsck`]
+#[derive(Debug, Default)]
+pub struct LfsFsckReport {
+    /// Number of distinct LFS objects checked
+    pub checked: usize,
+    /// `(path, oid)` of pointers whose content isn't stored
+    pub missing: Vec<(String, String)>,
+    /// `(path, oid)` of stored content that doesn't match its pointer
+    pub corrupt: Vec<(String, String)>,
+}
+
+impl LfsFsckReport {
+    /// True if nothing is missing or corrupt
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+/// List the LFS files in the index, sorted by path
+pub fn ls_files(repo: &Repository) -> Result<Vec<LfsFile>> {
+    let index = Index::load(&repo.index_path(), repo.object_format())?;
+    let mut files = Vec::new();
+
+    for entry in index.entries() {
+        let blob = cat_file::read_object(repo, &entry.hash)?;
+        if let Some(pointer) = Pointer::parse(&blob.content) {
+            let present = lfs::object_path(repo, &pointer.oid).exists();
+            files.push(LfsFile { path: entry.path.clone(), pointer, present });
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Check the stored content of every LFS pointer reachable from refs,
+/// HEAD or the index
+pub fn fsck(repo: &Repository) -> Result<LfsFsckReport> {
+    let index = Index::load(&repo.index_path(), repo.object_format())?;
+    let index_paths: BTreeMap<&str, &str> = index.entries()
+        .map(|entry| (entry.hash.as_str(), entry.path.as_str()))
+        .collect();
+
+    // Pointers by path, so the report comes out in a stable order
+    let mut pointers: BTreeMap<String, Pointer> = BTreeMap::new();
+    for (hash, name) in repack::collect_reachable(repo)? {
+        let object = cat_file::read_object(repo, &hash)?;
+        if object.object_type != "blob" {
+            continue;
+        }
+        if let Some(pointer) = Pointer::parse(&object.content) {
+            let path = match index_paths.get(hash.as_str()) {
+                Some(path) if name.is_empty() => path.to_string(),
+                _ => name,
+            };
+            pointers.insert(path, pointer);
+        }
+    }
+
+    let mut report = LfsFsckReport::default();
+    let mut seen = HashSet::new();
+    for (path, pointer) in pointers {
+        if !seen.insert(pointer.oid.clone()) {
+            continue;
+        }
+        report.checked += 1;
+
+        match lfs::verify_content(repo, &pointer)? {
+            None => report.missing.push((path, pointer.oid)),
+            Some(false) => report.corrupt.push((path, pointer.oid)),
+            Some(true) => {}
+        }
+    }
+
+    Ok(report)
+}
+
+/// Execute `lfs ls-files`
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::lfs::run_ls_files;
+///
+/// run_ls_files().unwrap();
+/// ```
+pub fn run_ls_files() -> Result<()> {
+    let repo = Repository::find()?;
+
+    for file in ls_files(&repo)? {
+        let marker = if file.present { '*' } else { '-' };
+        println!("{} {} {}", &file.pointer.oid[..10], marker, file.path);
+    }
+
+    Ok(())
+}
+
+/// Execute `lfs fsck`
+///
+/// # Errors
+///
+/// Fails if any LFS object is missing or corrupt, so the process exits
+/// non-zero.
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::lfs::run_fsck;
+///
+/// run_fsck().unwrap();
+/// ```
+pub fn run_fsck() -> Result<()> {
+    let repo = Repository::find()?;
+    let report = fsck(&repo)?;
+
+    for (path, oid) in &report.missing {
+        println!("missing: {} ({})", path, oid);
+    }
+    for (path, oid) in &report.corrupt {
+        println!("corrupt: {} ({})", path, oid);
+    }
+
+    if !report.is_ok() {
+        anyhow::bail!(
+            "lfs fsck found {} missing or corrupt objects",
+            report.missing.len() + report.corrupt.len()
+        );
+    }
+
+    println!("lfs fsck OK ({} objects)", report.checked);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+    use crate::index::IndexEntry;
+    use crate::lfs::LfsAttributes;
+
+    #[test]
+    fn test_ls_files_and_fsck() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let attributes = LfsAttributes::parse("*.bin filter=lfs\n");
+
+        let mut index = Index::new(repo.object_format());
+        for (name, content) in [("a.bin", &b"aaaa"[..]), ("b.bin", b"bbbb"), ("notes.txt", b"text\n")] {
+            let path = temp.path().join(name);
+            fs::write(&path, content).unwrap();
+            let hash = lfs::store_file(&repo, &attributes, name, &path).unwrap();
+            index.add_entry(IndexEntry { path: name.to_string(), hash, size: content.len() as u64, ..IndexEntry::default() });
+        }
+        index.save(&repo.index_path()).unwrap();
+
+        let files = ls_files(&repo).unwrap();
+        assert_eq!(files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), vec!["a.bin", "b.bin"]);
+        assert!(files.iter().all(|f| f.present));
+        assert!(fsck(&repo).unwrap().is_ok());
+
+        // Lose one object and corrupt the other
+        fs::remove_file(lfs::object_path(&repo, &files[0].pointer.oid)).unwrap();
+        fs::write(lfs::object_path(&repo, &files[1].pointer.oid), b"changed").unwrap();
+
+        let report = fsck(&repo).unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.missing, vec![("a.bin".to_string(), files[0].pointer.oid.clone())]);
+        assert_eq!(report.corrupt, vec![("b.bin".to_string(), files[1].pointer.oid.clone())]);
+        assert!(!ls_files(&repo).unwrap()[0].present);
+    }
+}
@@ -19,8 +19,9 @@ use std::collections::HashSet;
 use anyhow::{Context, Result};
 
 use crate::Repository;
+use crate::refs;
+use crate::commit_graph;
 use crate::objects::commit::Commit;
-use crate::commands::cat_file;
 
 /// Read the current HEAD commit hash
 ///
@@ -40,17 +41,10 @@ pub fn read_head(repo: &Repository) -> Result<Option<String>> {
     let head_content = head_content.trim();
 
     // Check if it's a ref (e.g., "ref: refs/heads/main")
+    // The ref may be loose or in packed-refs; it doesn't exist yet if
+    // there are no commits
     if let Some(ref_path) = head_content.strip_prefix("ref: ") {
-        let ref_file = repo.rit_dir.join(ref_path.trim());
-        if ref_file.exists() {
-            let commit_hash = std::fs::read_to_string(&ref_file)
-                .context("Failed to read ref file")?
-                .trim()
-                .to_string();
-            return Ok(Some(commit_hash));
-        }
-        // Ref file doesn't exist yet (no commits)
-        return Ok(None);
+        return refs::read_ref(repo, ref_path.trim());
     }
 
     // It's a direct commit hash (detached HEAD)
@@ -112,27 +106,18 @@ fn traverse_commits(
     while !current.is_empty() && !visited.contains(&current) {
         visited.insert(current.clone());
 
-        // Read commit object
-        let object = cat_file::read_object(repo, &current)
+        // Read and parse commit (cached: merges revisit shared history)
+        let commit = repo.read_commit(&current)
             .context(format!("Failed to read commit: {}", current))?;
 
-        if object.object_type != "commit" {
-            break;
-        }
-
-        // Parse commit
-        let commit_content = String::from_utf8_lossy(&object.content);
-        let commit = Commit::parse(&commit_content)?;
-
         // Format and add to output
         output.push(format_commit(&commit, &current, oneline));
 
         // Move to parent (for now, just take first parent)
         // TODO: Handle merge commits properly with graph
-        if let Some(parent) = commit.parents.first() {
-            current = parent.clone();
-        } else {
-            break; // No more parents
+        match commit_graph::parents(repo, &current)?.into_iter().next() {
+            Some(parent) => current = parent,
+            None => break, // No more parents
         }
     }
 
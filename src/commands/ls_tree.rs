@@ -18,7 +18,6 @@
 use anyhow::{Context, Result};
 
 use crate::Repository;
-use crate::objects::Tree;
 use crate::commands::cat_file;
 
 /// Format a tree entry for display
@@ -60,17 +59,10 @@ fn list_tree_recursive(
     name_only: bool,
     prefix: &str,
 ) -> Result<Vec<String>> {
-    // Read the tree object
-    let object = cat_file::read_object(repo, tree_hash)
+    // Read and parse the tree object
+    let tree = repo.read_tree(tree_hash)
         .context(format!("Failed to read tree object: {}", tree_hash))?;
 
-    if object.object_type != "tree" {
-        anyhow::bail!("Not a tree object: {}", tree_hash);
-    }
-
-    // Parse the tree
-    let tree = Tree::parse(&object.content)?;
-
     let mut output = Vec::new();
 
     for entry in &tree.entries {
@@ -143,8 +135,9 @@ fn list_tree_recursive(
 /// ```
 pub fn run(tree_hash: &str, recursive: bool, name_only: bool) -> Result<()> {
     let repo = Repository::find()?;
+    let tree_hash = cat_file::resolve_object(&repo, tree_hash)?;
 
-    let output = list_tree_recursive(&repo, tree_hash, recursive, name_only, "")?;
+    let output = list_tree_recursive(&repo, &tree_hash, recursive, name_only, "")?;
 
     for line in output {
         println!("{}", line);
@@ -156,7 +149,6 @@ pub fn run(tree_hash: &str, recursive: bool, name_only: bool) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::tempdir;
     use crate::Repository;
     use crate::objects::{Tree, TreeEntry};
 
@@ -183,8 +175,7 @@ mod tests {
 
     #[test]
     fn test_list_tree_simple() {
-        let temp = tempdir().unwrap();
-        let repo = Repository::init(temp.path()).unwrap();
+        let repo = Repository::in_memory();
 
         // Create a simple tree
         let mut tree = Tree::new();
@@ -9,15 +9,25 @@
 //! - `cat-file`: Read and display object contents
 //! - `write-tree`: Create tree from working directory
 //! - `commit-tree`: Create commit object
+//! - `repack`: Pack reachable objects into a packfile
 //!
 //! ### Porcelain Commands (User-facing)
 //! - `init`: Initialize repository
 //! - `add`: Stage files
+//! - `add -p`: Stage chosen hunks of files interactively
+//! - `rm`: Remove files from the index and working tree
+//! - `mv`: Move or rename tracked files
 //! - `commit`: Create commit with staged changes
+//! - `reset`: Move the branch and reset the index or working tree
 //! - `log`: Display commit history
 //! - `status`: Show working tree status
 //! - `branch`: Manage branches
 //! - `checkout`: Switch branches
+//! - `gc`: Pack objects and clean up the object database
+//! - `fsck`: Verify object integrity and connectivity
+//! - `prune`: Delete old unreachable loose objects
+//! - `commit-graph`: Write the commit-graph file
+//! - `lfs`: List and check large files kept outside the object database
 
 pub mod init;
 pub mod hash_object;
@@ -27,11 +37,21 @@ pub mod ls_tree;
 pub mod commit_tree;
 pub mod log;
 pub mod add;
+pub mod add_patch;
+pub mod rm;
+pub mod mv;
 pub mod commit;
+pub mod reset;
 pub mod branch;
 pub mod checkout;
 pub mod tag;
 pub mod status;
 pub mod diff;
 pub mod graph;
+pub mod repack;
+pub mod gc;
+pub mod fsck;
+pub mod prune;
+pub mod commit_graph;
+pub mod lfs;
 
@@ -0,0 +1,320 @@
+//! # Mv Command
+//!
+//! Move or rename tracked files and directories, on disk and in the index.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Rename a file
+//! rit mv old.txt new.txt
+//!
+//! # Move files into a directory
+//! rit mv a.txt b.txt docs/
+//!
+//! # Rename a directory
+//! rit mv src lib
+//!
+//! # Show what would happen
+//! rit mv -n old.txt new.txt
+//!
+//! # Overwrite an existing file
+//! rit mv -f draft.txt final.txt
+//! ```
+//!
+//! All sources are checked before anything is moved, and the index is
+//! locked for the whole operation, so either every rename lands in the
+//! index or none does.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::index::Index;
+
+/// Options for [`move_paths`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MvOptions {
+    /// Overwrite an existing destination file (`-f`)
+    pub force: bool,
+    /// Only report what would be moved (`-n`)
+    pub dry_run: bool,
+}
+
+/// Join two repository-relative paths
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Index paths under a directory (`dir/...`)
+fn tracked_under<'a>(index: &'a Index, dir: &str) -> Vec<&'a str> {
+    let prefix = format!("{}/", dir);
+    index.entries.keys()
+        .chain(index.conflicts.keys())
+        .filter(|path| dir.is_empty() || path.starts_with(&prefix))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Work out and check the renames for `rit mv <sources>... <destination>`
+///
+/// If the destination is an existing directory, each source moves inside
+/// it; otherwise there must be a single source, which is renamed.
+///
+/// # Returns
+///
+/// `(source, destination)` pairs, relative to the repository root
+fn plan(repo: &Repository, index: &Index, sources: &[String], destination: &str, force: bool) -> Result<Vec<(String, String)>> {
+    let into_directory = repo.root.join(destination).is_dir();
+    if sources.len() > 1 && !into_directory {
+        anyhow::bail!("fatal: destination '{}' is not a directory", destination);
+    }
+
+    let mut renames = Vec::new();
+    let mut targets = HashSet::new();
+
+    for source in sources {
+        let source = source.trim_end_matches('/').to_string();
+        let name = source.rsplit('/').next().unwrap_or(&source);
+        let target = if into_directory { join(destination, name) } else { destination.to_string() };
+        let fail = |reason: &str| anyhow::anyhow!("fatal: {}, source={}, destination={}", reason, source, target);
+
+        let source_path = repo.root.join(&source);
+        let target_path = repo.root.join(&target);
+
+        if source.is_empty() || !source_path.exists() {
+            return Err(fail("bad source"));
+        }
+        if source == target {
+            return Err(fail("can not move directory into itself"));
+        }
+        if index.get_conflict(&source).is_some() {
+            return Err(fail("conflicted"));
+        }
+
+        if source_path.is_dir() {
+            let tracked = tracked_under(index, &source);
+            if tracked.is_empty() {
+                return Err(fail("source directory is empty"));
+            }
+            if tracked.iter().any(|path| index.get_conflict(path).is_some()) {
+                return Err(fail("conflicted"));
+            }
+            if target.starts_with(&format!("{}/", source)) {
+                return Err(fail("can not move directory into itself"));
+            }
+            if target_path.exists() {
+                return Err(fail("destination already exists"));
+            }
+        } else {
+            if !index.contains(&source) {
+                return Err(fail("not under version control"));
+            }
+            if target_path.is_dir() {
+                return Err(fail("destination already exists"));
+            }
+            if target_path.exists() && !force {
+                return Err(fail("destination exists"));
+            }
+        }
+
+        let parent_exists = Path::new(&target).parent()
+            .is_none_or(|parent| repo.root.join(parent).is_dir());
+        if !parent_exists {
+            return Err(fail("destination directory does not exist"));
+        }
+        if !targets.insert(target.clone()) {
+            return Err(fail("multiple sources for the same target"));
+        }
+
+        renames.push((source, target));
+    }
+
+    Ok(renames)
+}
+
+/// Move tracked files or directories and update their index entries
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `sources` - Paths to move, relative to the repository root
+/// * `destination` - New path, or an existing directory to move into
+/// * `options` - `-f` and `-n`
+///
+/// # Returns
+///
+/// The `(source, destination)` renames, done or (with `dry_run`) planned
+pub fn move_paths(repo: &Repository, sources: &[String], destination: &str, options: MvOptions) -> Result<Vec<(String, String)>> {
+    let (mut index, lock) = Index::load_for_update(&repo.index_path(), repo.object_format())?;
+    let renames = plan(repo, &index, sources, destination.trim_end_matches('/'), options.force)?;
+
+    if options.dry_run {
+        return Ok(renames);
+    }
+
+    for (source, target) in &renames {
+        let source_path = repo.root.join(source);
+        let target_path = repo.root.join(target);
+
+        let moved: Vec<(String, String)> = if source_path.is_dir() {
+            tracked_under(&index, source)
+                .into_iter()
+                .map(|path| (path.to_string(), format!("{}{}", target, &path[source.len()..])))
+                .collect()
+        } else {
+            vec![(source.clone(), target.clone())]
+        };
+
+        fs::rename(&source_path, &target_path)
+            .context(format!("Failed to move {} to {}", source, target))?;
+
+        for (old_path, new_path) in moved {
+            if let Some(mut entry) = index.entries.remove(&old_path) {
+                entry.path = new_path;
+                index.add_entry(entry);
+            }
+        }
+    }
+
+    index.save_locked(lock)?;
+    Ok(renames)
+}
+
+/// Execute the mv command
+///
+/// # Arguments
+///
+/// * `sources` - Files or directories to move, relative to the current directory
+/// * `destination` - New name, or an existing directory to move them into
+/// * `options` - `-f` and `-n`
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::mv::{run, MvOptions};
+///
+/// run(vec!["old.txt".to_string()], "new.txt", MvOptions::default()).unwrap();
+/// ```
+pub fn run(sources: Vec<String>, destination: &str, options: MvOptions) -> Result<()> {
+    let repo = Repository::find()?;
+
+    let sources = sources.iter()
+        .map(|path| repo.relative_path(Path::new(path)))
+        .collect::<Result<Vec<_>>>()?;
+    let destination = repo.relative_path(Path::new(destination))?;
+
+    let renames = move_paths(&repo, &sources, &destination, options)?;
+    if options.dry_run {
+        for (source, target) in renames {
+            println!("Renaming {} to {}", source, target);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::index::{IndexEntry, STAGE_OURS};
+
+    fn stage(repo: &Repository, index: &mut Index, path: &str) {
+        let file_path = repo.root.join(path);
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        fs::write(&file_path, path).unwrap();
+        let metadata = fs::metadata(&file_path).unwrap();
+        index.add_entry(IndexEntry::from_metadata(path.to_string(), "a".repeat(40), &metadata));
+    }
+
+    fn paths(index: &Index) -> Vec<String> {
+        let mut paths: Vec<String> = index.entries.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_mv_file_and_directory() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let mut index = Index::new(repo.object_format());
+        stage(&repo, &mut index, "a.txt");
+        stage(&repo, &mut index, "src/main.rs");
+        stage(&repo, &mut index, "src/util/mod.rs");
+        fs::create_dir(temp.path().join("docs")).unwrap();
+        index.save(&repo.index_path()).unwrap();
+
+        // Dry run changes nothing
+        let dry_run = MvOptions { dry_run: true, ..MvOptions::default() };
+        let planned = move_paths(&repo, &["a.txt".to_string()], "docs", dry_run).unwrap();
+        assert_eq!(planned, vec![("a.txt".to_string(), "docs/a.txt".to_string())]);
+        assert!(temp.path().join("a.txt").exists());
+
+        move_paths(&repo, &["a.txt".to_string()], "docs/", MvOptions::default()).unwrap();
+        move_paths(&repo, &["src".to_string()], "lib", MvOptions::default()).unwrap();
+
+        assert!(temp.path().join("docs/a.txt").exists());
+        assert!(temp.path().join("lib/util/mod.rs").exists());
+        assert!(!temp.path().join("src").exists());
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(paths(&index), vec!["docs/a.txt", "lib/main.rs", "lib/util/mod.rs"]);
+    }
+
+    #[test]
+    fn test_mv_refuses_bad_moves() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let mut index = Index::new(repo.object_format());
+        stage(&repo, &mut index, "a.txt");
+        stage(&repo, &mut index, "b.txt");
+        stage(&repo, &mut index, "dir/c.txt");
+        fs::write(temp.path().join("untracked.txt"), "x").unwrap();
+        index.save(&repo.index_path()).unwrap();
+
+        let mv = |sources: &[&str], destination: &str, force: bool| {
+            let sources: Vec<String> = sources.iter().map(|s| s.to_string()).collect();
+            move_paths(&repo, &sources, destination, MvOptions { force, dry_run: false })
+                .map_err(|e| e.to_string())
+        };
+
+        assert!(mv(&["a.txt"], "b.txt", false).unwrap_err().contains("destination exists"));
+        assert!(mv(&["untracked.txt"], "u.txt", false).unwrap_err().contains("not under version control"));
+        assert!(mv(&["missing.txt"], "m.txt", false).unwrap_err().contains("bad source"));
+        assert!(mv(&["dir"], "dir/inner", false).unwrap_err().contains("into itself"));
+        assert!(mv(&["a.txt", "b.txt"], "new.txt", false).unwrap_err().contains("not a directory"));
+        assert!(mv(&["a.txt"], "nowhere/a.txt", false).unwrap_err().contains("does not exist"));
+
+        // -f replaces the tracked destination
+        mv(&["a.txt"], "b.txt", true).unwrap();
+        assert_eq!(fs::read_to_string(temp.path().join("b.txt")).unwrap(), "a.txt");
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(paths(&index), vec!["b.txt", "dir/c.txt"]);
+    }
+
+    #[test]
+    fn test_mv_refuses_directory_with_conflicts() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let mut index = Index::new(repo.object_format());
+        stage(&repo, &mut index, "dir/clean.txt");
+        stage(&repo, &mut index, "dir/conflicted.txt");
+        let entry = index.get_entry("dir/conflicted.txt").unwrap().clone();
+        index.add_conflict_entry(entry, STAGE_OURS).unwrap();
+        index.save(&repo.index_path()).unwrap();
+
+        let err = move_paths(&repo, &["dir".to_string()], "moved", MvOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("conflicted"));
+
+        // Nothing moved, and the conflict is still recorded at its path
+        assert!(temp.path().join("dir/conflicted.txt").exists());
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(paths(&index), vec!["dir/clean.txt"]);
+        assert!(index.get_conflict("dir/conflicted.txt").is_some());
+    }
+}
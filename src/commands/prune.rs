@@ -0,0 +1,316 @@
+//! # Prune Command
+//!
+//! Delete unreachable loose objects.
+//!
+//! ## How it works
+//!
+//! 1. Walk everything reachable from `refs/heads`, `refs/tags` (and any other
+//!    ref), `HEAD` and the index (see [`repack::collect_reachable`])
+//! 2. Every loose object not in that set is a candidate
+//! 3. Candidates whose file is older than the expiry are deleted
+//!
+//! The expiry is a grace period: an object written by an `add` or
+//! `write-tree` that is still in progress is unreachable for a moment, and
+//! must not be deleted underneath it. Stale `tmp_*` files left by an
+//! interrupted write are removed under the same rule.
+//!
+//! Packed objects are never touched; `rit gc --prune` rewrites packs.
+//! Objects in alternate object stores (`objects/info/alternates`) are read
+//! to follow reachability but never deleted: only this repository's own
+//! loose objects are candidates.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Remove unreachable objects older than two weeks
+//! rit prune
+//!
+//! # Show what would be removed
+//! rit prune --dry-run
+//!
+//! # Remove all unreachable objects regardless of age
+//! rit prune --expire=now
+//! ```
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::commands::repack;
+
+/// Expiry used when none is given
+pub const DEFAULT_EXPIRE: &str = "2.weeks.ago";
+
+/// Parse an expiry like `now`, `never`, `2.weeks.ago` or a Unix timestamp
+///
+/// # Arguments
+///
+/// * `expire` - The expiry string
+/// * `now` - The current time in seconds since the epoch
+///
+/// # Returns
+///
+/// The cutoff in seconds since the epoch (objects last modified at or
+/// before it may be pruned), or `None` for `never`
+///
+/// # Example
+///
+/// ```
+/// use rit::commands::prune::parse_expiry;
+///
+/// assert_eq!(parse_expiry("now", 1000).unwrap(), Some(1000));
+/// assert_eq!(parse_expiry("10.minutes.ago", 1000).unwrap(), Some(400));
+/// assert_eq!(parse_expiry("never", 1000).unwrap(), None);
+/// ```
+pub fn parse_expiry(expire: &str, now: u64) -> Result<Option<u64>> {
+    let expire = expire.trim().to_lowercase();
+
+    match expire.as_str() {
+        "now" | "all" => return Ok(Some(now)),
+        "never" => return Ok(None),
+        _ => {}
+    }
+
+    if let Ok(timestamp) = expire.parse::<u64>() {
+        return Ok(Some(timestamp));
+    }
+
+    // "2.weeks.ago", "2 weeks ago", "1.day.ago"
+    let parts: Vec<&str> = expire
+        .split(|c: char| c == '.' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    if parts.len() != 3 || parts[2] != "ago" {
+        anyhow::bail!("Invalid expiry: {}", expire);
+    }
+
+    let count: u64 = parts[0].parse()
+        .context(format!("Invalid expiry: {}", expire))?;
+
+    let unit = match parts[1].trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        "month" => 30 * 24 * 60 * 60,
+        "year" => 365 * 24 * 60 * 60,
+        other => anyhow::bail!("Invalid expiry unit: {}", other),
+    };
+
+    Ok(Some(now.saturating_sub(count.saturating_mul(unit))))
+}
+
+/// Get a file's modification time in seconds since the epoch
+fn modified_secs(path: &Path) -> Result<u64> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .context(format!("Failed to read metadata: {}", path.display()))?;
+    Ok(modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0))
+}
+
+/// Find (and unless `dry_run`, delete) unreachable loose objects
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `cutoff` - Only objects last modified at or before this time (seconds
+///   since the epoch) are pruned; `None` prunes nothing
+/// * `dry_run` - If true, only report what would be removed
+///
+/// # Returns
+///
+/// The `(hash, type)` of each pruned object
+pub fn prune(repo: &Repository, cutoff: Option<u64>, dry_run: bool) -> Result<Vec<(String, String)>> {
+    let Some(cutoff) = cutoff else {
+        return Ok(Vec::new());
+    };
+
+    let store = repo.loose_store();
+    let reachable = repack::collect_reachable(repo)?;
+    let mut pruned = Vec::new();
+
+    for hash in store.list_loose()? {
+        if reachable.contains_key(&hash) || modified_secs(&store.object_path(&hash))? > cutoff {
+            continue;
+        }
+
+        let object_type = store.read_loose(&hash)
+            .map(|o| o.object_type)
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        if !dry_run {
+            repack::remove_loose_object(repo, &hash)?;
+        }
+        pruned.push((hash, object_type));
+    }
+
+    // Leftovers from interrupted writes
+    if !dry_run {
+        for entry in fs::read_dir(store.objects_dir()).context("Failed to read objects directory")? {
+            let path = entry?.path();
+            let is_tmp = path.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("tmp_"));
+            if is_tmp && modified_secs(&path)? <= cutoff {
+                fs::remove_file(&path)
+                    .context(format!("Failed to remove {}", path.display()))?;
+            }
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Execute the prune command
+///
+/// # Arguments
+///
+/// * `expire` - Only prune objects older than this (default: two weeks)
+/// * `dry_run` - If true, list what would be removed without deleting
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::prune::run;
+///
+/// run(Some("now"), true).unwrap();
+/// ```
+pub fn run(expire: Option<&str>, dry_run: bool) -> Result<()> {
+    let repo = Repository::find()?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let cutoff = parse_expiry(expire.unwrap_or(DEFAULT_EXPIRE), now)?;
+
+    let pruned = prune(&repo, cutoff, dry_run)?;
+
+    if dry_run {
+        for (hash, object_type) in &pruned {
+            println!("{} {}", hash, object_type);
+        }
+    } else {
+        println!("Pruned {} unreachable objects", pruned.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use crate::commands::hash_object;
+    use crate::index::{Index, IndexEntry};
+
+    /// Backdate a loose object's modification time
+    fn age_object(repo: &Repository, hash: &str, secs: u64) {
+        let path = repo.loose_store().object_path(hash);
+        let time = SystemTime::now() - Duration::from_secs(secs);
+        File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        let now = 10_000_000;
+        assert_eq!(parse_expiry("now", now).unwrap(), Some(now));
+        assert_eq!(parse_expiry("never", now).unwrap(), None);
+        assert_eq!(parse_expiry("2.weeks.ago", now).unwrap(), Some(now - 14 * 86400));
+        assert_eq!(parse_expiry("1 hour ago", now).unwrap(), Some(now - 3600));
+        assert_eq!(parse_expiry("12345", now).unwrap(), Some(12345));
+        assert!(parse_expiry("yesterday", now).is_err());
+        assert!(parse_expiry("3.fortnights.ago", now).is_err());
+    }
+
+    #[test]
+    fn test_prune_respects_expiry() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let old = hash_object::store_object(&repo, "blob", b"old orphan").unwrap();
+        let fresh = hash_object::store_object(&repo, "blob", b"fresh orphan").unwrap();
+        age_object(&repo, &old, 30 * 86400);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let cutoff = parse_expiry(DEFAULT_EXPIRE, now).unwrap();
+
+        // Dry run reports but keeps the object
+        let pruned = prune(&repo, cutoff, true).unwrap();
+        assert_eq!(pruned, vec![(old.clone(), "blob".to_string())]);
+        assert!(repo.objects.exists(&old));
+
+        prune(&repo, cutoff, false).unwrap();
+        assert!(!repo.objects.exists(&old));
+        assert!(repo.objects.exists(&fresh));
+
+        assert!(prune(&repo, None, false).unwrap().is_empty());
+        assert!(repo.objects.exists(&fresh));
+    }
+
+    #[test]
+    fn test_prune_keeps_reachable_objects() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        // A staged blob is reachable through the index
+        let staged = hash_object::store_object(&repo, "blob", b"staged\n").unwrap();
+        let mut index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        index.add_entry(IndexEntry {
+            path: "staged.txt".to_string(),
+            hash: staged.clone(),
+            size: 7,
+            mtime: 0,
+            ..IndexEntry::default()
+        });
+        index.save(&repo.index_path()).unwrap();
+
+        let orphan = hash_object::store_object(&repo, "blob", b"orphan").unwrap();
+        age_object(&repo, &staged, 30 * 86400);
+        age_object(&repo, &orphan, 30 * 86400);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let pruned = prune(&repo, Some(now), false).unwrap();
+        assert_eq!(pruned, vec![(orphan, "blob".to_string())]);
+        assert!(repo.objects.exists(&staged));
+    }
+
+    #[test]
+    fn test_prune_never_deletes_alternate_objects() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("shared")).unwrap();
+        fs::create_dir_all(temp.path().join("clone")).unwrap();
+        let shared = Repository::init(&temp.path().join("shared")).unwrap();
+        let shared_orphan = hash_object::store_object(&shared, "blob", b"shared orphan").unwrap();
+        let borrowed = hash_object::store_object(&shared, "blob", b"borrowed\n").unwrap();
+        age_object(&shared, &shared_orphan, 30 * 86400);
+
+        let clone_root = temp.path().join("clone");
+        Repository::init(&clone_root).unwrap();
+        let info = clone_root.join(".rit/objects/info");
+        fs::create_dir_all(&info).unwrap();
+        fs::write(info.join("alternates"), format!("{}\n", shared.objects_dir().display())).unwrap();
+        let repo = Repository::find_from(&clone_root).unwrap();
+
+        // The index points at an object that only the alternate has
+        let mut index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        index.add_entry(IndexEntry {
+            path: "borrowed.txt".to_string(),
+            hash: borrowed.clone(),
+            size: 9,
+            mtime: 0,
+            ..IndexEntry::default()
+        });
+        index.save(&repo.index_path()).unwrap();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(prune(&repo, Some(now), false).unwrap().is_empty());
+        assert!(shared.objects.exists(&shared_orphan));
+        assert!(repo.objects.exists(&borrowed));
+    }
+}
@@ -0,0 +1,353 @@
+//! # Repack Command
+//!
+//! Pack all reachable objects into a single packfile.
+//!
+//! ## How it works
+//!
+//! 1. Start from every ref under `refs/`, `HEAD` and the index
+//! 2. Walk commits, trees, tags and blobs to collect reachable objects
+//! 3. Keep objects already in older packs so nothing packed is lost
+//! 4. Write a new `.pack` + `.idx` pair (see [`crate::pack::write_pack`])
+//! 5. With `-d`, remove the old packs and the loose copies of packed objects
+//!
+//! Unreachable loose objects are never touched here; `rit gc --prune`
+//! removes them. Objects that only exist in an alternate object store
+//! (`objects/info/alternates`) are left there rather than copied into the
+//! new pack.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Write a pack, keep loose objects
+//! rit repack
+//!
+//! # Write a pack and remove redundant loose objects and packs
+//! rit repack -d
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::refs;
+use crate::index::Index;
+use crate::objects::{Commit, Tag, Tree};
+use crate::pack::{self, PackEntry};
+use crate::commands::cat_file;
+
+/// List the hashes of all loose objects in the repository
+pub fn list_loose_objects(repo: &Repository) -> Result<Vec<String>> {
+    repo.loose_store().list_loose()
+}
+
+/// Collect the starting points for reachability
+///
+/// These are every ref under `refs/`, `HEAD`, and every blob in the index
+/// (so staged but uncommitted content is never lost).
+pub fn collect_roots(repo: &Repository) -> Result<Vec<String>> {
+    Ok(collect_named_roots(repo)?
+        .into_iter()
+        .map(|(_, hash)| hash)
+        .collect())
+}
+
+/// Collect the starting points for reachability along with where each came from
+///
+/// # Returns
+///
+/// `(name, hash)` pairs, where the name is a ref like `refs/heads/main`,
+/// `HEAD`, or `index:<path>`
+pub fn collect_named_roots(repo: &Repository) -> Result<Vec<(String, String)>> {
+    let mut roots = Vec::new();
+
+    // Loose and packed refs alike
+    roots.extend(refs::list_refs(repo, "refs/")?);
+
+    if let Some(head) = crate::commands::log::read_head(repo)? {
+        roots.push(("HEAD".to_string(), head));
+    }
+
+    let index = Index::load(&repo.index_path(), repo.object_format())?;
+    // Conflict stages too, so the versions being merged are kept
+    let mut entries: Vec<_> = index.all_entries().collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    for entry in entries {
+        roots.push((format!("index:{}", entry.path), entry.hash.clone()));
+    }
+
+    Ok(roots)
+}
+
+/// Walk the object graph from all roots
+///
+/// # Returns
+///
+/// A map from each reachable object hash to the path it was reached
+/// through (empty for commits and tags)
+pub fn collect_reachable(repo: &Repository) -> Result<HashMap<String, String>> {
+    let mut reachable: HashMap<String, String> = HashMap::new();
+    let mut stack: Vec<(String, String)> = collect_roots(repo)?
+        .into_iter()
+        .map(|hash| (hash, String::new()))
+        .collect();
+
+    while let Some((hash, name)) = stack.pop() {
+        if reachable.contains_key(&hash) {
+            continue;
+        }
+
+        let object = cat_file::read_object(repo, &hash)
+            .context(format!("Missing reachable object: {}", hash))?;
+        reachable.insert(hash.clone(), name.clone());
+
+        match object.object_type.as_str() {
+            "commit" => {
+                let commit = Commit::parse(&String::from_utf8_lossy(&object.content))?;
+                stack.push((commit.tree, String::new()));
+                for parent in commit.parents {
+                    stack.push((parent, String::new()));
+                }
+            }
+            "tree" => {
+                let tree = Tree::parse(&object.content, repo.object_format())?;
+                for entry in tree.entries {
+                    // Gitlinks point into other repositories
+                    if entry.mode == "160000" {
+                        continue;
+                    }
+                    let path = if name.is_empty() {
+                        entry.name.clone()
+                    } else {
+                        format!("{}/{}", name, entry.name)
+                    };
+                    stack.push((entry.hash, path));
+                }
+            }
+            "tag" => {
+                let tag = Tag::parse(&String::from_utf8_lossy(&object.content))?;
+                stack.push((tag.object, String::new()));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(reachable)
+}
+
+/// Remove a loose object file, and its fan-out directory if now empty
+pub fn remove_loose_object(repo: &Repository, hash: &str) -> Result<()> {
+    let dir = repo.objects_dir().join(&hash[..2]);
+    let path = dir.join(&hash[2..]);
+
+    if path.exists() {
+        fs::remove_file(&path)
+            .context(format!("Failed to remove loose object: {}", hash))?;
+    }
+
+    // Ignore errors: the directory still holds other objects
+    let _ = fs::remove_dir(&dir);
+    Ok(())
+}
+
+/// Pack reachable objects into a new packfile
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `delete_redundant` - Remove old packs and loose objects that are now packed
+/// * `keep_unreachable_packed` - Carry objects from old packs over even when
+///   unreachable, so deleting the old packs never loses data
+///
+/// # Returns
+///
+/// The path of the new `.idx` file and the number of objects packed, or
+/// `None` if there was nothing to pack
+pub fn repack(
+    repo: &Repository,
+    delete_redundant: bool,
+    keep_unreachable_packed: bool,
+) -> Result<Option<(PathBuf, usize)>> {
+    let mut objects = collect_reachable(repo)?;
+    let store = repo.loose_store();
+    let old_packs = store.packs()?;
+
+    // Objects borrowed from alternates stay there instead of being copied
+    let borrowed = store.alternate_hashes()?;
+    if !borrowed.is_empty() {
+        objects.retain(|hash, _| {
+            !borrowed.contains(hash)
+                || store.object_path(hash).exists()
+                || old_packs.iter().any(|p| p.contains(hash))
+        });
+    }
+
+    if keep_unreachable_packed {
+        for old in old_packs.iter() {
+            for hash in old.index.hashes() {
+                objects.entry(hash).or_default();
+            }
+        }
+    }
+
+    if objects.is_empty() {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::with_capacity(objects.len());
+    for (hash, name_hint) in &objects {
+        let object = cat_file::read_object(repo, hash)?;
+        entries.push(PackEntry {
+            hash: hash.clone(),
+            object_type: object.object_type,
+            content: object.content,
+            name_hint: name_hint.clone(),
+        });
+    }
+
+    let count = entries.len();
+    let idx_path = pack::write_pack(&repo.objects_dir().join("pack"), entries, repo.object_format())?;
+
+    if delete_redundant {
+        let new_pack = idx_path.with_extension("pack");
+
+        for old in old_packs.iter() {
+            if old.pack_path == new_pack {
+                continue;
+            }
+            fs::remove_file(old.pack_path.with_extension("idx"))
+                .context("Failed to remove old pack index")?;
+            fs::remove_file(&old.pack_path)
+                .context("Failed to remove old pack")?;
+        }
+
+        let packed: HashSet<&String> = objects.keys().collect();
+        for hash in list_loose_objects(repo)? {
+            if packed.contains(&hash) {
+                remove_loose_object(repo, &hash)?;
+            }
+        }
+    }
+
+    // The object store still has the old list of packs
+    repo.objects.refresh();
+
+    Ok(Some((idx_path, count)))
+}
+
+/// Remove loose objects that are not reachable from any root
+///
+/// # Returns
+///
+/// The number of objects removed
+pub fn remove_unreachable_loose(repo: &Repository) -> Result<usize> {
+    let reachable = collect_reachable(repo)?;
+    let mut removed = 0;
+
+    for hash in list_loose_objects(repo)? {
+        if !reachable.contains_key(&hash) {
+            remove_loose_object(repo, &hash)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Execute the repack command
+///
+/// # Arguments
+///
+/// * `delete_redundant` - If true, remove loose objects and old packs that
+///   are now in the new pack
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::repack::run;
+///
+/// run(true).unwrap();
+/// ```
+pub fn run(delete_redundant: bool) -> Result<()> {
+    let repo = Repository::find()?;
+
+    match repack(&repo, delete_redundant, true)? {
+        Some((idx_path, count)) => {
+            let name = idx_path.file_stem()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            println!("Packed {} objects into {}.pack", count, name);
+        }
+        None => println!("Nothing new to pack."),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::commands::hash_object;
+    use crate::objects::TreeEntry;
+
+    /// Create a commit containing a single file and point `main` at it
+    fn commit_file(repo: &Repository, content: &[u8], parent: Option<&str>) -> String {
+        let blob = hash_object::store_object(repo, "blob", content).unwrap();
+        let mut tree = Tree::new();
+        tree.add_entry(TreeEntry::file("file.txt".to_string(), blob));
+        let tree_hash = hash_object::store_object(repo, "tree", &tree.serialize().unwrap()).unwrap();
+
+        let mut commit = format!("tree {}\n", tree_hash);
+        if let Some(parent) = parent {
+            commit.push_str(&format!("parent {}\n", parent));
+        }
+        commit.push_str("author Test <test@example.com> 1000 +0000\n");
+        commit.push_str("committer Test <test@example.com> 1000 +0000\n\nmessage\n");
+        let hash = hash_object::store_object(repo, "commit", commit.as_bytes()).unwrap();
+
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", hash)).unwrap();
+        hash
+    }
+
+    #[test]
+    fn test_collect_reachable() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let first = commit_file(&repo, b"one\n", None);
+        commit_file(&repo, b"two\n", Some(&first));
+
+        // 2 commits + 2 trees + 2 blobs
+        let reachable = collect_reachable(&repo).unwrap();
+        assert_eq!(reachable.len(), 6);
+    }
+
+    #[test]
+    fn test_repack_removes_loose_objects() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let first = commit_file(&repo, b"one\n", None);
+        let second = commit_file(&repo, b"two\n", Some(&first));
+        let orphan = hash_object::store_object(&repo, "blob", b"orphan").unwrap();
+
+        let (_, count) = repack(&repo, true, true).unwrap().unwrap();
+        assert_eq!(count, 6);
+
+        // Only the unreachable object is left loose
+        assert_eq!(list_loose_objects(&repo).unwrap(), vec![orphan]);
+        let object = cat_file::read_object(&repo, &second).unwrap();
+        assert_eq!(object.object_type, "commit");
+
+        // Running again replaces the pack rather than adding another
+        commit_file(&repo, b"three\n", Some(&second));
+        repack(&repo, true, true).unwrap();
+        assert_eq!(repo.loose_store().packs().unwrap().len(), 1);
+
+        // The repository's own store notices the old pack is gone
+        let object = cat_file::read_object(&repo, &second).unwrap();
+        assert_eq!(object.object_type, "commit");
+    }
+}
@@ -0,0 +1,500 @@
+//! # Reset Command
+//!
+//! Move the current branch to a commit, and reset the index (and with
+//! `--hard` the working tree) to match it. With paths, only those index
+//! entries are reset, which unstages them.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Unstage everything
+//! rit reset
+//!
+//! # Unstage one file
+//! rit reset -- file.txt
+//! rit reset HEAD file.txt
+//!
+//! # Move the branch back, keeping its changes staged
+//! rit reset --soft <commit>
+//!
+//! # Move the branch back, keeping its changes in the working tree
+//! rit reset <commit>
+//!
+//! # Move the branch back and discard all changes
+//! rit reset --hard <commit>
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::commands::{add, cat_file, checkout, commit, log, rm};
+use crate::index::{self, Index, IndexEntry};
+use crate::lfs::{self, LfsAttributes};
+use crate::objects::tree;
+
+/// How much a reset changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResetMode {
+    /// Only move the branch (`--soft`)
+    Soft,
+    /// Move the branch and reset the index (`--mixed`)
+    #[default]
+    Mixed,
+    /// Move the branch and reset the index and working tree (`--hard`)
+    Hard,
+}
+
+/// Files of a tree by path, with their blob hash and index mode
+type TreeFiles = BTreeMap<String, (String, u32)>;
+
+/// Collect the files of a tree, with paths relative to the tree's root
+fn tree_files(repo: &Repository, tree_hash: &str, prefix: &str, files: &mut TreeFiles) -> Result<()> {
+    let tree = repo.read_tree(tree_hash)
+        .context(format!("Failed to read tree object: {}", tree_hash))?;
+
+    for entry in &tree.entries {
+        let path = if prefix.is_empty() {
+            entry.name.clone()
+        } else {
+            format!("{}/{}", prefix, entry.name)
+        };
+
+        if entry.is_tree() {
+            tree_files(repo, &entry.hash, &path, files)?;
+        } else {
+            let mode = match entry.mode.as_str() {
+                tree::MODE_EXEC => index::MODE_EXECUTABLE,
+                tree::MODE_SYMLINK => index::MODE_SYMLINK,
+                _ => index::MODE_FILE,
+            };
+            files.insert(path, (entry.hash.clone(), mode));
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve the commit to reset to
+///
+/// An unborn HEAD resolves to `None` when `allow_unborn` is set, which
+/// resets to an empty tree: before the first commit, `rit reset` unstages
+/// everything.
+///
+/// Anything else must be a commit (tags are peeled), since `--soft` writes
+/// it to the branch without reading it.
+fn resolve_target(repo: &Repository, target: &str, allow_unborn: bool) -> Result<Option<String>> {
+    if target == "HEAD" && allow_unborn && log::read_head(repo)?.is_none() {
+        return Ok(None);
+    }
+    let hash = cat_file::resolve_revision(repo, target)?;
+    cat_file::peel_to_commit(repo, &hash).map(Some)
+}
+
+/// The files of a commit's tree (none for `None`)
+fn commit_files(repo: &Repository, commit_hash: Option<&str>) -> Result<TreeFiles> {
+    let mut files = TreeFiles::new();
+    if let Some(hash) = commit_hash {
+        let commit = repo.read_commit(hash)
+            .context(format!("Failed to read commit: {}", hash))?;
+        tree_files(repo, &commit.tree, "", &mut files)?;
+    }
+    Ok(files)
+}
+
+/// Set a path's index entry to a file from the target tree
+///
+/// An existing entry for the same content is kept with its stat data, so
+/// unchanged files aren't hashed again. Otherwise the entry gets no stat
+/// data, which makes the next status compare the working file by content.
+fn reset_entry(index: &mut Index, old: Option<IndexEntry>, path: &str, hash: &str, mode: u32) {
+    match old {
+        Some(entry) if entry.hash == hash && entry.mode == mode => {
+            // Not through add_entry: the file wasn't checked, so a racy
+            // entry must stay racy
+            index.conflicts.remove(path);
+            index.entries.insert(path.to_string(), entry);
+        }
+        _ => index.add_entry(IndexEntry {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            mode,
+            ..IndexEntry::default()
+        }),
+    }
+}
+
+/// Reset the current branch, and with it the index or working tree
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `target` - Branch name, tag, commit hash or `HEAD`
+/// * `mode` - What to reset besides the branch
+///
+/// # Returns
+///
+/// The commit HEAD now points to (`None` when resetting an unborn HEAD)
+pub fn reset(repo: &Repository, target: &str, mode: ResetMode) -> Result<Option<String>> {
+    let commit_hash = resolve_target(repo, target, mode == ResetMode::Mixed)?;
+    let (mut index, lock) = Index::load_for_update(&repo.index_path(), repo.object_format())?;
+
+    match mode {
+        ResetMode::Soft => {
+            if index.has_conflicts() {
+                anyhow::bail!("fatal: Cannot do a soft reset in the middle of a merge.");
+            }
+            // The index is left alone
+            drop(lock);
+        }
+        ResetMode::Mixed => {
+            let files = commit_files(repo, commit_hash.as_deref())?;
+            let mut old_entries = std::mem::take(&mut index.entries);
+            index.conflicts.clear();
+            for (path, (hash, mode)) in &files {
+                reset_entry(&mut index, old_entries.remove(path), path, hash, *mode);
+            }
+            index.save_locked(lock)?;
+        }
+        ResetMode::Hard => {
+            let commit_hash = commit_hash.as_deref().expect("hard reset needs a commit");
+            let files = commit_files(repo, Some(commit_hash))?;
+
+            // Tracked files that the target doesn't have are deleted;
+            // untracked files are kept
+            let tracked: BTreeSet<String> = index.entries.keys()
+                .chain(index.conflicts.keys())
+                .cloned()
+                .collect();
+            for path in tracked.iter().filter(|path| !files.contains_key(*path)) {
+                rm::remove_from_working_tree(&repo.root, path)?;
+            }
+
+            let tree_hash = repo.read_commit(commit_hash)?.tree.clone();
+            checkout::write_tree_to_working_dir(repo, &tree_hash, &repo.root, true)?;
+
+            // Every file was just written, so record its fresh stat data
+            index.entries.clear();
+            index.conflicts.clear();
+            for (path, (hash, _)) in files {
+                let file_path = repo.root.join(&path);
+                let metadata = fs::metadata(&file_path)
+                    .context(format!("Failed to get metadata for: {}", file_path.display()))?;
+                index.add_entry(IndexEntry::from_metadata(path, hash, &metadata));
+            }
+            index.save_locked(lock)?;
+        }
+    }
+
+    if let Some(hash) = &commit_hash {
+        commit::update_ref(repo, hash)?;
+    }
+    Ok(commit_hash)
+}
+
+/// Reset some index entries to their version in a commit
+///
+/// Paths the commit doesn't have are removed from the index. The branch
+/// and working tree are left alone.
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `target` - Branch name, tag, commit hash or `HEAD`
+/// * `pathspecs` - Paths relative to the repository root
+pub fn reset_paths(repo: &Repository, target: &str, pathspecs: &[String]) -> Result<()> {
+    let commit_hash = resolve_target(repo, target, true)?;
+    let files = commit_files(repo, commit_hash.as_deref())?;
+    let (mut index, lock) = Index::load_for_update(&repo.index_path(), repo.object_format())?;
+
+    let paths: BTreeSet<String> = files.keys()
+        .chain(index.entries.keys())
+        .chain(index.conflicts.keys())
+        .filter(|path| pathspecs.iter().any(|spec| add::matches_pathspec(path, spec)))
+        .cloned()
+        .collect();
+
+    for path in paths {
+        match files.get(&path) {
+            Some((hash, mode)) => {
+                let old = index.entries.remove(&path);
+                reset_entry(&mut index, old, &path, hash, *mode);
+            }
+            None => index.remove_entry(&path),
+        }
+    }
+
+    index.save_locked(lock)
+}
+
+/// Tracked files whose working copy differs from the index
+///
+/// # Returns
+///
+/// `(status, path)` pairs, sorted by path, with status `M` (modified) or
+/// `D` (deleted)
+fn unstaged_changes(repo: &Repository) -> Result<Vec<(char, String)>> {
+    let index = Index::load(&repo.index_path(), repo.object_format())?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
+
+    let mut entries: Vec<&IndexEntry> = index.entries().collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut changes = Vec::new();
+    for entry in entries {
+        let file_path = repo.root.join(&entry.path);
+        match fs::metadata(&file_path) {
+            Err(_) => changes.push(('D', entry.path.clone())),
+            Ok(metadata) if index.needs_rehash(entry, &metadata) => {
+                if lfs::hash_file(repo, &lfs_attributes, &entry.path, &file_path)? != entry.hash {
+                    changes.push(('M', entry.path.clone()));
+                }
+            }
+            Ok(_) => {}
+        }
+    }
+    Ok(changes)
+}
+
+/// Execute the reset command
+///
+/// # Arguments
+///
+/// * `args` - An optional commit (default `HEAD`) followed by paths. The
+///   first argument is taken as a path if it doesn't name a commit.
+/// * `paths` - Paths given after `--`
+/// * `mode` - `--soft`, `--mixed` or `--hard` (ignored with paths)
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::reset::{run, ResetMode};
+///
+/// // Unstage a file
+/// run(vec![], vec!["file.txt".to_string()], ResetMode::Mixed).unwrap();
+///
+/// // Throw away all changes since the last commit
+/// run(vec!["HEAD".to_string()], vec![], ResetMode::Hard).unwrap();
+/// ```
+pub fn run(args: Vec<String>, paths: Vec<String>, mode: ResetMode) -> Result<()> {
+    let repo = Repository::find()?;
+
+    // Split the arguments into the target and paths, as in
+    // `rit reset [<commit>] [--] <paths>...`
+    let (target, paths) = match args.split_first() {
+        None => ("HEAD".to_string(), paths),
+        Some((first, rest)) if !paths.is_empty() => {
+            if !rest.is_empty() {
+                anyhow::bail!("fatal: only one commit can be given before '--'");
+            }
+            (first.clone(), paths)
+        }
+        Some((first, rest)) => {
+            if first == "HEAD" || cat_file::resolve_revision(&repo, first).is_ok() {
+                (first.clone(), rest.to_vec())
+            } else if Path::new(first).exists() {
+                ("HEAD".to_string(), args)
+            } else {
+                anyhow::bail!(
+                    "fatal: ambiguous argument '{}': unknown revision or path not in the working tree.\n\
+                     Use '--' to separate paths from revisions, like this:\n\
+                     'rit reset [<commit>] -- [<file>...]'",
+                    first
+                );
+            }
+        }
+    };
+
+    if paths.is_empty() {
+        let commit_hash = reset(&repo, &target, mode)?;
+        match (mode, commit_hash) {
+            (ResetMode::Hard, Some(hash)) => {
+                let commit = repo.read_commit(&hash)?;
+                let subject = commit.message.lines().next().unwrap_or("");
+                println!("HEAD is now at {} {}", &hash[..7], subject);
+            }
+            (ResetMode::Mixed, _) => print_unstaged(&repo)?,
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    match mode {
+        ResetMode::Soft => anyhow::bail!("fatal: Cannot do soft reset with paths."),
+        ResetMode::Hard => anyhow::bail!("fatal: Cannot do hard reset with paths."),
+        ResetMode::Mixed => {}
+    }
+
+    let pathspecs = paths.iter()
+        .map(|path| repo.relative_path(Path::new(path)))
+        .collect::<Result<Vec<_>>>()?;
+    reset_paths(&repo, &target, &pathspecs)?;
+    print_unstaged(&repo)
+}
+
+/// List the changes left in the working tree after a reset
+fn print_unstaged(repo: &Repository) -> Result<()> {
+    let changes = unstaged_changes(repo)?;
+    if !changes.is_empty() {
+        println!("Unstaged changes after reset:");
+        for (status, path) in changes {
+            println!("{}\t{}", status, path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::commands::hash_object;
+    use crate::objects::{commit::Author, Tag, Tree, TreeEntry};
+
+    /// Commit some top-level files on main and return the commit hash
+    fn commit(repo: &Repository, files: &[(&str, &str)], parent: Option<&str>) -> String {
+        let mut tree = Tree::new();
+        for (name, content) in files {
+            let hash = repo.objects.write("blob", content.as_bytes()).unwrap();
+            tree.add_entry(TreeEntry::file(name.to_string(), hash));
+        }
+        tree.sort();
+        let tree_hash = hash_object::store_object(repo, "tree", &tree.serialize().unwrap()).unwrap();
+
+        let parent = parent.map(|p| format!("parent {}\n", p)).unwrap_or_default();
+        let content = format!(
+            "tree {}\n{}author Test <test@example.com> 1234567890 +0000\n\
+             committer Test <test@example.com> 1234567890 +0000\n\nsnapshot\n",
+            tree_hash, parent
+        );
+        let hash = hash_object::store_object(repo, "commit", content.as_bytes()).unwrap();
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", hash)).unwrap();
+        hash
+    }
+
+    fn index_paths(repo: &Repository) -> Vec<String> {
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        let mut paths: Vec<String> = index.entries.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Two commits: a.txt v1, then a.txt v2 plus b.txt, with the index and
+    /// working tree matching the second
+    fn setup() -> (tempfile::TempDir, Repository, String, String) {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let first = commit(&repo, &[("a.txt", "one\n")], None);
+        let second = commit(&repo, &[("a.txt", "two\n"), ("b.txt", "b\n")], Some(&first));
+        fs::write(temp.path().join("a.txt"), "two\n").unwrap();
+        fs::write(temp.path().join("b.txt"), "b\n").unwrap();
+        reset(&repo, "HEAD", ResetMode::Hard).unwrap();
+        (temp, repo, first, second)
+    }
+
+    #[test]
+    fn test_reset_modes() {
+        let (temp, repo, first, second) = setup();
+        assert_eq!(index_paths(&repo), vec!["a.txt", "b.txt"]);
+        assert!(unstaged_changes(&repo).unwrap().is_empty());
+
+        // --soft moves only the branch
+        reset(&repo, &first, ResetMode::Soft).unwrap();
+        assert_eq!(log::read_head(&repo).unwrap(), Some(first.clone()));
+        assert_eq!(index_paths(&repo), vec!["a.txt", "b.txt"]);
+
+        // --mixed also resets the index; the working tree keeps the changes
+        reset(&repo, &second, ResetMode::Soft).unwrap();
+        reset(&repo, &first, ResetMode::Mixed).unwrap();
+        assert_eq!(index_paths(&repo), vec!["a.txt"]);
+        assert_eq!(unstaged_changes(&repo).unwrap(), vec![('M', "a.txt".to_string())]);
+        assert_eq!(fs::read_to_string(temp.path().join("b.txt")).unwrap(), "b\n");
+
+        // --hard also rewrites the working tree, removing files the target
+        // doesn't track but keeping untracked ones
+        reset(&repo, &second, ResetMode::Mixed).unwrap();
+        fs::write(temp.path().join("untracked.txt"), "u").unwrap();
+        reset(&repo, &first, ResetMode::Hard).unwrap();
+        assert_eq!(fs::read_to_string(temp.path().join("a.txt")).unwrap(), "one\n");
+        assert!(!temp.path().join("b.txt").exists());
+        assert!(temp.path().join("untracked.txt").exists());
+        assert!(unstaged_changes(&repo).unwrap().is_empty());
+        assert_eq!(log::read_head(&repo).unwrap(), Some(first));
+    }
+
+    #[test]
+    fn test_reset_paths() {
+        let (temp, repo, first, second) = setup();
+
+        // Stage a change and a new file, then unstage just the change
+        fs::write(temp.path().join("a.txt"), "three\n").unwrap();
+        fs::write(temp.path().join("c.txt"), "c\n").unwrap();
+        let all = add::AddOptions { all: true, ..add::AddOptions::default() };
+        add::add_paths(&repo, &["".to_string()], all).unwrap();
+
+        reset_paths(&repo, "HEAD", &["a.txt".to_string()]).unwrap();
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        let head_files = commit_files(&repo, Some(&second)).unwrap();
+        assert_eq!(index.get_entry("a.txt").unwrap().hash, head_files["a.txt"].0);
+        assert!(index.contains("c.txt"));
+
+        // A path the commit lacks is dropped from the index
+        reset_paths(&repo, &first, &["b.txt".to_string(), "c.txt".to_string()]).unwrap();
+        assert_eq!(index_paths(&repo), vec!["a.txt"]);
+        assert_eq!(log::read_head(&repo).unwrap(), Some(second));
+        assert!(temp.path().join("c.txt").exists());
+    }
+
+    #[test]
+    fn test_reset_refuses_non_commit_targets() {
+        let (_temp, repo, _first, second) = setup();
+        let commit = repo.read_commit(&second).unwrap();
+        let blob = commit_files(&repo, Some(&second)).unwrap()["a.txt"].0.clone();
+
+        for target in [&commit.tree, &blob] {
+            for mode in [ResetMode::Soft, ResetMode::Mixed, ResetMode::Hard] {
+                let err = reset(&repo, target, mode).unwrap_err();
+                assert!(err.to_string().contains("not a commit"));
+            }
+            assert!(reset_paths(&repo, target, &["a.txt".to_string()]).is_err());
+        }
+        assert_eq!(log::read_head(&repo).unwrap(), Some(second));
+    }
+
+    #[test]
+    fn test_reset_to_tag() {
+        let (temp, repo, first, second) = setup();
+
+        // A lightweight tag names the commit directly
+        fs::write(repo.rit_dir.join("refs/tags/v1"), format!("{}\n", first)).unwrap();
+        reset(&repo, "v1", ResetMode::Hard).unwrap();
+        assert_eq!(log::read_head(&repo).unwrap(), Some(first.clone()));
+        assert_eq!(fs::read_to_string(temp.path().join("a.txt")).unwrap(), "one\n");
+
+        // An annotated tag is peeled, so the branch gets the commit, not the tag
+        let tagger = Author::with_timestamp("Test", "test@example.com", 1234567890, "+0000");
+        let tag = Tag::new(&second, "v2", tagger, "second");
+        let tag_hash = hash_object::store_object(&repo, "tag", tag.serialize().as_bytes()).unwrap();
+        fs::write(repo.rit_dir.join("refs/tags/v2"), format!("{}\n", tag_hash)).unwrap();
+        reset(&repo, "v2", ResetMode::Soft).unwrap();
+        assert_eq!(log::read_head(&repo).unwrap(), Some(second.clone()));
+
+        reset_paths(&repo, "v2", &["b.txt".to_string()]).unwrap();
+        assert_eq!(index_paths(&repo), vec!["a.txt", "b.txt"]);
+        assert_eq!(log::read_head(&repo).unwrap(), Some(second));
+    }
+
+    #[test]
+    fn test_reset_unborn_head() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("a.txt"), "a\n").unwrap();
+        add::add_paths(&repo, &["a.txt".to_string()], add::AddOptions::default()).unwrap();
+
+        reset(&repo, "HEAD", ResetMode::Mixed).unwrap();
+        assert!(index_paths(&repo).is_empty());
+        assert!(reset(&repo, "HEAD", ResetMode::Hard).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! # Rm Command
+//!
+//! Remove files from the index, and from the working tree unless `--cached`
+//! is given.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Stop tracking a file and delete it
+//! rit rm old.txt
+//!
+//! # Stop tracking a file but keep it on disk
+//! rit rm --cached secrets.env
+//!
+//! # Remove a whole directory
+//! rit rm -r build/
+//!
+//! # Remove even with uncommitted changes
+//! rit rm -f notes.txt
+//! ```
+//!
+//! ## Safety
+//!
+//! Without `-f`, a file is only removed if nothing would be lost: its
+//! staged content must match HEAD and its working copy must match the
+//! index. With `--cached` the working copy is kept, so only a file whose
+//! staged content matches neither HEAD nor the working copy is refused.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::commands::{log, status};
+use crate::index::Index;
+use crate::lfs::{self, LfsAttributes};
+
+/// Options for [`remove`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RmOptions {
+    /// Only remove from the index, keep the working tree file (`--cached`)
+    pub cached: bool,
+    /// Skip the up-to-date checks (`-f`)
+    pub force: bool,
+    /// Allow directories, removing everything under them (`-r`)
+    pub recursive: bool,
+}
+
+/// Find the index paths a pathspec names
+///
+/// A pathspec names a tracked file, or a directory containing tracked files
+/// (`""` is the repository root).
+///
+/// # Returns
+///
+/// The matching paths, and whether the pathspec named a directory
+fn match_pathspec(tracked: &BTreeSet<&str>, pathspec: &str) -> (Vec<String>, bool) {
+    let spec = pathspec.trim_end_matches('/');
+    if tracked.contains(spec) {
+        return (vec![spec.to_string()], false);
+    }
+
+    let matches = tracked.iter()
+        .filter(|path| spec.is_empty() || path.strip_prefix(spec).is_some_and(|rest| rest.starts_with('/')))
+        .map(|path| path.to_string())
+        .collect();
+    (matches, true)
+}
+
+/// Check whether a working tree file still matches its index entry
+fn working_file_matches(
+    repo: &Repository,
+    index: &Index,
+    lfs_attributes: &LfsAttributes,
+    path: &str,
+) -> Result<bool> {
+    let file_path = repo.root.join(path);
+    let Some(entry) = index.get_entry(path) else {
+        return Ok(true);
+    };
+    let Ok(metadata) = fs::metadata(&file_path) else {
+        // Already deleted: nothing to lose
+        return Ok(true);
+    };
+    if !index.needs_rehash(entry, &metadata) {
+        return Ok(true);
+    }
+    Ok(lfs::hash_file(repo, lfs_attributes, path, &file_path)? == entry.hash)
+}
+
+/// Build the error for files that can't be removed safely
+fn refusal(reason: &str, paths: &[String], hint: &str) -> anyhow::Error {
+    let (noun, verb) = if paths.len() == 1 { ("file", "has") } else { ("files", "have") };
+    let mut message = format!("the following {} {} {}:\n", noun, verb, reason);
+    for path in paths {
+        message.push_str(&format!("    {}\n", path));
+    }
+    message.push_str(hint);
+    anyhow::anyhow!(message)
+}
+
+/// Delete a file and any parent directories it leaves empty
+pub fn remove_from_working_tree(root: &Path, path: &str) -> Result<()> {
+    let file_path = root.join(path);
+    match fs::remove_file(&file_path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).context(format!("Failed to remove {}", file_path.display())),
+    }
+
+    let mut dir: Option<PathBuf> = file_path.parent().map(Path::to_path_buf);
+    while let Some(current) = dir {
+        if current == root || fs::remove_dir(&current).is_err() {
+            break;
+        }
+        dir = current.parent().map(Path::to_path_buf);
+    }
+    Ok(())
+}
+
+/// Remove paths from the index and (unless `cached`) the working tree
+///
+/// Nothing is removed if any pathspec fails to match or any file fails the
+/// safety checks.
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `pathspecs` - Paths relative to the repository root
+/// * `options` - `--cached`, `-f` and `-r`
+///
+/// # Returns
+///
+/// The removed paths, sorted
+pub fn remove(repo: &Repository, pathspecs: &[String], options: RmOptions) -> Result<Vec<String>> {
+    let index_path = repo.index_path();
+    let (mut index, lock) = Index::load_for_update(&index_path, repo.object_format())?;
+
+    // Conflicted paths can be removed too, which resolves them as deleted
+    let tracked: BTreeSet<&str> = index.entries.keys()
+        .chain(index.conflicts.keys())
+        .map(String::as_str)
+        .collect();
+
+    let mut targets = BTreeSet::new();
+    for pathspec in pathspecs {
+        let (matches, is_directory) = match_pathspec(&tracked, pathspec);
+        if matches.is_empty() {
+            anyhow::bail!("fatal: pathspec '{}' did not match any files", pathspec);
+        }
+        if is_directory && !options.recursive {
+            anyhow::bail!("fatal: not removing '{}' recursively without -r", pathspec.trim_end_matches('/'));
+        }
+        targets.extend(matches);
+    }
+
+    if !options.force {
+        check_removable(repo, &index, &targets, options.cached)?;
+    }
+
+    for path in &targets {
+        index.remove_entry(path);
+        if !options.cached {
+            remove_from_working_tree(&repo.root, path)?;
+        }
+    }
+    index.save_locked(lock)?;
+
+    Ok(targets.into_iter().collect())
+}
+
+/// Refuse to remove files whose changes would be lost
+fn check_removable(repo: &Repository, index: &Index, targets: &BTreeSet<String>, cached: bool) -> Result<()> {
+    let head_files: HashMap<String, String> = match log::read_head(repo)? {
+        Some(commit_hash) => {
+            let commit = repo.read_commit(&commit_hash)
+                .context("HEAD does not point to a commit")?;
+            status::get_tree_files(repo, &commit.tree)?
+        }
+        None => HashMap::new(),
+    };
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
+
+    let mut staged_and_modified = Vec::new();
+    let mut staged = Vec::new();
+    let mut modified = Vec::new();
+
+    for path in targets {
+        let Some(entry) = index.get_entry(path) else {
+            continue;
+        };
+        let matches_head = head_files.get(path) == Some(&entry.hash);
+        let matches_file = working_file_matches(repo, index, &lfs_attributes, path)?;
+
+        if !matches_head && !matches_file {
+            staged_and_modified.push(path.clone());
+        } else if !cached && !matches_head {
+            staged.push(path.clone());
+        } else if !cached && !matches_file {
+            modified.push(path.clone());
+        }
+    }
+
+    if !staged_and_modified.is_empty() {
+        return Err(refusal(
+            "staged content different from both the file and the HEAD",
+            &staged_and_modified,
+            "(use -f to force removal)",
+        ));
+    }
+    if !staged.is_empty() {
+        return Err(refusal(
+            "changes staged in the index",
+            &staged,
+            "(use --cached to keep the file, or -f to force removal)",
+        ));
+    }
+    if !modified.is_empty() {
+        return Err(refusal(
+            "local modifications",
+            &modified,
+            "(use --cached to keep the file, or -f to force removal)",
+        ));
+    }
+    Ok(())
+}
+
+/// Execute the rm command
+///
+/// # Arguments
+///
+/// * `paths` - Files or directories to remove, relative to the current directory
+/// * `options` - `--cached`, `-f` and `-r`
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::commands::rm::{run, RmOptions};
+///
+/// // Stop tracking a file but keep it on disk
+/// run(vec!["secrets.env".to_string()], RmOptions { cached: true, ..RmOptions::default() }).unwrap();
+/// ```
+pub fn run(paths: Vec<String>, options: RmOptions) -> Result<()> {
+    let repo = Repository::find()?;
+
+    let pathspecs = paths.iter()
+        .map(|path| repo.relative_path(Path::new(path)))
+        .collect::<Result<Vec<_>>>()?;
+
+    for path in remove(&repo, &pathspecs, options)? {
+        println!("rm '{}'", path);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::index::IndexEntry;
+
+    /// Write a file and stage it, as `rit add` would
+    fn stage(repo: &Repository, index: &mut Index, path: &str, content: &str) {
+        let file_path = repo.root.join(path);
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        fs::write(&file_path, content).unwrap();
+        let hash = lfs::store_file(repo, &LfsAttributes::default(), path, &file_path).unwrap();
+        let metadata = fs::metadata(&file_path).unwrap();
+        index.add_entry(IndexEntry::from_metadata(path.to_string(), hash, &metadata));
+    }
+
+    fn specs(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rm_and_rm_cached() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let mut index = Index::new(repo.object_format());
+        stage(&repo, &mut index, "keep.txt", "keep\n");
+        stage(&repo, &mut index, "gone.txt", "gone\n");
+        index.save(&repo.index_path()).unwrap();
+
+        // Newly added files have staged changes, so need -f
+        let err = remove(&repo, &specs(&["gone.txt"]), RmOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("changes staged in the index"));
+
+        // --cached keeps the file, since the index matches it
+        let cached = RmOptions { cached: true, ..RmOptions::default() };
+        assert_eq!(remove(&repo, &specs(&["keep.txt"]), cached).unwrap(), vec!["keep.txt"]);
+        assert!(temp.path().join("keep.txt").exists());
+
+        let force = RmOptions { force: true, ..RmOptions::default() };
+        remove(&repo, &specs(&["gone.txt"]), force).unwrap();
+        assert!(!temp.path().join("gone.txt").exists());
+
+        assert!(Index::load(&repo.index_path(), repo.object_format()).unwrap().entries.is_empty());
+        assert!(remove(&repo, &specs(&["nothing.txt"]), force).is_err());
+    }
+
+    #[test]
+    fn test_rm_recursive_and_modified_checks() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let mut index = Index::new(repo.object_format());
+        stage(&repo, &mut index, "dir/a.txt", "a\n");
+        stage(&repo, &mut index, "dir/sub/b.txt", "b\n");
+        stage(&repo, &mut index, "dirty.txt", "staged\n");
+        index.save(&repo.index_path()).unwrap();
+
+        let cached = RmOptions { cached: true, ..RmOptions::default() };
+        let err = remove(&repo, &specs(&["dir"]), cached).unwrap_err();
+        assert!(err.to_string().contains("without -r"));
+
+        // Index differs from both HEAD (none) and the modified file
+        fs::write(temp.path().join("dirty.txt"), "changed\n").unwrap();
+        let err = remove(&repo, &specs(&["dirty.txt"]), cached).unwrap_err();
+        assert!(err.to_string().contains("different from both the file and the HEAD"));
+
+        let recursive = RmOptions { recursive: true, force: true, ..RmOptions::default() };
+        let removed = remove(&repo, &specs(&["dir/"]), recursive).unwrap();
+        assert_eq!(removed, vec!["dir/a.txt", "dir/sub/b.txt"]);
+        // Emptied directories go too
+        assert!(!temp.path().join("dir").exists());
+
+        let index = Index::load(&repo.index_path(), repo.object_format()).unwrap();
+        assert_eq!(index.entries.len(), 1);
+        assert!(index.contains("dirty.txt"));
+    }
+}
@@ -8,6 +8,7 @@
 //! rit status
 //! ```
 
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
@@ -15,36 +16,37 @@ use walkdir::WalkDir;
 
 use crate::Repository;
 use crate::index::Index;
-use crate::commands::{log, hash_object, cat_file};
+use crate::lockfile::LockFile;
+use crate::commands::log;
 use crate::ignore;
+use crate::lfs::{self, LfsAttributes};
 
 /// Compare working directory with index and HEAD
 pub fn run() -> Result<()> {
     let repo = Repository::find()?;
     
-    // Load index
-    let index = Index::load(&repo.index_path())?;
+    // Load index, locking it if possible so refreshed stat data can be saved
+    let lock = LockFile::acquire(&repo.index_path()).ok();
+    let mut index = Index::load(&repo.index_path(), repo.object_format())?;
+    let mut refreshed = false;
     
     // Load ignore rules
     let ignore_rules = ignore::load_ignore_rules(&repo.root)?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
     
     // Get HEAD commit
     let head_commit = log::read_head(&repo)?;
     let head_tree_hash = if let Some(commit_hash) = head_commit {
         // Read commit to get tree
-        let commit_obj = cat_file::read_object(&repo, &commit_hash)?;
-        if commit_obj.object_type != "commit" {
-            anyhow::bail!("HEAD does not point to a commit");
-        }
-        let commit_content = String::from_utf8_lossy(&commit_obj.content);
-        let commit = crate::objects::commit::Commit::parse(&commit_content)?;
-        Some(commit.tree)
+        let commit = repo.read_commit(&commit_hash)
+            .context("HEAD does not point to a commit")?;
+        Some(commit.tree.clone())
     } else {
         None
     };
     
     // Collect status information
-    let mut staged = Vec::new();
+    let mut staged: Vec<(String, &str)> = Vec::new();
     let mut modified = Vec::new();
     let mut deleted = Vec::new();
     let mut untracked = Vec::new();
@@ -60,12 +62,20 @@ pub fn run() -> Result<()> {
             .to_string();
         
         if let Some(index_entry) = index.get_entry(&relative_path) {
-            // File is in index - check if it's modified
-            let current_hash = hash_file(&repo, file_path)?;
+            // File is in index - check if it's modified, hashing only if the
+            // stat data doesn't show it unchanged
+            let metadata = fs::metadata(file_path)
+                .context(format!("Failed to get metadata for: {}", file_path.display()))?;
+            if !index.needs_rehash(index_entry, &metadata) {
+                continue;
+            }
+            let current_hash = lfs::hash_file(&repo, &lfs_attributes, &relative_path, file_path)?;
             if current_hash != index_entry.hash {
                 modified.push(relative_path);
+            } else {
+                refreshed |= index.refresh_entry(&relative_path, &metadata);
             }
-        } else {
+        } else if index.get_conflict(&relative_path).is_none() {
             // File is not in index - untracked
             untracked.push(relative_path);
         }
@@ -79,86 +89,108 @@ pub fn run() -> Result<()> {
             if let Some(index_entry) = index.get_entry(path) {
                 // File is in both HEAD and index
                 if index_entry.hash != *hash {
-                    staged.push(path.clone());
+                    staged.push((path.clone(), "modified"));
                 }
-            } else {
-                // File is in HEAD but not in index - deleted
-                deleted.push(path.clone());
+            } else if index.get_conflict(path).is_none() {
+                // File is in HEAD but not in index - deletion staged (rit rm)
+                staged.push((path.clone(), "deleted"));
             }
         }
         
         // Files in index but not in HEAD - newly staged
         for entry in index.entries() {
             if !head_files.contains_key(&entry.path) {
-                staged.push(entry.path.clone());
+                staged.push((entry.path.clone(), "new file"));
             }
         }
     } else {
         // No HEAD - all index entries are staged
         for entry in index.entries() {
-            staged.push(entry.path.clone());
+            staged.push((entry.path.clone(), "new file"));
         }
     }
     
     // Files in index but not in working directory - deleted
+    let working_set: HashSet<&PathBuf> = working_files.iter().collect();
     for entry in index.entries() {
         let file_path = repo.root.join(&entry.path);
-        if !file_path.exists() {
-            if !deleted.contains(&entry.path) {
-                deleted.push(entry.path.clone());
-            }
+        if !working_set.contains(&file_path)
+            && !file_path.exists()
+            && !deleted.contains(&entry.path)
+        {
+            deleted.push(entry.path.clone());
         }
     }
     
+    // Paths with unresolved merge conflicts
+    let unmerged: Vec<(String, &str)> = index.unmerged_paths()
+        .into_iter()
+        .map(|path| (path.to_string(), index.conflicts[path].description()))
+        .collect();
+
+    // Keep the stat data of files found unchanged, so they aren't hashed
+    // again next time. This is only a cache, so it is skipped when another
+    // process holds the lock, and failing to write it is fine.
+    if let (true, Some(lock)) = (refreshed, lock) {
+        let _ = index.save_locked(lock);
+    }
+
     // Print status
-    print_status(&staged, &modified, &deleted, &untracked)?;
+    print_status(&staged, &unmerged, &modified, &deleted, &untracked)?;
     
     Ok(())
 }
 
 /// Get all files in the working directory
-fn get_working_files(root: &Path, ignore_rules: &crate::ignore::IgnoreRules) -> Result<Vec<PathBuf>> {
+///
+/// The repository directory and ignored directories are pruned from the
+/// walk, and file types come from the directory listing, so each file costs
+/// no extra `stat` call.
+pub fn get_working_files(root: &Path, ignore_rules: &crate::ignore::IgnoreRules) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
-    
-    for entry in WalkDir::new(root)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        
-        // Skip .rit directory
-        if path.strip_prefix(root)
-            .map(|p| p.starts_with(".rit"))
-            .unwrap_or(false)
-        {
+
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            return true;
+        };
+
+        // Skip the repository directory (.rit, or .git for Git repositories)
+        if relative.starts_with(crate::RIT_DIR) || relative.starts_with(crate::GIT_DIR) {
+            return false;
+        }
+
+        // Skip ignored directories
+        let relative_str = relative.to_string_lossy().replace('\\', "/");
+        !(entry.file_type().is_dir() && ignore_rules.is_ignored(&relative_str, true))
+    });
+
+    for entry in walker.filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
             continue;
         }
-        
-        // Check ignore rules
+        let path = entry.path();
+
+        // Skip ignored files
         if let Ok(relative) = path.strip_prefix(root) {
             let relative_str = relative.to_string_lossy().replace('\\', "/");
-            
-            // Skip if directory is ignored
-            if path.is_dir() && ignore_rules.is_ignored(&relative_str, true) {
-                continue;
-            }
-            
-            // Skip if file is ignored
-            if path.is_file() && ignore_rules.is_ignored(&relative_str, false) {
+            if ignore_rules.is_ignored(&relative_str, false) {
                 continue;
             }
         }
-        
-        if path.is_file() {
+
+        // Symlinks count if they point at a file, as with `Path::is_file`
+        if entry.file_type().is_file() || path.is_file() {
             files.push(path.to_path_buf());
         }
     }
-    
+
     Ok(files)
 }
 
 /// Get all files in a tree (recursively)
-fn get_tree_files(repo: &Repository, tree_hash: &str) -> Result<std::collections::HashMap<String, String>> {
+///
+/// Returns a map from path (relative to the tree root) to blob hash.
+pub fn get_tree_files(repo: &Repository, tree_hash: &str) -> Result<std::collections::HashMap<String, String>> {
     let mut files = std::collections::HashMap::new();
     get_tree_files_recursive(repo, tree_hash, "", &mut files)?;
     Ok(files)
@@ -171,12 +203,7 @@ fn get_tree_files_recursive(
     prefix: &str,
     files: &mut std::collections::HashMap<String, String>,
 ) -> Result<()> {
-    let object = cat_file::read_object(repo, tree_hash)?;
-    if object.object_type != "tree" {
-        anyhow::bail!("Not a tree object: {}", tree_hash);
-    }
-    
-    let tree = crate::objects::Tree::parse(&object.content)?;
+    let tree = repo.read_tree(tree_hash)?;
     
     for entry in &tree.entries {
         let full_path = if prefix.is_empty() {
@@ -197,16 +224,10 @@ fn get_tree_files_recursive(
     Ok(())
 }
 
-/// Hash a file in the working directory
-fn hash_file(_repo: &Repository, file_path: &Path) -> Result<String> {
-    let content = fs::read(file_path)
-        .context(format!("Failed to read file: {}", file_path.display()))?;
-    Ok(hash_object::hash_content("blob", &content))
-}
-
 /// Print the status output
 fn print_status(
-    staged: &[String],
+    staged: &[(String, &str)],
+    unmerged: &[(String, &str)],
     modified: &[String],
     deleted: &[String],
     untracked: &[String],
@@ -217,11 +238,7 @@ fn print_status(
     let current_branch = if head_path.exists() {
         let head_content = fs::read_to_string(&head_path)?;
         if let Some(ref_path) = head_content.trim().strip_prefix("ref: ") {
-            if let Some(branch) = ref_path.strip_prefix("refs/heads/") {
-                Some(branch.to_string())
-            } else {
-                None
-            }
+            ref_path.strip_prefix("refs/heads/").map(|branch| branch.to_string())
         } else {
             None
         }
@@ -236,7 +253,7 @@ fn print_status(
     }
     println!();
     
-    if staged.is_empty() && modified.is_empty() && deleted.is_empty() && untracked.is_empty() {
+    if staged.is_empty() && unmerged.is_empty() && modified.is_empty() && deleted.is_empty() && untracked.is_empty() {
         println!("nothing to commit, working tree clean");
         return Ok(());
     }
@@ -246,8 +263,19 @@ fn print_status(
         println!("Changes to be committed:");
         println!("  (use \"rit reset HEAD <file>...\" to unstage)");
         println!();
-        for file in staged {
-            println!("        new file:   {}", file);
+        for (file, change) in staged {
+            println!("        {:<12}{}", format!("{}:", change), file);
+        }
+        println!();
+    }
+    
+    // Conflicted files
+    if !unmerged.is_empty() {
+        println!("Unmerged paths:");
+        println!("  (use \"rit add <file>...\" to mark resolution)");
+        println!();
+        for (file, description) in unmerged {
+            println!("        {:<17}{}", format!("{}:", description), file);
         }
         println!();
     }
@@ -301,7 +329,7 @@ mod tests {
     #[test]
     fn test_get_working_files() {
         let temp = tempdir().unwrap();
-        let repo = Repository::init(temp.path()).unwrap();
+        let _repo = Repository::init(temp.path()).unwrap();
         
         std::fs::write(temp.path().join("file1.txt"), "content1").unwrap();
         std::fs::write(temp.path().join("file2.txt"), "content2").unwrap();
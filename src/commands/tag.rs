@@ -18,11 +18,12 @@
 //! rit tag -d v1.0.0
 //! ```
 
-use std::fs;
 use std::path::PathBuf;
 use anyhow::{Context, Result};
 
 use crate::Repository;
+use crate::refs;
+use crate::atomic;
 use crate::commands::log;
 
 /// Get the path to a tag reference file
@@ -32,35 +33,9 @@ fn tag_ref_path(repo: &Repository, tag_name: &str) -> PathBuf {
 
 /// List all tags
 fn list_tags(repo: &Repository) -> Result<()> {
-    let tags_dir = repo.rit_dir.join("refs").join("tags");
-    
-    if !tags_dir.exists() {
-        return Ok(()); // No tags yet
-    }
-
-    let mut tags = Vec::new();
-
-    // Read all tag files
-    if tags_dir.is_dir() {
-        for entry in fs::read_dir(&tags_dir)
-            .context("Failed to read refs/tags directory")? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() {
-                if let Some(tag_name) = path.file_name().and_then(|n| n.to_str()) {
-                    tags.push(tag_name.to_string());
-                }
-            }
-        }
-    }
-
-    // Sort tags
-    tags.sort();
-
-    // Print tags
-    for tag in tags {
-        println!("{}", tag);
+    // Loose and packed tags, sorted by name
+    for (name, _) in refs::list_refs(repo, "refs/tags/")? {
+        println!("{}", name.trim_start_matches("refs/tags/"));
     }
 
     Ok(())
@@ -81,7 +56,7 @@ fn create_lightweight_tag(repo: &Repository, tag_name: &str) -> Result<()> {
     let tag_path = tag_ref_path(repo, tag_name);
 
     // Check if tag already exists
-    if tag_path.exists() {
+    if refs::ref_exists(repo, &format!("refs/tags/{}", tag_name))? {
         anyhow::bail!("fatal: tag '{}' already exists", tag_name);
     }
 
@@ -94,7 +69,7 @@ fn create_lightweight_tag(repo: &Repository, tag_name: &str) -> Result<()> {
     };
 
     // Create the tag file
-    fs::write(&tag_path, format!("{}\n", current_commit))
+    atomic::write(&tag_path, &repo.rit_dir, format!("{}\n", current_commit).as_bytes())
         .context(format!("Failed to create tag file: {}", tag_path.display()))?;
 
     println!("Created tag '{}'", tag_name);
@@ -130,21 +105,11 @@ fn create_annotated_tag(
 
 /// Delete a tag
 fn delete_tag(repo: &Repository, tag_name: &str) -> Result<()> {
-    let tag_path = tag_ref_path(repo, tag_name);
-
-    if !tag_path.exists() {
-        anyhow::bail!("error: tag '{}' not found", tag_name);
-    }
-
-    // Read the tag commit (for display)
-    let tag_commit = fs::read_to_string(&tag_path)
-        .context("Failed to read tag file")?
-        .trim()
-        .to_string();
-
-    // Delete the tag file
-    fs::remove_file(&tag_path)
-        .context(format!("Failed to delete tag file: {}", tag_path.display()))?;
+    // Delete the tag (loose file and any packed-refs entry)
+    let tag_commit = match refs::delete_ref(repo, &format!("refs/tags/{}", tag_name))? {
+        Some(hash) => hash,
+        None => anyhow::bail!("error: tag '{}' not found", tag_name),
+    };
 
     println!("Deleted tag '{}' (was {})", tag_name, &tag_commit[..7.min(tag_commit.len())]);
     Ok(())
@@ -231,7 +196,7 @@ mod tests {
         assert!(tag_path.exists());
         
         // Verify it points to the same commit
-        let tag_content = fs::read_to_string(&tag_path).unwrap();
+        let tag_content = std::fs::read_to_string(&tag_path).unwrap();
         let tag_commit = tag_content.trim();
         assert_eq!(tag_commit, commit_hash);
     }
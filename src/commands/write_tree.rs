@@ -5,7 +5,8 @@
 //! ## How it works
 //!
 //! 1. Walk the current directory (excluding .rit/)
-//! 2. For each file: hash it as a blob, store it
+//! 2. For each file: hash it as a blob, store it (as a pointer if
+//!    `.ritattributes` puts it in LFS)
 //! 3. For each subdirectory: recursively create a tree
 //! 4. Build a tree object with all entries
 //! 5. Store the tree object
@@ -20,11 +21,12 @@
 
 use std::fs;
 use std::path::Path;
-use anyhow::{Context, Result};
+use anyhow::Result;
 
 use crate::Repository;
 use crate::objects::{Tree, TreeEntry};
 use crate::commands::hash_object;
+use crate::lfs::{self, LfsAttributes};
 
 /// Write a tree object for a directory
 ///
@@ -33,14 +35,17 @@ use crate::commands::hash_object;
 /// * `repo` - The repository
 /// * `dir_path` - Path to the directory to process
 /// * `base_path` - Base path for relative names (for recursion)
+/// * `lfs_attributes` - Which files are stored as LFS pointers
 ///
 /// # Returns
 ///
 /// The SHA-1 hash of the created tree object
+#[allow(clippy::only_used_in_recursion)]
 fn write_tree_recursive(
     repo: &Repository,
     dir_path: &Path,
     base_path: &Path,
+    lfs_attributes: &LfsAttributes,
 ) -> Result<String> {
     let mut tree = Tree::new();
 
@@ -57,8 +62,8 @@ fn write_tree_recursive(
         let file_name = entry.file_name();
         let file_name_str = file_name.to_string_lossy();
 
-        // Skip .rit directory
-        if file_name_str == ".rit" {
+        // Skip the repository directory (.rit, or .git for Git repositories)
+        if file_name_str == crate::RIT_DIR || file_name_str == crate::GIT_DIR {
             continue;
         }
 
@@ -66,9 +71,6 @@ fn write_tree_recursive(
 
         if metadata.is_file() {
             // Hash and store the file as a blob
-            let content = fs::read(&path)
-                .context(format!("Failed to read file: {}", path.display()))?;
-
             // Determine if file is executable
             let mode = if is_executable(&metadata) {
                 crate::objects::tree::MODE_EXEC
@@ -76,7 +78,12 @@ fn write_tree_recursive(
                 crate::objects::tree::MODE_FILE
             };
 
-            let blob_hash = hash_object::store_object(repo, "blob", &content)?;
+            // LFS paths are matched relative to the repository root
+            let relative_path = path.strip_prefix(&repo.root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let blob_hash = lfs::store_file(repo, lfs_attributes, &relative_path, &path)?;
 
             // Add entry to tree
             tree.add_entry(TreeEntry::new(
@@ -86,7 +93,7 @@ fn write_tree_recursive(
             ));
         } else if metadata.is_dir() {
             // Recursively create tree for subdirectory
-            let subtree_hash = write_tree_recursive(repo, &path, base_path)?;
+            let subtree_hash = write_tree_recursive(repo, &path, base_path, lfs_attributes)?;
 
             // Add subtree entry
             tree.add_entry(TreeEntry::directory(
@@ -141,7 +148,8 @@ pub fn run() -> Result<String> {
     let current_dir = std::env::current_dir()?;
 
     // Create tree from current directory
-    let tree_hash = write_tree_recursive(&repo, &current_dir, &current_dir)?;
+    let lfs_attributes = LfsAttributes::load(&repo.root)?;
+    let tree_hash = write_tree_recursive(&repo, &current_dir, &current_dir, &lfs_attributes)?;
 
     println!("{}", tree_hash);
     Ok(tree_hash)
@@ -166,7 +174,7 @@ mod tests {
         std::env::set_current_dir(temp.path()).unwrap();
 
         // Write tree
-        let hash = write_tree_recursive(&repo, temp.path(), temp.path()).unwrap();
+        let hash = write_tree_recursive(&repo, temp.path(), temp.path(), &LfsAttributes::default()).unwrap();
 
         // Verify hash is valid (40 hex chars)
         assert_eq!(hash.len(), 40);
@@ -186,7 +194,7 @@ mod tests {
         fs::create_dir(temp.path().join("subdir")).unwrap();
         fs::write(temp.path().join("subdir").join("sub.txt"), b"sub").unwrap();
 
-        let hash = write_tree_recursive(&repo, temp.path(), temp.path()).unwrap();
+        let hash = write_tree_recursive(&repo, temp.path(), temp.path(), &LfsAttributes::default()).unwrap();
 
         // Should have created tree with 2 entries: root.txt and subdir
         assert_eq!(hash.len(), 40);
@@ -198,5 +206,21 @@ mod tests {
         // Restore directory
         std::env::set_current_dir("/").unwrap();
     }
-}
 
+    #[test]
+    fn test_write_tree_stores_lfs_pointers() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        fs::create_dir(temp.path().join("assets")).unwrap();
+        fs::write(temp.path().join("assets").join("big.bin"), [0u8; 1000]).unwrap();
+        let attributes = LfsAttributes::parse("*.bin filter=lfs\n");
+
+        let hash = write_tree_recursive(&repo, temp.path(), temp.path(), &attributes).unwrap();
+
+        let read_tree = |hash: &str| Tree::parse(&repo.objects.read(hash).unwrap().content, repo.object_format()).unwrap();
+        let assets = read_tree(&read_tree(&hash).entries[0].hash);
+        let blob = repo.objects.read(&assets.entries[0].hash).unwrap().content;
+        assert_eq!(lfs::Pointer::parse(&blob), Some(lfs::Pointer::for_content(&[0u8; 1000])));
+    }
+}
@@ -0,0 +1,692 @@
+//! # Commit-Graph
+//!
+//! Walking history normally means inflating and parsing every commit just
+//! to learn its parents. The commit-graph file
+//! (`objects/info/commit-graph`) stores that information for every
+//! reachable commit in a compact, binary-searchable table, in the same
+//! format Git uses:
+//!
+//! ```text
+//! CGPH 01 <hash version> <chunk count> 00   # header
+//! <chunk id> <offset> ...                   # chunk lookup, 12 bytes each,
+//! 00000000 <end offset>                     #   ending with a zero id
+//! OIDF  fanout[256]                         # 4-byte counts by first byte
+//! OIDL  hashes[N]                           # sorted object IDs
+//! CDAT  N x (tree, parent1, parent2, gen|date)
+//! EDGE  extra parents of octopus merges
+//! <checksum>
+//! ```
+//!
+//! Parents are stored as positions in the sorted hash list. `CDAT` keeps
+//! each commit's root tree, its commit date (34 bits) and its generation
+//! number (30 bits): 1 for root commits, otherwise one more than the
+//! largest generation of its parents. A commit can only be an ancestor of
+//! commits with a larger generation, which lets ancestry checks stop early
+//! (see [`is_ancestor`]).
+//!
+//! The file is an optional cache: [`parents`] falls back to parsing the
+//! commit when it isn't listed, so a missing or stale commit-graph only
+//! costs speed. It is written by `rit commit-graph write` and `rit gc`.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::atomic;
+use crate::hash::ObjectFormat;
+use crate::objects::Commit;
+
+/// Magic bytes at the start of a commit-graph file
+const SIGNATURE: [u8; 4] = *b"CGPH";
+
+/// The only commit-graph version
+const VERSION: u8 = 1;
+
+/// Chunk: 256-entry fanout table
+const CHUNK_OID_FANOUT: [u8; 4] = *b"OIDF";
+/// Chunk: sorted object IDs
+const CHUNK_OID_LOOKUP: [u8; 4] = *b"OIDL";
+/// Chunk: tree, parents, generation and date of each commit
+const CHUNK_COMMIT_DATA: [u8; 4] = *b"CDAT";
+/// Chunk: parent lists of commits with more than two parents
+const CHUNK_EXTRA_EDGES: [u8; 4] = *b"EDGE";
+
+/// Parent slot value for "no parent"
+const PARENT_NONE: u32 = 0x7000_0000;
+/// Flag on the second parent slot: the rest are in the `EDGE` chunk
+const PARENT_EXTRA_EDGES: u32 = 0x8000_0000;
+/// Flag on the last entry of a parent list in the `EDGE` chunk
+const LAST_EDGE: u32 = 0x8000_0000;
+
+/// Largest generation number that fits in the file (30 bits)
+pub const GENERATION_MAX: u32 = 0x3fff_ffff;
+
+/// Mask for the 34-bit commit date
+const DATE_MASK: u64 = 0x3_ffff_ffff;
+
+/// The path of the commit-graph file inside an objects directory
+pub fn graph_path(objects_dir: &Path) -> PathBuf {
+    objects_dir.join("info").join("commit-graph")
+}
+
+/// What the commit-graph records about one commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphCommit {
+    /// Root tree of the commit
+    pub tree: String,
+    /// Parent commit hashes, in order
+    pub parents: Vec<String>,
+    /// Generation number (1 for root commits)
+    pub generation: u32,
+    /// Committer timestamp (seconds since the epoch)
+    pub commit_time: u64,
+}
+
+/// A parsed commit-graph file
+#[derive(Debug, Clone)]
+pub struct CommitGraph {
+    /// The whole file
+    data: Vec<u8>,
+    /// Cumulative commit counts by first hash byte
+    fanout: Vec<u32>,
+    /// Offset of the `OIDL` chunk
+    lookup_offset: usize,
+    /// Offset of the `CDAT` chunk
+    data_offset: usize,
+    /// Offset and length of the `EDGE` chunk, if any
+    edges: Option<(usize, usize)>,
+    /// The object format the IDs are in
+    format: ObjectFormat,
+}
+
+impl CommitGraph {
+    /// Read the commit-graph of an objects directory, if there is one
+    pub fn open(objects_dir: &Path, format: ObjectFormat) -> Result<Option<Self>> {
+        let path = graph_path(objects_dir);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let data = fs::read(&path)
+            .context(format!("Failed to read {}", path.display()))?;
+        Self::parse(data, format)
+            .context(format!("Invalid commit-graph: {}", path.display()))
+            .map(Some)
+    }
+
+    /// Parse a commit-graph file whose object IDs are in `format`
+    ///
+    /// The chunk layout and every parent position are checked, so lookups
+    /// on the result never index out of range.
+    pub fn parse(data: Vec<u8>, format: ObjectFormat) -> Result<Self> {
+        let hash_len = format.raw_len();
+
+        if data.len() < 8 || data[..4] != SIGNATURE {
+            anyhow::bail!("missing commit-graph signature");
+        }
+        if data[4] != VERSION {
+            anyhow::bail!("unsupported commit-graph version {}", data[4]);
+        }
+        if data[5] != hash_version(format) {
+            anyhow::bail!("commit-graph hash version {} does not match {}", data[5], format);
+        }
+
+        // Chunk lookup table: (id, offset) pairs, terminated by a zero id
+        let chunk_count = data[6] as usize;
+        let table_end = 8 + (chunk_count + 1) * 12;
+        if data.len() < table_end + hash_len {
+            anyhow::bail!("truncated commit-graph chunk table");
+        }
+
+        let mut chunks: HashMap<[u8; 4], (usize, usize)> = HashMap::new();
+        for i in 0..chunk_count {
+            let pos = 8 + i * 12;
+            let id: [u8; 4] = data[pos..pos + 4].try_into()?;
+            let start = read_u64(&data, pos + 4) as usize;
+            let end = read_u64(&data, pos + 16) as usize;
+            if start < table_end || end < start || end > data.len() - hash_len {
+                anyhow::bail!("commit-graph chunk {} out of range", String::from_utf8_lossy(&id));
+            }
+            chunks.insert(id, (start, end - start));
+        }
+
+        let chunk = |id: [u8; 4]| {
+            chunks.get(&id).copied().with_context(|| {
+                format!("commit-graph is missing the {} chunk", String::from_utf8_lossy(&id))
+            })
+        };
+
+        let (fanout_offset, fanout_len) = chunk(CHUNK_OID_FANOUT)?;
+        if fanout_len != 256 * 4 {
+            anyhow::bail!("commit-graph fanout chunk has the wrong size");
+        }
+        let fanout: Vec<u32> = (0..256).map(|i| read_u32(&data, fanout_offset + i * 4)).collect();
+        if fanout.windows(2).any(|w| w[0] > w[1]) {
+            anyhow::bail!("commit-graph fanout is not sorted");
+        }
+        let count = fanout[255] as usize;
+
+        let (lookup_offset, lookup_len) = chunk(CHUNK_OID_LOOKUP)?;
+        let (data_offset, data_len) = chunk(CHUNK_COMMIT_DATA)?;
+        if lookup_len != count * hash_len || data_len != count * (hash_len + 16) {
+            anyhow::bail!("commit-graph chunk sizes don't match its {} commits", count);
+        }
+
+        let graph = Self {
+            edges: chunks.get(&CHUNK_EXTRA_EDGES).copied(),
+            data,
+            fanout,
+            lookup_offset,
+            data_offset,
+            format,
+        };
+
+        for position in 0..count {
+            graph.parent_positions(position)?;
+        }
+
+        Ok(graph)
+    }
+
+    /// Number of commits in the graph
+    pub fn len(&self) -> usize {
+        self.fanout[255] as usize
+    }
+
+    /// Whether the graph has no commits
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The raw object ID at a position
+    fn raw_hash(&self, position: usize) -> &[u8] {
+        let hash_len = self.format.raw_len();
+        let start = self.lookup_offset + position * hash_len;
+        &self.data[start..start + hash_len]
+    }
+
+    /// The hex object ID at a position
+    fn hash_at(&self, position: usize) -> String {
+        hex::encode(self.raw_hash(position))
+    }
+
+    /// Find a commit's position in the sorted hash list
+    fn position(&self, hash: &str) -> Option<usize> {
+        let raw = hex::decode(hash).ok()?;
+        if raw.len() != self.format.raw_len() {
+            return None;
+        }
+
+        let first = raw[0] as usize;
+        let mut low = if first == 0 { 0 } else { self.fanout[first - 1] as usize };
+        let mut high = self.fanout[first] as usize;
+
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.raw_hash(mid).cmp(&raw[..]) {
+                std::cmp::Ordering::Equal => return Some(mid),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    /// Check whether a commit is in the graph
+    pub fn contains(&self, hash: &str) -> bool {
+        self.position(hash).is_some()
+    }
+
+    /// Iterate over the commit hashes in sorted order
+    pub fn hashes(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len()).map(|position| self.hash_at(position))
+    }
+
+    /// Offset of a commit's `CDAT` record
+    fn record(&self, position: usize) -> usize {
+        self.data_offset + position * (self.format.raw_len() + 16)
+    }
+
+    /// Decode the parent positions of the commit at `position`
+    fn parent_positions(&self, position: usize) -> Result<Vec<usize>> {
+        let record = self.record(position) + self.format.raw_len();
+        let first = read_u32(&self.data, record);
+        let second = read_u32(&self.data, record + 4);
+
+        let mut parents = Vec::new();
+        if first != PARENT_NONE {
+            parents.push(first);
+        }
+
+        if second & PARENT_EXTRA_EDGES != 0 {
+            let (edges_offset, edges_len) = self.edges
+                .context("commit-graph has an octopus merge but no EDGE chunk")?;
+            let mut index = (second & !PARENT_EXTRA_EDGES) as usize;
+            loop {
+                if (index + 1) * 4 > edges_len {
+                    anyhow::bail!("commit-graph extra edge out of range");
+                }
+                let edge = read_u32(&self.data, edges_offset + index * 4);
+                parents.push(edge & !LAST_EDGE);
+                if edge & LAST_EDGE != 0 {
+                    break;
+                }
+                index += 1;
+            }
+        } else if second != PARENT_NONE {
+            parents.push(second);
+        }
+
+        parents.into_iter()
+            .map(|parent| {
+                let parent = parent as usize;
+                if parent >= self.len() {
+                    anyhow::bail!("commit-graph parent position {} out of range", parent);
+                }
+                Ok(parent)
+            })
+            .collect()
+    }
+
+    /// The generation number of a commit, if it is in the graph
+    pub fn generation(&self, hash: &str) -> Option<u32> {
+        let position = self.position(hash)?;
+        let record = self.record(position) + self.format.raw_len() + 8;
+        Some(read_u32(&self.data, record) >> 2)
+    }
+
+    /// Look up a commit's tree, parents, generation and date
+    ///
+    /// # Returns
+    ///
+    /// `None` if the commit isn't in the graph
+    pub fn get(&self, hash: &str) -> Option<GraphCommit> {
+        let position = self.position(hash)?;
+        let record = self.record(position);
+        let hash_len = self.format.raw_len();
+
+        let tree = hex::encode(&self.data[record..record + hash_len]);
+        let parents = self.parent_positions(position).ok()?
+            .into_iter()
+            .map(|parent| self.hash_at(parent))
+            .collect();
+
+        let high = read_u32(&self.data, record + hash_len + 8);
+        let low = read_u32(&self.data, record + hash_len + 12);
+
+        Some(GraphCommit {
+            tree,
+            parents,
+            generation: high >> 2,
+            commit_time: ((high as u64 & 0x3) << 32) | low as u64,
+        })
+    }
+}
+
+/// The hash version byte for an object format
+fn hash_version(format: ObjectFormat) -> u8 {
+    match format {
+        ObjectFormat::Sha1 => 1,
+        ObjectFormat::Sha256 => 2,
+    }
+}
+
+/// Encode a commit-graph for a set of commits
+///
+/// The set must be closed under parents: every parent of every commit is
+/// in it too.
+pub fn encode(commits: &HashMap<String, Commit>, format: ObjectFormat) -> Result<Vec<u8>> {
+    let mut hashes: Vec<&String> = commits.keys().collect();
+    hashes.sort();
+    let positions: HashMap<&str, u32> = hashes.iter()
+        .enumerate()
+        .map(|(i, hash)| (hash.as_str(), i as u32))
+        .collect();
+
+    let generations = generation_numbers(commits)?;
+
+    let mut fanout = [0u32; 256];
+    let mut lookup = Vec::new();
+    let mut commit_data = Vec::new();
+    let mut edges: Vec<u32> = Vec::new();
+
+    for hash in &hashes {
+        let raw = hex::decode(hash).context(format!("Invalid commit hash: {}", hash))?;
+        fanout[raw[0] as usize] += 1;
+        lookup.extend_from_slice(&raw);
+
+        let commit = &commits[*hash];
+        commit_data.extend(hex::decode(&commit.tree).context(format!("Invalid tree hash in {}", hash))?);
+
+        let parents: Vec<u32> = commit.parents.iter()
+            .map(|parent| positions[parent.as_str()])
+            .collect();
+        let (first, second) = match parents.as_slice() {
+            [] => (PARENT_NONE, PARENT_NONE),
+            [first] => (*first, PARENT_NONE),
+            [first, second] => (*first, *second),
+            [first, rest @ ..] => {
+                let start = edges.len() as u32;
+                edges.extend_from_slice(rest);
+                *edges.last_mut().unwrap() |= LAST_EDGE;
+                (*first, PARENT_EXTRA_EDGES | start)
+            }
+        };
+        commit_data.extend_from_slice(&first.to_be_bytes());
+        commit_data.extend_from_slice(&second.to_be_bytes());
+
+        let time = commit.committer.timestamp & DATE_MASK;
+        let generation = generations[hash.as_str()];
+        commit_data.extend_from_slice(&((generation << 2) | (time >> 32) as u32).to_be_bytes());
+        commit_data.extend_from_slice(&(time as u32).to_be_bytes());
+    }
+
+    let mut total = 0;
+    let mut fanout_chunk = Vec::with_capacity(256 * 4);
+    for count in fanout {
+        total += count;
+        fanout_chunk.extend_from_slice(&total.to_be_bytes());
+    }
+
+    let mut chunks: Vec<([u8; 4], Vec<u8>)> = vec![
+        (CHUNK_OID_FANOUT, fanout_chunk),
+        (CHUNK_OID_LOOKUP, lookup),
+        (CHUNK_COMMIT_DATA, commit_data),
+    ];
+    if !edges.is_empty() {
+        chunks.push((CHUNK_EXTRA_EDGES, edges.iter().flat_map(|e| e.to_be_bytes()).collect()));
+    }
+
+    let mut out = SIGNATURE.to_vec();
+    out.extend_from_slice(&[VERSION, hash_version(format), chunks.len() as u8, 0]);
+
+    let mut offset = (8 + (chunks.len() + 1) * 12) as u64;
+    for (id, chunk) in &chunks {
+        out.extend_from_slice(id);
+        out.extend_from_slice(&offset.to_be_bytes());
+        offset += chunk.len() as u64;
+    }
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&offset.to_be_bytes());
+
+    for (_, chunk) in chunks {
+        out.extend(chunk);
+    }
+
+    let checksum = format.digest(&out);
+    out.extend(checksum);
+    Ok(out)
+}
+
+/// Compute the generation number of every commit
+///
+/// Iterative so that long histories can't overflow the stack.
+fn generation_numbers(commits: &HashMap<String, Commit>) -> Result<HashMap<&str, u32>> {
+    let mut generations: HashMap<&str, u32> = HashMap::new();
+
+    for start in commits.keys() {
+        let mut stack = vec![start.as_str()];
+
+        while let Some(&hash) = stack.last() {
+            if generations.contains_key(hash) {
+                stack.pop();
+                continue;
+            }
+
+            let commit = &commits[hash];
+            let mut pending = false;
+            let mut generation = 0;
+            for parent in &commit.parents {
+                if !commits.contains_key(parent) {
+                    anyhow::bail!("commit {} has parent {} which is not in the graph", hash, parent);
+                }
+                match generations.get(parent.as_str()) {
+                    Some(&g) => generation = generation.max(g),
+                    None => {
+                        pending = true;
+                        stack.push(parent.as_str());
+                    }
+                }
+            }
+
+            if !pending {
+                generations.insert(hash, (generation + 1).min(GENERATION_MAX));
+                stack.pop();
+            }
+        }
+    }
+
+    Ok(generations)
+}
+
+/// Write the commit-graph for every commit reachable from refs and HEAD
+///
+/// Tags are followed to the commits they point to.
+///
+/// # Returns
+///
+/// The path written and the number of commits in it
+pub fn write(repo: &Repository) -> Result<(PathBuf, usize)> {
+    let mut stack: Vec<String> = crate::refs::list_refs(repo, "refs/")?
+        .into_iter()
+        .map(|(_, hash)| hash)
+        .collect();
+    if let Some(head) = crate::commands::log::read_head(repo)? {
+        stack.push(head);
+    }
+
+    let mut commits: HashMap<String, Commit> = HashMap::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    while let Some(hash) = stack.pop() {
+        if !seen.insert(hash.clone()) {
+            continue;
+        }
+
+        let object = repo.objects.read(&hash)
+            .context(format!("Failed to read object: {}", hash))?;
+        match object.object_type.as_str() {
+            "commit" => {
+                let commit = Commit::parse(&String::from_utf8_lossy(&object.content))
+                    .context(format!("Invalid commit: {}", hash))?;
+                stack.extend(commit.parents.iter().cloned());
+                commits.insert(hash, commit);
+            }
+            "tag" => {
+                let tag = crate::objects::Tag::parse(&String::from_utf8_lossy(&object.content))
+                    .context(format!("Invalid tag: {}", hash))?;
+                stack.push(tag.object);
+            }
+            // Refs to trees or blobs have no history
+            _ => {}
+        }
+    }
+
+    let data = encode(&commits, repo.object_format())?;
+    let path = graph_path(&repo.objects_dir());
+    fs::create_dir_all(path.parent().unwrap())
+        .context("Failed to create objects/info directory")?;
+    atomic::write(&path, &repo.objects_dir(), &data)
+        .context("Failed to write commit-graph")?;
+
+    Ok((path, commits.len()))
+}
+
+/// The parents of a commit, from the commit-graph when it's listed there
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::{commit_graph, Repository};
+///
+/// let repo = Repository::find().unwrap();
+/// let parents = commit_graph::parents(&repo, "abc1234").unwrap();
+/// ```
+pub fn parents(repo: &Repository, hash: &str) -> Result<Vec<String>> {
+    if let Some(commit) = repo.commit_graph().and_then(|graph| graph.get(hash)) {
+        return Ok(commit.parents);
+    }
+    Ok(repo.read_commit(hash)?.parents.clone())
+}
+
+/// Check whether `ancestor` is reachable from `descendant`
+///
+/// A commit counts as its own ancestor. With a commit-graph, the walk
+/// skips every commit whose generation is lower than `ancestor`'s, since
+/// none of those can lead to it; when `ancestor` isn't in the graph at all,
+/// no commit in the graph can lead to it either.
+///
+/// # Example
+///
+/// ```no_run
+/// use rit::{commit_graph, Repository};
+///
+/// let repo = Repository::find().unwrap();
+/// if commit_graph::is_ancestor(&repo, "abc1234", "def5678").unwrap() {
+///     println!("already merged");
+/// }
+/// ```
+pub fn is_ancestor(repo: &Repository, ancestor: &str, descendant: &str) -> Result<bool> {
+    let graph = repo.commit_graph();
+    let min_generation = graph.map(|graph| graph.generation(ancestor).unwrap_or(u32::MAX));
+
+    let mut stack = vec![descendant.to_string()];
+    let mut visited: HashSet<String> = HashSet::new();
+
+    while let Some(hash) = stack.pop() {
+        if hash == ancestor {
+            return Ok(true);
+        }
+        if !visited.insert(hash.clone()) {
+            continue;
+        }
+
+        for parent in parents(repo, &hash)? {
+            let too_old = match (graph, min_generation) {
+                (Some(graph), Some(min)) => graph.generation(&parent).is_some_and(|g| g < min),
+                _ => false,
+            };
+            if !too_old {
+                stack.push(parent);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// Read a big-endian u32 at `pos`
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+/// Read a big-endian u64 at `pos`
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    u64::from_be_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::commands::hash_object;
+
+    /// Store a commit with the given parents and committer time
+    fn commit(repo: &Repository, parents: &[&str], time: u64) -> String {
+        let mut content = format!("tree {}\n", "4b825dc642cb6eb9a060e54bf8d69288fbee4904");
+        for parent in parents {
+            content.push_str(&format!("parent {}\n", parent));
+        }
+        content.push_str(&format!(
+            "author T <t@example.com> {0} +0000\ncommitter T <t@example.com> {0} +0000\n\nc{0}\n",
+            time
+        ));
+        hash_object::store_object(repo, "commit", content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_write_and_read_graph() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        // root <- a <- merge (with b and c as extra parents) , time past 2^32
+        let root = commit(&repo, &[], 100);
+        let a = commit(&repo, &[&root], 200);
+        let b = commit(&repo, &[&root], 300);
+        let c = commit(&repo, &[&b], 400);
+        let merge = commit(&repo, &[&a, &b, &c], (1 << 33) + 5);
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", merge)).unwrap();
+
+        let (path, count) = write(&repo).unwrap();
+        assert_eq!(count, 5);
+
+        let graph = CommitGraph::parse(fs::read(&path).unwrap(), repo.object_format()).unwrap();
+        assert_eq!(graph.len(), 5);
+        assert_eq!(graph.generation(&root), Some(1));
+        assert_eq!(graph.generation(&c), Some(3));
+
+        let info = graph.get(&merge).unwrap();
+        assert_eq!(info.parents, vec![a.clone(), b.clone(), c.clone()]);
+        assert_eq!(info.generation, 4);
+        assert_eq!(info.commit_time, (1 << 33) + 5);
+        assert_eq!(info.tree, "4b825dc642cb6eb9a060e54bf8d69288fbee4904");
+        assert!(graph.get(&"0".repeat(40)).is_none());
+
+        let mut sorted = vec![root, a, b, c, merge];
+        sorted.sort();
+        assert_eq!(graph.hashes().collect::<Vec<_>>(), sorted);
+    }
+
+    #[test]
+    fn test_parse_rejects_corrupt_graph() {
+        let format = ObjectFormat::Sha1;
+        assert!(CommitGraph::parse(b"not a graph".to_vec(), format).is_err());
+
+        let mut commits = HashMap::new();
+        let parsed = Commit::parse(
+            "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\nauthor T <t@e> 1 +0000\ncommitter T <t@e> 1 +0000\n\nx\n"
+        ).unwrap();
+        commits.insert("a".repeat(40), parsed);
+        let data = encode(&commits, format).unwrap();
+        assert_eq!(CommitGraph::parse(data.clone(), format).unwrap().len(), 1);
+        assert!(CommitGraph::parse(data.clone(), ObjectFormat::Sha256).is_err());
+
+        // Point the parent outside the table
+        let mut broken = data;
+        let record = broken.len() - 20 - 36 + 20;
+        broken[record..record + 4].copy_from_slice(&7u32.to_be_bytes());
+        assert!(CommitGraph::parse(broken, format).is_err());
+    }
+
+    #[test]
+    fn test_is_ancestor_with_and_without_graph() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let root = commit(&repo, &[], 1);
+        let main = commit(&repo, &[&root], 2);
+        let side = commit(&repo, &[&root], 3);
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", main)).unwrap();
+        fs::write(repo.rit_dir.join("refs/heads/side"), format!("{}\n", side)).unwrap();
+
+        let check = |repo: &Repository| {
+            assert!(is_ancestor(repo, &root, &main).unwrap());
+            assert!(is_ancestor(repo, &main, &main).unwrap());
+            assert!(!is_ancestor(repo, &side, &main).unwrap());
+            assert!(!is_ancestor(repo, &main, &root).unwrap());
+        };
+        check(&repo);
+
+        write(&repo).unwrap();
+        let repo = Repository::find_from(temp.path()).unwrap();
+        assert!(repo.commit_graph().is_some());
+        check(&repo);
+
+        // A commit made after the graph was written is still walked
+        let newer = commit(&repo, &[&side], 4);
+        assert!(is_ancestor(&repo, &root, &newer).unwrap());
+        assert_eq!(parents(&repo, &newer).unwrap(), vec![side]);
+    }
+}
@@ -0,0 +1,233 @@
+//! # Repository Config
+//!
+//! Reading and writing `.rit/config`, in the same INI-style format Git uses
+//! for `.git/config`:
+//!
+//! ```text
+//! [core]
+//!     repositoryformatversion = 1
+//! [extensions]
+//!     objectformat = sha256
+//! ```
+//!
+//! Keys are addressed as `section.key` (or `section.subsection.key`).
+//! Section and key names are case-insensitive; subsection names are not.
+//!
+//! ## Example
+//!
+//! ```
+//! use rit::config::Config;
+//!
+//! let mut config = Config::parse("[core]\n\tbare = false\n").unwrap();
+//! config.set("extensions.objectFormat", "sha256");
+//! assert_eq!(config.get("core.bare"), Some("false"));
+//! assert_eq!(config.get("extensions.objectformat"), Some("sha256"));
+//! ```
+
+use std::path::Path;
+use anyhow::{Context, Result};
+
+use crate::atomic;
+
+/// One `[section]` or `[section "subsection"]` block
+#[derive(Debug, Clone)]
+struct Section {
+    /// Lowercased section name
+    name: String,
+    /// Subsection name, if any (case-sensitive)
+    subsection: Option<String>,
+    /// Lowercased key and raw value, in file order
+    entries: Vec<(String, String)>,
+}
+
+/// A parsed config file
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    sections: Vec<Section>,
+}
+
+/// Split `section[.subsection].key` into its lowercased/verbatim parts
+fn split_key(key: &str) -> Result<(String, Option<String>, String)> {
+    let first = key.find('.').context(format!("key does not contain a section: {}", key))?;
+    let last = key.rfind('.').unwrap_or(first);
+
+    let section = key[..first].to_lowercase();
+    let name = key[last + 1..].to_lowercase();
+    let subsection = (first != last).then(|| key[first + 1..last].to_string());
+
+    if section.is_empty() || name.is_empty() {
+        anyhow::bail!("invalid config key: {}", key);
+    }
+
+    Ok((section, subsection, name))
+}
+
+impl Config {
+    /// Create an empty config
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a config file; a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read config: {}", path.display()))?;
+        Self::parse(&content)
+            .context(format!("Invalid config: {}", path.display()))
+    }
+
+    /// Parse config file contents
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut sections: Vec<Section> = Vec::new();
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.strip_suffix(']')
+                    .context(format!("line {}: unterminated section header", number + 1))?;
+
+                let (name, subsection) = match header.split_once(' ') {
+                    Some((name, sub)) => {
+                        let sub = sub.trim().trim_matches('"').to_string();
+                        (name.to_lowercase(), Some(sub))
+                    }
+                    None => (header.to_lowercase(), None),
+                };
+
+                sections.push(Section { name, subsection, entries: Vec::new() });
+                continue;
+            }
+
+            let section = sections.last_mut()
+                .context(format!("line {}: key outside of a section", number + 1))?;
+
+            // A bare key means "true"
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (line, "true"),
+            };
+            let value = value.strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+
+            section.entries.push((key.to_lowercase(), value.to_string()));
+        }
+
+        Ok(Self { sections })
+    }
+
+    /// Get a value; the last one wins if a key is set more than once
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let (section, subsection, name) = split_key(key).ok()?;
+
+        self.sections.iter()
+            .rev()
+            .filter(|s| s.name == section && s.subsection == subsection)
+            .flat_map(|s| s.entries.iter().rev())
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Get a boolean value (`true`/`yes`/`on`/`1` or `false`/`no`/`off`/`0`)
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => match value.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(Some(true)),
+                "false" | "no" | "off" | "0" | "" => Ok(Some(false)),
+                _ => anyhow::bail!("bad boolean config value '{}' for '{}'", value, key),
+            },
+        }
+    }
+
+    /// Set a value, replacing the last existing one or adding it
+    pub fn set(&mut self, key: &str, value: &str) {
+        let (section, subsection, name) = match split_key(key) {
+            Ok(parts) => parts,
+            Err(_) => return,
+        };
+
+        let existing = self.sections.iter_mut()
+            .rev()
+            .filter(|s| s.name == section && s.subsection == subsection)
+            .flat_map(|s| s.entries.iter_mut().rev())
+            .find(|(k, _)| *k == name);
+
+        if let Some(entry) = existing {
+            entry.1 = value.to_string();
+            return;
+        }
+
+        match self.sections.iter_mut().rev().find(|s| s.name == section && s.subsection == subsection) {
+            Some(s) => s.entries.push((name, value.to_string())),
+            None => self.sections.push(Section {
+                name: section,
+                subsection,
+                entries: vec![(name, value.to_string())],
+            }),
+        }
+    }
+
+    /// Serialize to the on-disk format
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        for section in &self.sections {
+            match &section.subsection {
+                Some(sub) => out.push_str(&format!("[{} \"{}\"]\n", section.name, sub)),
+                None => out.push_str(&format!("[{}]\n", section.name)),
+            }
+            for (key, value) in &section.entries {
+                out.push_str(&format!("\t{} = {}\n", key, value));
+            }
+        }
+
+        out
+    }
+
+    /// Write the config file atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp_dir = path.parent().unwrap_or(Path::new("."));
+        atomic::write(path, tmp_dir, self.serialize().as_bytes())
+            .context("Failed to write config")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_get() {
+        let config = Config::parse(
+            "# comment\n[core]\n\trepositoryFormatVersion = 1\n\tbare\n[remote \"origin\"]\n\turl = \"/tmp/x\"\n",
+        ).unwrap();
+
+        assert_eq!(config.get("core.repositoryformatversion"), Some("1"));
+        assert_eq!(config.get_bool("core.bare").unwrap(), Some(true));
+        assert_eq!(config.get("remote.origin.url"), Some("/tmp/x"));
+        assert_eq!(config.get("core.missing"), None);
+        assert!(Config::parse("key = value\n").is_err());
+    }
+
+    #[test]
+    fn test_set_and_roundtrip() {
+        let mut config = Config::new();
+        config.set("core.repositoryformatversion", "0");
+        config.set("core.repositoryformatversion", "1");
+        config.set("extensions.objectformat", "sha256");
+
+        let parsed = Config::parse(&config.serialize()).unwrap();
+        assert_eq!(parsed.get("core.repositoryformatversion"), Some("1"));
+        assert_eq!(parsed.get("extensions.objectformat"), Some("sha256"));
+        assert_eq!(parsed.serialize().matches("[core]").count(), 1);
+    }
+}
@@ -0,0 +1,181 @@
+//! # Object Formats
+//!
+//! A repository names its objects with either SHA-1 (the default, 40 hex
+//! characters) or SHA-256 (64 hex characters). The choice is made once at
+//! `rit init --object-format=<format>` time and recorded in `.rit/config`:
+//!
+//! ```text
+//! [core]
+//!     repositoryformatversion = 1
+//! [extensions]
+//!     objectformat = sha256
+//! ```
+//!
+//! Everything that hashes content or stores raw object IDs (trees, packs)
+//! asks the repository's [`ObjectFormat`] for the algorithm and widths
+//! instead of assuming SHA-1.
+//!
+//! ## Example
+//!
+//! ```
+//! use rit::hash::ObjectFormat;
+//!
+//! let format = ObjectFormat::from_name("sha256").unwrap();
+//! assert_eq!(format.hex_len(), 64);
+//! assert_eq!(format.hash_object("blob", b"test content\n").len(), 64);
+//! ```
+
+use std::fmt;
+use std::io;
+use anyhow::Result;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+/// The hash algorithm used to name objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ObjectFormat {
+    /// SHA-1, 20-byte IDs (Git's original format)
+    #[default]
+    Sha1,
+    /// SHA-256, 32-byte IDs
+    Sha256,
+}
+
+impl ObjectFormat {
+    /// Parse a format name as used in `--object-format` and config
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "sha1" => Ok(Self::Sha1),
+            "sha256" => Ok(Self::Sha256),
+            other => anyhow::bail!("unknown object format '{}'", other),
+        }
+    }
+
+    /// The format's name ("sha1" or "sha256")
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    /// Length of a raw object ID in bytes
+    pub fn raw_len(&self) -> usize {
+        match self {
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+        }
+    }
+
+    /// Length of a hex-encoded object ID
+    pub fn hex_len(&self) -> usize {
+        self.raw_len() * 2
+    }
+
+    /// Check that a string is a full hex object ID in this format
+    pub fn is_valid_hex(&self, hash: &str) -> bool {
+        hash.len() == self.hex_len() && hash.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    /// Start a new incremental hash
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            Self::Sha1 => Hasher::Sha1(Sha1::new()),
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Hash raw bytes (no object header)
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    /// Compute an object ID: the hash of `<type> <size>\0<content>`
+    pub fn hash_object(&self, object_type: &str, content: &[u8]) -> String {
+        let header = format!("{} {}\0", object_type, content.len());
+
+        let mut hasher = self.hasher();
+        hasher.update(header.as_bytes());
+        hasher.update(content);
+        hasher.finalize_hex()
+    }
+}
+
+impl fmt::Display for ObjectFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An in-progress hash in either format
+#[derive(Debug, Clone)]
+pub enum Hasher {
+    /// SHA-1 state
+    Sha1(Sha1),
+    /// SHA-256 state
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    /// Feed more data into the hash
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha1(h) => h.update(data),
+            Self::Sha256(h) => h.update(data),
+        }
+    }
+
+    /// Finish and return the raw digest
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha1(h) => h.finalize().to_vec(),
+            Self::Sha256(h) => h.finalize().to_vec(),
+        }
+    }
+
+    /// Finish and return the hex-encoded digest
+    pub fn finalize_hex(self) -> String {
+        hex::encode(self.finalize())
+    }
+}
+
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_hashes() {
+        // echo "test content" | git hash-object --stdin
+        assert_eq!(
+            ObjectFormat::Sha1.hash_object("blob", b"test content\n"),
+            "d670460b4b4aece5915caf5c68d12f560a9fe3e4"
+        );
+        // Same, in a repository created with --object-format=sha256
+        assert_eq!(
+            ObjectFormat::Sha256.hash_object("blob", b"test content\n"),
+            "13b7e821533d3fe3728a3c4560606a65aab99f4390b9df0714f9075c0ef4c2d6"
+        );
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(ObjectFormat::from_name("SHA256").unwrap(), ObjectFormat::Sha256);
+        assert_eq!(ObjectFormat::from_name("sha1").unwrap().hex_len(), 40);
+        assert!(ObjectFormat::from_name("md5").is_err());
+        assert!(ObjectFormat::Sha256.is_valid_hex(&"a".repeat(64)));
+        assert!(!ObjectFormat::Sha256.is_valid_hex(&"a".repeat(40)));
+    }
+}
@@ -34,8 +34,8 @@ impl IgnorePattern {
             return None;
         }
         
-        let (pattern, negated) = if trimmed.starts_with('!') {
-            (trimmed[1..].trim(), true)
+        let (pattern, negated) = if let Some(rest) = trimmed.strip_prefix('!') {
+            (rest.trim(), true)
         } else {
             (trimmed, false)
         };
@@ -112,10 +112,10 @@ impl IgnorePattern {
                 }
             } else if parts.len() == 1 {
                 // Pattern like "prefix*" or "*suffix"
-                if pattern.ends_with('*') {
-                    return text.starts_with(&pattern[..pattern.len() - 1]);
-                } else if pattern.starts_with('*') {
-                    return text.ends_with(&pattern[1..]);
+                if let Some(prefix) = pattern.strip_suffix('*') {
+                    return text.starts_with(prefix);
+                } else if let Some(suffix) = pattern.strip_prefix('*') {
+                    return text.ends_with(suffix);
                 }
             }
         }
@@ -164,15 +164,26 @@ impl IgnoreRules {
         let content = fs::read_to_string(ignore_path)
             .context("Failed to read ignore file")?;
         
-        let mut patterns = Vec::new();
-        
-        for line in content.lines() {
-            if let Some(pattern) = IgnorePattern::parse(line) {
-                patterns.push(pattern);
-            }
-        }
+        Ok(Self::parse(&content))
+    }
+    
+    /// Parse rules from the contents of an ignore file, one pattern per line
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rit::ignore::IgnoreRules;
+    ///
+    /// let rules = IgnoreRules::parse("*.log\n!keep.log\n");
+    /// assert!(rules.is_ignored("debug.log", false));
+    /// assert!(!rules.is_ignored("keep.log", false));
+    /// ```
+    pub fn parse(content: &str) -> Self {
+        let patterns = content.lines()
+            .filter_map(IgnorePattern::parse)
+            .collect();
         
-        Ok(Self { patterns })
+        Self { patterns }
     }
     
     /// Check if a path should be ignored
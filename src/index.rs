@@ -1,80 +1,608 @@
 //! # Index (Staging Area)
 //!
-//! The index tracks files that are staged for the next commit.
-//! We use a simplified JSON format for V1 (Git uses a complex binary format).
+//! The index tracks files that are staged for the next commit. It is stored
+//! in Git's binary `DIRC` format, so rit and Git can share a `.git/index`:
+//!
+//! ```text
+//! "DIRC" | version (4 bytes) | entry count (4 bytes)
+//! entries, sorted by path
+//! extensions
+//! checksum of everything above (SHA-1 or SHA-256)
+//! ```
+//!
+//! Each entry holds stat data, the blob's object ID, a flags word (stage and
+//! path length) and the path. Version 2 pads every entry with NULs to a
+//! multiple of 8 bytes; version 3 adds an extended flags word; version 4 drops
+//! the padding and stores each path as the number of bytes to remove from the
+//! previous path plus the new suffix.
+//!
+//! ## Stat Data and Racy Timestamps
+//!
+//! Every entry records the file's size, ctime, mtime (with nanoseconds), dev,
+//! ino, mode, uid and gid as they were when the blob was hashed. A file whose
+//! stat data still matches is taken to be unchanged without reading it, so
+//! `status`, `diff` and `add` only re-hash the files that look different.
+//!
+//! A file written in the same instant as the index (or later) could change
+//! again without its mtime moving, so such "racily clean" entries are always
+//! re-hashed. When an index is rewritten, racy entries that were not checked
+//! are saved with size 0, which forces a re-hash after the index timestamp
+//! has moved on.
+//!
+//! ## Merge Conflicts
+//!
+//! A path normally has one entry, at stage 0. A merge that can't resolve a
+//! path leaves up to three entries for it instead: stage 1 (the common
+//! ancestor), stage 2 ("ours", the current branch) and stage 3 ("theirs").
+//! These are kept apart from the stage 0 entries, in [`Index::conflicts`],
+//! so code that builds trees from [`Index::entries`] never sees them.
+//! Staging the path again with [`Index::add_entry`] resolves the conflict.
+//!
+//! ## Locking
+//!
+//! Every write goes through `.rit/index.lock` (see [`crate::lockfile`]). A
+//! command that updates the index takes the lock before loading it, with
+//! [`Index::load_for_update`], so two concurrent `rit add` runs can't both
+//! start from the same old index; the second one fails straight away.
+//!
+//! ## Object Format
+//!
+//! Entry IDs and the checksum use the repository's object format, which is
+//! passed in when an index is created or loaded rather than guessed from the
+//! entries. An existing index must match it, except that an empty one is
+//! simply rewritten in the repository's format.
+//!
+//! ## Versions
+//!
+//! New indexes are written as version 2 unless `RIT_INDEX_VERSION` says
+//! otherwise, and an existing index keeps its version. Optional extensions
+//! that Git adds (such as the cached tree) are skipped when reading and not
+//! written back. Indexes saved by older rit releases as JSON are still read,
+//! and become binary on the next save.
 
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
 use std::path::Path;
+use std::time::UNIX_EPOCH;
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use std::io::Write;
+use crate::hash::ObjectFormat;
+use crate::lockfile::LockFile;
+
+/// Signature at the start of every binary index
+const SIGNATURE: &[u8; 4] = b"DIRC";
+
+/// Environment variable that sets the version of newly created indexes
+pub const VERSION_ENV: &str = "RIT_INDEX_VERSION";
+
+/// Index version written when nothing else is asked for
+pub const DEFAULT_VERSION: u32 = 2;
+
+/// Mode of a regular, non-executable file
+pub const MODE_FILE: u32 = 0o100644;
+
+/// Mode of an executable file
+pub const MODE_EXECUTABLE: u32 = 0o100755;
+
+/// Mode of a symbolic link
+pub const MODE_SYMLINK: u32 = 0o120000;
+
+/// Flags word: the entry has an extended flags word (version 3+)
+const FLAG_EXTENDED: u16 = 0x4000;
+
+/// Flags word: the merge stage (bits 12-13)
+const FLAG_STAGE_MASK: u16 = 0x3000;
+
+/// Bit position of the merge stage in the flags word
+const FLAG_STAGE_SHIFT: u16 = 12;
+
+/// Stage of the common ancestor's version of a conflicted path
+pub const STAGE_BASE: u8 = 1;
+
+/// Stage of the current branch's version of a conflicted path
+pub const STAGE_OURS: u8 = 2;
+
+/// Stage of the merged branch's version of a conflicted path
+pub const STAGE_THEIRS: u8 = 3;
+
+/// Flags word: path length, saturating at 0xFFF
+const FLAG_NAME_MASK: u16 = 0x0fff;
+
+/// Size of the fixed stat fields that precede the object ID in an entry
+const STAT_LEN: usize = 40;
 
 /// Represents a single entry in the index
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
 pub struct IndexEntry {
     /// File path (relative to repository root)
     pub path: String,
-    /// SHA-1 hash of the blob
+    /// Object ID of the blob
     pub hash: String,
     /// File size in bytes
     pub size: u64,
     /// Modification time (Unix timestamp)
     pub mtime: u64,
+    /// Nanosecond part of the modification time
+    #[serde(default)]
+    pub mtime_nsec: u32,
+    /// Status change time (Unix timestamp)
+    #[serde(default)]
+    pub ctime: u64,
+    /// Nanosecond part of the status change time
+    #[serde(default)]
+    pub ctime_nsec: u32,
+    /// Device the file is on
+    #[serde(default)]
+    pub dev: u32,
+    /// Inode number
+    #[serde(default)]
+    pub ino: u32,
+    /// File mode ([`MODE_FILE`], [`MODE_EXECUTABLE`] or [`MODE_SYMLINK`]; 0 if unknown)
+    #[serde(default)]
+    pub mode: u32,
+    /// Owner user ID
+    #[serde(default)]
+    pub uid: u32,
+    /// Owner group ID
+    #[serde(default)]
+    pub gid: u32,
+}
+
+impl IndexEntry {
+    /// Create an entry for a file that was just hashed
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path relative to the repository root
+    /// * `hash` - Object ID of the file's blob
+    /// * `metadata` - The file's metadata, taken before it was hashed
+    pub fn from_metadata(path: String, hash: String, metadata: &Metadata) -> Self {
+        let mut entry = Self { path, hash, ..Self::default() };
+        entry.update_stat(metadata);
+        entry
+    }
+
+    /// Record new stat data, e.g. after checking that the content is unchanged
+    pub fn update_stat(&mut self, metadata: &Metadata) {
+        let stat = Stat::from_metadata(metadata);
+        self.size = stat.size;
+        self.mtime = stat.mtime;
+        self.mtime_nsec = stat.mtime_nsec;
+        self.ctime = stat.ctime;
+        self.ctime_nsec = stat.ctime_nsec;
+        self.dev = stat.dev;
+        self.ino = stat.ino;
+        self.mode = stat.mode;
+        self.uid = stat.uid;
+        self.gid = stat.gid;
+    }
+
+    /// Check whether a file's metadata matches what was recorded
+    ///
+    /// Fields are compared in their 32-bit on-disk form. An entry with no
+    /// recorded mode (from a legacy index) or a smudged size never matches.
+    pub fn matches_stat(&self, metadata: &Metadata) -> bool {
+        let stat = Stat::from_metadata(metadata);
+        self.mode != 0
+            && self.mode == stat.mode
+            && self.size as u32 == stat.size as u32
+            && (self.size != 0 || stat.size == 0)
+            && self.mtime as u32 == stat.mtime as u32
+            && self.mtime_nsec == stat.mtime_nsec
+            && self.ctime as u32 == stat.ctime as u32
+            && self.ctime_nsec == stat.ctime_nsec
+            && self.dev == stat.dev
+            && self.ino == stat.ino
+            && self.uid == stat.uid
+            && self.gid == stat.gid
+    }
+}
+
+/// Stat data in the form stored in the index
+struct Stat {
+    size: u64,
+    mtime: u64,
+    mtime_nsec: u32,
+    ctime: u64,
+    ctime_nsec: u32,
+    dev: u32,
+    ino: u32,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+}
+
+impl Stat {
+    #[cfg(unix)]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        let mode = if metadata.file_type().is_symlink() {
+            MODE_SYMLINK
+        } else if metadata.mode() & 0o111 != 0 {
+            MODE_EXECUTABLE
+        } else {
+            MODE_FILE
+        };
+
+        Self {
+            size: metadata.len(),
+            mtime: metadata.mtime() as u64,
+            mtime_nsec: metadata.mtime_nsec() as u32,
+            ctime: metadata.ctime() as u64,
+            ctime_nsec: metadata.ctime_nsec() as u32,
+            dev: metadata.dev() as u32,
+            ino: metadata.ino() as u32,
+            mode,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        }
+    }
+
+    /// Without Unix metadata only the size, mtime and file type are known
+    #[cfg(not(unix))]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        let mtime = metadata.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        Self {
+            size: metadata.len(),
+            mtime: mtime.as_secs(),
+            mtime_nsec: mtime.subsec_nanos(),
+            ctime: 0,
+            ctime_nsec: 0,
+            dev: 0,
+            ino: 0,
+            mode: if metadata.file_type().is_symlink() { MODE_SYMLINK } else { MODE_FILE },
+            uid: 0,
+            gid: 0,
+        }
+    }
+}
+
+/// The versions of a path left behind by a merge conflict
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conflict {
+    /// Stage 1: the common ancestor's version
+    pub base: Option<IndexEntry>,
+    /// Stage 2: the current branch's version
+    pub ours: Option<IndexEntry>,
+    /// Stage 3: the merged branch's version
+    pub theirs: Option<IndexEntry>,
+}
+
+impl Conflict {
+    /// Get the entry at a stage (1, 2 or 3)
+    pub fn stage(&self, stage: u8) -> Option<&IndexEntry> {
+        match stage {
+            STAGE_BASE => self.base.as_ref(),
+            STAGE_OURS => self.ours.as_ref(),
+            STAGE_THEIRS => self.theirs.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// All entries with their stage numbers, in stage order
+    pub fn stages(&self) -> impl Iterator<Item = (u8, &IndexEntry)> {
+        [STAGE_BASE, STAGE_OURS, STAGE_THEIRS]
+            .into_iter()
+            .filter_map(|stage| self.stage(stage).map(|entry| (stage, entry)))
+    }
+
+    /// Describe the conflict as `git status` does, e.g. "both modified"
+    pub fn description(&self) -> &'static str {
+        match (self.base.is_some(), self.ours.is_some(), self.theirs.is_some()) {
+            (true, true, true) => "both modified",
+            (false, true, true) => "both added",
+            (true, true, false) => "deleted by them",
+            (true, false, true) => "deleted by us",
+            (true, false, false) => "both deleted",
+            (false, true, false) => "added by us",
+            (false, false, true) => "added by them",
+            (false, false, false) => "unmerged",
+        }
+    }
 }
 
 /// Represents the index (staging area)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct Index {
-    /// Map of file paths to index entries
+    /// Map of file paths to index entries (stage 0)
     pub entries: HashMap<String, IndexEntry>,
+    /// Map of conflicted paths to their stage 1-3 entries
+    pub conflicts: HashMap<String, Conflict>,
+    /// On-disk format version (2, 3 or 4)
+    pub version: u32,
+    /// Object format of the entries' IDs and the checksum
+    pub format: ObjectFormat,
+    /// Modification time of the index file when it was loaded (seconds, nanoseconds)
+    pub timestamp: Option<(u64, u32)>,
+    /// Paths whose entries were added or checked since loading
+    uptodate: HashSet<String>,
+}
+
+/// The JSON layout used by older rit releases
+#[derive(Deserialize)]
+struct JsonIndex {
+    entries: HashMap<String, IndexEntry>,
 }
 
 impl Index {
-    /// Create a new empty index
-    pub fn new() -> Self {
+    /// Create a new empty index for a repository using `format`
+    pub fn new(format: ObjectFormat) -> Self {
         Self {
             entries: HashMap::new(),
+            conflicts: HashMap::new(),
+            version: default_version(),
+            format,
+            timestamp: None,
+            uptodate: HashSet::new(),
         }
     }
 
     /// Load index from file
-    pub fn load(index_path: &Path) -> Result<Self> {
+    ///
+    /// A missing or empty file is an empty index. Binary indexes have their
+    /// checksum verified; anything else is read as a legacy JSON index.
+    ///
+    /// # Arguments
+    ///
+    /// * `index_path` - The index file
+    /// * `format` - The repository's object format
+    pub fn load(index_path: &Path, format: ObjectFormat) -> Result<Self> {
         if !index_path.exists() {
-            return Ok(Self::new());
+            return Ok(Self::new(format));
         }
 
-        let content = fs::read_to_string(index_path)
+        let content = fs::read(index_path)
             .context("Failed to read index file")?;
 
+        if content.starts_with(SIGNATURE) {
+            let mut index = Self::parse(&content)
+                .context(format!("Failed to parse index file {}", index_path.display()))?;
+            if index.format != format {
+                if index.all_entries().next().is_some() {
+                    anyhow::bail!(
+                        "index file {} uses {} object IDs, but the repository uses {}",
+                        index_path.display(), index.format.name(), format.name()
+                    );
+                }
+                index.format = format;
+            }
+            index.timestamp = fs::metadata(index_path)?.modified().ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| (d.as_secs(), d.subsec_nanos()));
+            return Ok(index);
+        }
+
+        let content = String::from_utf8(content)
+            .context("Failed to parse index file")?;
+
         if content.trim().is_empty() {
-            return Ok(Self::new());
+            return Ok(Self::new(format));
         }
 
-        let index: Index = serde_json::from_str(&content)
+        let index: JsonIndex = serde_json::from_str(&content)
             .context("Failed to parse index file")?;
 
-        Ok(index)
+        Ok(Self { entries: index.entries, ..Self::new(format) })
     }
 
-    /// Save index to file
+    /// Lock the index, then load it, for a command that will change it
+    ///
+    /// Pass the lock to [`Index::save_locked`] to write the result; dropping
+    /// it instead leaves the index as it was.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use rit::index::Index;
+    ///
+    /// use rit::hash::ObjectFormat;
+    ///
+    /// let (mut index, lock) = Index::load_for_update(Path::new(".rit/index"), ObjectFormat::Sha1).unwrap();
+    /// index.remove_entry("old.txt");
+    /// index.save_locked(lock).unwrap();
+    /// ```
+    pub fn load_for_update(index_path: &Path, format: ObjectFormat) -> Result<(Self, LockFile)> {
+        let lock = LockFile::acquire(index_path)?;
+        let index = Self::load(index_path, format)?;
+        Ok((index, lock))
+    }
+
+    /// Save index to file, taking the index lock for the write
     pub fn save(&self, index_path: &Path) -> Result<()> {
-        let content = serde_json::to_string_pretty(self)
+        self.save_locked(LockFile::acquire(index_path)?)
+    }
+
+    /// Save index through a lock taken with [`Index::load_for_update`]
+    pub fn save_locked(&self, mut lock: LockFile) -> Result<()> {
+        let content = self.encode()
             .context("Failed to serialize index")?;
-        
-        fs::write(index_path, content)
+
+        lock.write_all(&content)
+            .context("Failed to write index file")?;
+        lock.commit()
             .context("Failed to write index file")?;
 
         Ok(())
     }
 
+    /// Parse a binary (`DIRC`) index
+    ///
+    /// The object format is recognised from the trailing checksum, which is
+    /// a SHA-1 or SHA-256 of the rest of the file.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let format = checksum_format(data)?;
+        let body = &data[..data.len() - format.raw_len()];
+        let mut cursor = Cursor { data: body, pos: 0 };
+
+        if cursor.take(4)? != SIGNATURE {
+            anyhow::bail!("index file corrupt: bad signature");
+        }
+        let version = cursor.u32()?;
+        if !(2..=4).contains(&version) {
+            anyhow::bail!("index file corrupt: unsupported version {}", version);
+        }
+        let count = cursor.u32()? as usize;
+
+        let mut index = Self { version, ..Self::new(format) };
+        index.entries.reserve(count);
+        let mut previous_path: Vec<u8> = Vec::new();
+        for _ in 0..count {
+            let (entry, stage) = read_entry(&mut cursor, version, format, &previous_path)?;
+            previous_path = entry.path.as_bytes().to_vec();
+            if stage == 0 {
+                index.entries.insert(entry.path.clone(), entry);
+            } else {
+                index.add_conflict_entry(entry, stage)?;
+            }
+        }
+
+        while cursor.pos < body.len() {
+            let signature = cursor.take(4)?;
+            let size = cursor.u32()? as usize;
+            if !signature[0].is_ascii_uppercase() {
+                anyhow::bail!(
+                    "index uses the '{}' extension, which rit does not support",
+                    String::from_utf8_lossy(signature)
+                );
+            }
+            cursor.take(size)?;
+        }
+
+        Ok(index)
+    }
+
+    /// Encode the index in binary (`DIRC`) form, entries sorted by path and stage
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        if !(2..=4).contains(&self.version) {
+            anyhow::bail!("unsupported index version {}", self.version);
+        }
+        let format = self.format;
+
+        let mut entries: Vec<(&IndexEntry, u8)> = self.entries.values()
+            .map(|entry| (entry, 0))
+            .chain(self.conflicts.values().flat_map(|c| c.stages().map(|(stage, entry)| (entry, stage))))
+            .collect();
+        entries.sort_by(|a, b| (a.0.path.as_bytes(), a.1).cmp(&(b.0.path.as_bytes(), b.1)));
+
+        let mut out = Vec::with_capacity(12 + entries.len() * 80);
+        out.extend_from_slice(SIGNATURE);
+        out.extend_from_slice(&self.version.to_be_bytes());
+        out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+
+        let mut previous_path: &[u8] = &[];
+        for (entry, stage) in entries {
+            let smudge = self.is_racy(entry) && !self.uptodate.contains(&entry.path);
+            write_entry(&mut out, entry, stage, self.version, format, previous_path, smudge)?;
+            previous_path = entry.path.as_bytes();
+        }
+
+        let checksum = format.digest(&out);
+        out.extend_from_slice(&checksum);
+        Ok(out)
+    }
+
+    /// Check whether an entry's stat data can't be trusted
+    ///
+    /// An entry is racy when its file was modified no earlier than the index
+    /// was written: a later change within the same timestamp tick would leave
+    /// the stat data unchanged.
+    pub fn is_racy(&self, entry: &IndexEntry) -> bool {
+        match self.timestamp {
+            Some((secs, nsec)) => (entry.mtime, entry.mtime_nsec) >= (secs, nsec),
+            None => false,
+        }
+    }
+
+    /// Check whether a working tree file has to be re-hashed to compare it
+    /// with its entry, because its stat data differs or is racy
+    pub fn needs_rehash(&self, entry: &IndexEntry, metadata: &Metadata) -> bool {
+        !entry.matches_stat(metadata) || self.is_racy(entry)
+    }
+
+    /// Record fresh stat data for an entry whose content was found unchanged
+    ///
+    /// Returns whether the index is worth saving: the stored stat data
+    /// changed, or the entry was racy and a newer index timestamp would
+    /// spare the next check.
+    pub fn refresh_entry(&mut self, path: &str, metadata: &Metadata) -> bool {
+        self.uptodate.insert(path.to_string());
+        let racy = match self.entries.get(path) {
+            Some(entry) => self.is_racy(entry),
+            None => return false,
+        };
+        let entry = self.entries.get_mut(path).expect("entry exists");
+        if entry.matches_stat(metadata) {
+            return racy;
+        }
+        entry.update_stat(metadata);
+        true
+    }
+
     /// Add or update an entry in the index
+    ///
+    /// Any conflict stages for the path are dropped: staging a conflicted
+    /// path marks it resolved.
     pub fn add_entry(&mut self, entry: IndexEntry) {
+        self.conflicts.remove(&entry.path);
+        self.uptodate.insert(entry.path.clone());
         self.entries.insert(entry.path.clone(), entry);
     }
 
-    /// Remove an entry from the index
+    /// Record one side of a merge conflict
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The version of the path at this stage
+    /// * `stage` - [`STAGE_BASE`], [`STAGE_OURS`] or [`STAGE_THEIRS`]
+    pub fn add_conflict_entry(&mut self, entry: IndexEntry, stage: u8) -> Result<()> {
+        self.entries.remove(&entry.path);
+        let conflict = self.conflicts.entry(entry.path.clone()).or_default();
+        let slot = match stage {
+            STAGE_BASE => &mut conflict.base,
+            STAGE_OURS => &mut conflict.ours,
+            STAGE_THEIRS => &mut conflict.theirs,
+            _ => anyhow::bail!("invalid stage {} for '{}'", stage, entry.path),
+        };
+        *slot = Some(entry);
+        Ok(())
+    }
+
+    /// Remove an entry from the index, including any conflict stages
     pub fn remove_entry(&mut self, path: &str) {
         self.entries.remove(path);
+        self.conflicts.remove(path);
+    }
+
+    /// Check whether any path has an unresolved conflict
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    /// Conflicted paths, sorted
+    pub fn unmerged_paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.conflicts.keys().map(String::as_str).collect();
+        paths.sort();
+        paths
+    }
+
+    /// Get the conflict for a path, if it is unmerged
+    pub fn get_conflict(&self, path: &str) -> Option<&Conflict> {
+        self.conflicts.get(path)
+    }
+
+    /// Get every entry, including conflict stages
+    ///
+    /// Use this where any blob the index refers to matters, e.g. to keep
+    /// objects from being pruned.
+    pub fn all_entries(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.values()
+            .chain(self.conflicts.values().flat_map(|c| c.stages().map(|(_, entry)| entry)))
     }
 
     /// Get an entry by path
@@ -93,9 +621,203 @@ impl Index {
     }
 }
 
-impl Default for Index {
-    fn default() -> Self {
-        Self::new()
+/// The version for a new index: `RIT_INDEX_VERSION`, or version 2
+fn default_version() -> u32 {
+    match std::env::var(VERSION_ENV) {
+        Ok(value) => match value.trim().parse::<u32>() {
+            Ok(version) if (2..=4).contains(&version) => version,
+            _ => {
+                eprintln!(
+                    "warning: {} set, but the value is invalid. Using version {}",
+                    VERSION_ENV, DEFAULT_VERSION
+                );
+                DEFAULT_VERSION
+            }
+        },
+        Err(_) => DEFAULT_VERSION,
+    }
+}
+
+/// Work out the object format from the trailing checksum, verifying it
+fn checksum_format(data: &[u8]) -> Result<ObjectFormat> {
+    for format in [ObjectFormat::Sha1, ObjectFormat::Sha256] {
+        let len = format.raw_len();
+        if data.len() < 12 + len {
+            continue;
+        }
+        let (body, checksum) = data.split_at(data.len() - len);
+        if format.digest(body) == checksum {
+            return Ok(format);
+        }
+    }
+    anyhow::bail!("index file corrupt: bad checksum")
+}
+
+/// Read one entry starting at the cursor
+///
+/// Version 4 paths are stored relative to `previous_path`.
+/// Returns the entry and its stage.
+fn read_entry(cursor: &mut Cursor, version: u32, format: ObjectFormat, previous_path: &[u8]) -> Result<(IndexEntry, u8)> {
+    let start = cursor.pos;
+    let stat = cursor.take(STAT_LEN)?;
+    let field = |i: usize| u32::from_be_bytes([stat[i * 4], stat[i * 4 + 1], stat[i * 4 + 2], stat[i * 4 + 3]]);
+
+    let hash = hex::encode(cursor.take(format.raw_len())?);
+    let flags = cursor.u16()?;
+    if flags & FLAG_EXTENDED != 0 {
+        if version < 3 {
+            anyhow::bail!("index file corrupt: extended flags in a version {} index", version);
+        }
+        cursor.u16()?;
+    }
+
+    let path = if version == 4 {
+        let strip = cursor.varint()? as usize;
+        if strip > previous_path.len() {
+            anyhow::bail!("index file corrupt: bad path prefix");
+        }
+        let mut path = previous_path[..previous_path.len() - strip].to_vec();
+        path.extend_from_slice(cursor.until_nul()?);
+        path
+    } else {
+        let path = cursor.until_nul()?.to_vec();
+        // Entries are padded with 1-8 NULs to a multiple of 8 bytes
+        let padded = (cursor.pos - 1 - start + 8) & !7;
+        cursor.pos = start;
+        cursor.take(padded)?;
+        path
+    };
+    let path = String::from_utf8(path)
+        .context("index file corrupt: path is not valid UTF-8")?;
+
+    let stage = ((flags & FLAG_STAGE_MASK) >> FLAG_STAGE_SHIFT) as u8;
+
+    let entry = IndexEntry {
+        path,
+        hash,
+        size: field(9) as u64,
+        mtime: field(2) as u64,
+        mtime_nsec: field(3),
+        ctime: field(0) as u64,
+        ctime_nsec: field(1),
+        dev: field(4),
+        ino: field(5),
+        mode: field(6),
+        uid: field(7),
+        gid: field(8),
+    };
+    Ok((entry, stage))
+}
+
+/// Append one entry to `out`
+///
+/// A smudged entry is written with size 0 so that its stat data no longer
+/// matches the file.
+fn write_entry(
+    out: &mut Vec<u8>,
+    entry: &IndexEntry,
+    stage: u8,
+    version: u32,
+    format: ObjectFormat,
+    previous_path: &[u8],
+    smudge: bool,
+) -> Result<()> {
+    if !format.is_valid_hex(&entry.hash) {
+        anyhow::bail!("invalid object ID '{}' for '{}' in the index", entry.hash, entry.path);
+    }
+    let start = out.len();
+
+    // Git keeps only the low 32 bits of the times and size
+    let mode = if entry.mode == 0 { MODE_FILE } else { entry.mode };
+    let size = if smudge { 0 } else { entry.size as u32 };
+    let stat = [
+        entry.ctime as u32, entry.ctime_nsec,
+        entry.mtime as u32, entry.mtime_nsec,
+        entry.dev, entry.ino, mode, entry.uid, entry.gid, size,
+    ];
+    for value in stat {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend_from_slice(&hex::decode(&entry.hash)?);
+
+    let path = entry.path.as_bytes();
+    let flags = ((stage as u16) << FLAG_STAGE_SHIFT) | path.len().min(FLAG_NAME_MASK as usize) as u16;
+    out.extend_from_slice(&flags.to_be_bytes());
+
+    if version == 4 {
+        let common = previous_path.iter().zip(path).take_while(|(a, b)| a == b).count();
+        encode_varint(out, (previous_path.len() - common) as u64);
+        out.extend_from_slice(&path[common..]);
+        out.push(0);
+    } else {
+        out.extend_from_slice(path);
+        let padded = (out.len() - start + 8) & !7;
+        out.resize(start + padded, 0);
+    }
+    Ok(())
+}
+
+/// Append a variable-length integer as used by version 4 path prefixes
+///
+/// This is the same encoding as a pack's `OFS_DELTA` distance: big-endian
+/// groups of 7 bits, with one subtracted before each continuation.
+fn encode_varint(out: &mut Vec<u8>, mut value: u64) {
+    let mut bytes = vec![(value & 0x7f) as u8];
+    value >>= 7;
+    while value > 0 {
+        value -= 1;
+        bytes.push(0x80 | (value & 0x7f) as u8);
+        value >>= 7;
+    }
+    bytes.reverse();
+    out.extend_from_slice(&bytes);
+}
+
+/// A read position in an index file
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Take the next `len` bytes
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .context("index file corrupt: unexpected end of file")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a variable-length integer (see [`encode_varint`])
+    fn varint(&mut self) -> Result<u64> {
+        let mut byte = self.take(1)?[0];
+        let mut value = (byte & 0x7f) as u64;
+        while byte & 0x80 != 0 {
+            byte = self.take(1)?[0];
+            value = ((value + 1) << 7) | (byte & 0x7f) as u64;
+        }
+        Ok(value)
+    }
+
+    /// Take bytes up to a NUL, consuming the NUL
+    fn until_nul(&mut self) -> Result<&'a [u8]> {
+        let rest = &self.data[self.pos..];
+        let len = rest.iter().position(|&b| b == 0)
+            .context("index file corrupt: unterminated path")?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
     }
 }
 
@@ -104,20 +826,32 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    fn entry(path: &str, hash: &str) -> IndexEntry {
+        IndexEntry {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            size: 100,
+            mtime: 1234567890,
+            mode: MODE_FILE,
+            ..IndexEntry::default()
+        }
+    }
+
     #[test]
     fn test_index_new() {
-        let index = Index::new();
+        let index = Index::new(ObjectFormat::Sha1);
         assert!(index.entries.is_empty());
     }
 
     #[test]
     fn test_index_add_entry() {
-        let mut index = Index::new();
+        let mut index = Index::new(ObjectFormat::Sha1);
         let entry = IndexEntry {
             path: "test.txt".to_string(),
             hash: "abc123".to_string(),
             size: 100,
             mtime: 1234567890,
+            ..IndexEntry::default()
         };
         index.add_entry(entry.clone());
         assert_eq!(index.get_entry("test.txt"), Some(&entry));
@@ -128,19 +862,225 @@ mod tests {
         let temp = tempdir().unwrap();
         let index_path = temp.path().join("index");
 
-        let mut index = Index::new();
+        let mut index = Index::new(ObjectFormat::Sha1);
         index.add_entry(IndexEntry {
             path: "test.txt".to_string(),
-            hash: "abc123".to_string(),
+            hash: "a".repeat(40),
             size: 100,
             mtime: 1234567890,
+            ..IndexEntry::default()
         });
 
         index.save(&index_path).unwrap();
-        let loaded = Index::load(&index_path).unwrap();
+        let loaded = Index::load(&index_path, ObjectFormat::Sha1).unwrap();
 
         assert_eq!(loaded.entries.len(), 1);
-        assert_eq!(loaded.get_entry("test.txt").unwrap().hash, "abc123");
+        assert_eq!(loaded.get_entry("test.txt").unwrap().hash, "a".repeat(40));
+        assert!(fs::read(&index_path).unwrap().starts_with(b"DIRC"));
+    }
+
+    #[test]
+    fn test_binary_layout_is_sorted_and_padded() {
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.version = 2;
+        index.add_entry(entry("src/main.rs", &"b".repeat(40)));
+        index.add_entry(entry("README", &"a".repeat(40)));
+
+        let data = index.encode().unwrap();
+        assert_eq!(&data[4..12], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        // 62 fixed bytes + "README" (6) padded to 72
+        assert_eq!(&data[12 + 62..12 + 68], b"README");
+        assert_eq!(&data[12 + 72 + 62..12 + 72 + 73], b"src/main.rs");
+        assert_eq!(data.len(), 12 + 72 + 80 + 20);
+    }
+
+    #[test]
+    fn test_version_4_and_sha256_round_trip() {
+        let formats = [(3, ObjectFormat::Sha1), (4, ObjectFormat::Sha1), (4, ObjectFormat::Sha256)];
+        for (version, format) in formats {
+            let hash = "c".repeat(format.hex_len());
+            let mut index = Index::new(format);
+            index.version = version;
+            for path in ["dir/a.txt", "dir/b.txt", "dir/sub/c.txt", "z"] {
+                index.add_entry(entry(path, &hash));
+            }
+
+            let loaded = Index::parse(&index.encode().unwrap()).unwrap();
+            assert_eq!(loaded.version, version);
+            assert_eq!(loaded.entries, index.entries);
+            assert_eq!(loaded.format, format);
+        }
     }
-}
 
+    #[test]
+    fn test_corrupt_index_is_rejected() {
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.add_entry(entry("file.txt", &"e".repeat(40)));
+        let mut data = index.encode().unwrap();
+        data[20] ^= 0xff;
+
+        let err = Index::parse(&data).unwrap_err();
+        assert!(err.to_string().contains("bad checksum"));
+    }
+
+    #[test]
+    fn test_optional_extensions_are_skipped() {
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.add_entry(entry("file.txt", &"e".repeat(40)));
+        let mut data = index.encode().unwrap();
+        data.truncate(data.len() - 20);
+
+        let mut with_tree = data.clone();
+        with_tree.extend_from_slice(b"TREE\0\0\0\x03abc");
+        let checksum = ObjectFormat::Sha1.digest(&with_tree);
+        with_tree.extend_from_slice(&checksum);
+        assert_eq!(Index::parse(&with_tree).unwrap().entries.len(), 1);
+
+        data.extend_from_slice(b"link\0\0\0\0");
+        let checksum = ObjectFormat::Sha1.digest(&data);
+        data.extend_from_slice(&checksum);
+        assert!(Index::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_stat_data_round_trip_and_match() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, "hello").unwrap();
+        let metadata = fs::metadata(&file).unwrap();
+
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.add_entry(IndexEntry::from_metadata("file.txt".to_string(), "1".repeat(40), &metadata));
+        let loaded = Index::parse(&index.encode().unwrap()).unwrap();
+        let entry = loaded.get_entry("file.txt").unwrap();
+        assert_eq!(entry, index.get_entry("file.txt").unwrap());
+        assert!(entry.matches_stat(&metadata));
+
+        fs::write(&file, "hello, world").unwrap();
+        assert!(!entry.matches_stat(&fs::metadata(&file).unwrap()));
+    }
+
+    #[test]
+    fn test_racy_entries_are_rehashed_and_smudged() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, "hello").unwrap();
+        let metadata = fs::metadata(&file).unwrap();
+        let entry = IndexEntry::from_metadata("file.txt".to_string(), "1".repeat(40), &metadata);
+
+        // Index written at the same instant as the file
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.entries.insert(entry.path.clone(), entry.clone());
+        index.timestamp = Some((entry.mtime, entry.mtime_nsec));
+        assert!(index.is_racy(&entry));
+        assert!(index.needs_rehash(&entry, &metadata));
+
+        // Saved without being checked: the size is smudged so it stays suspect
+        let saved = Index::parse(&index.encode().unwrap()).unwrap();
+        assert_eq!(saved.get_entry("file.txt").unwrap().size, 0);
+        assert!(!saved.get_entry("file.txt").unwrap().matches_stat(&metadata));
+
+        // Once checked, it is written as is
+        index.refresh_entry("file.txt", &metadata);
+        let saved = Index::parse(&index.encode().unwrap()).unwrap();
+        assert_eq!(saved.get_entry("file.txt").unwrap().size, 5);
+
+        index.timestamp = Some((entry.mtime + 1, 0));
+        assert!(!index.needs_rehash(&entry, &metadata));
+    }
+
+    #[test]
+    fn test_conflict_stages_round_trip_and_resolve() {
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.add_entry(entry("clean.txt", &"0".repeat(40)));
+        index.add_entry(entry("file.txt", &"0".repeat(40)));
+        index.add_conflict_entry(entry("file.txt", &"1".repeat(40)), STAGE_BASE).unwrap();
+        index.add_conflict_entry(entry("file.txt", &"2".repeat(40)), STAGE_OURS).unwrap();
+        index.add_conflict_entry(entry("file.txt", &"3".repeat(40)), STAGE_THEIRS).unwrap();
+        assert!(index.add_conflict_entry(entry("file.txt", &"4".repeat(40)), 4).is_err());
+
+        // The stage 0 entry is replaced by the stages
+        assert!(!index.contains("file.txt"));
+        assert_eq!(index.unmerged_paths(), vec!["file.txt"]);
+
+        let data = index.encode().unwrap();
+        // Second entry (after clean.txt, 72 bytes) is file.txt at stage 1
+        assert_eq!(data[12 + 72 + 60] >> 4, 0x1);
+
+        let mut loaded = Index::parse(&data).unwrap();
+        assert_eq!(loaded.conflicts, index.conflicts);
+        let conflict = loaded.get_conflict("file.txt").unwrap();
+        assert_eq!(conflict.description(), "both modified");
+        assert_eq!(conflict.stage(STAGE_THEIRS).unwrap().hash, "3".repeat(40));
+        assert_eq!(loaded.all_entries().count(), 4);
+
+        // Staging the path collapses the stages back to 0
+        loaded.add_entry(entry("file.txt", &"5".repeat(40)));
+        assert!(!loaded.has_conflicts());
+        let resolved = Index::parse(&loaded.encode().unwrap()).unwrap();
+        assert_eq!(resolved.get_entry("file.txt").unwrap().hash, "5".repeat(40));
+        assert!(resolved.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_conflict_descriptions() {
+        let side = || Some(entry("f", &"0".repeat(40)));
+        let conflict = Conflict { base: side(), ours: side(), theirs: None };
+        assert_eq!(conflict.description(), "deleted by them");
+        let conflict = Conflict { base: None, ours: side(), theirs: side() };
+        assert_eq!(conflict.description(), "both added");
+    }
+
+    #[test]
+    fn test_concurrent_update_is_refused() {
+        let temp = tempdir().unwrap();
+        let index_path = temp.path().join("index");
+
+        let (mut index, lock) = Index::load_for_update(&index_path, ObjectFormat::Sha1).unwrap();
+        assert!(Index::load_for_update(&index_path, ObjectFormat::Sha1).is_err());
+        assert!(Index::new(ObjectFormat::Sha1).save(&index_path).is_err());
+
+        index.add_entry(entry("file.txt", &"e".repeat(40)));
+        index.save_locked(lock).unwrap();
+        assert!(!temp.path().join("index.lock").exists());
+        assert!(Index::load(&index_path, ObjectFormat::Sha1).unwrap().contains("file.txt"));
+    }
+
+    #[test]
+    fn test_format_comes_from_the_repository() {
+        let temp = tempdir().unwrap();
+        let index_path = temp.path().join("index");
+
+        // An empty SHA-1 index is taken over by a SHA-256 repository
+        Index::new(ObjectFormat::Sha1).save(&index_path).unwrap();
+        let index = Index::load(&index_path, ObjectFormat::Sha256).unwrap();
+        assert_eq!(index.format, ObjectFormat::Sha256);
+
+        // One with entries is not
+        let mut index = Index::new(ObjectFormat::Sha1);
+        index.add_entry(entry("file.txt", &"e".repeat(40)));
+        index.save(&index_path).unwrap();
+        let err = Index::load(&index_path, ObjectFormat::Sha256).unwrap_err();
+        assert!(err.to_string().contains("uses sha1 object IDs"));
+    }
+
+    #[test]
+    fn test_legacy_json_index_is_read() {
+        let temp = tempdir().unwrap();
+        let index_path = temp.path().join("index");
+        let hash = "f".repeat(40);
+        fs::write(&index_path, format!(
+            r#"{{"entries": {{"a.txt": {{"path": "a.txt", "hash": "{}", "size": 3, "mtime": 7}}}}}}"#,
+            hash
+        )).unwrap();
+
+        let index = Index::load(&index_path, ObjectFormat::Sha1).unwrap();
+        assert_eq!(index.get_entry("a.txt").unwrap().hash, hash);
+
+        index.save(&index_path).unwrap();
+        assert!(fs::read(&index_path).unwrap().starts_with(b"DIRC"));
+        let converted = Index::load(&index_path, ObjectFormat::Sha1).unwrap();
+        assert_eq!(converted.get_entry("a.txt").unwrap().hash, hash);
+        assert_eq!(converted.get_entry("a.txt").unwrap().mtime, 7);
+    }
+}
@@ -0,0 +1,440 @@
+//! # Large File Storage
+//!
+//! Big binary files make every clone and repack slower, and their history
+//! is rarely useful. Paths listed in `.ritattributes` with `filter=lfs` are
+//! stored in the object database as small pointer blobs, in the format
+//! Git LFS uses:
+//!
+//! ```text
+//! version https://git-lfs.github.com/spec/v1
+//! oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393
+//! size 12345
+//! ```
+//!
+//! The real content lives in `.rit/lfs/objects/<oid[0..2]>/<oid[2..4]>/<oid>`,
+//! named by its SHA-256 whatever the repository's object format.
+//!
+//! ```text
+//! # .ritattributes
+//! *.psd filter=lfs
+//! assets/*.bin filter=lfs
+//! assets/small.bin -filter
+//! ```
+//!
+//! Patterns use the same syntax as `.ritignore`, and later lines override
+//! earlier ones. Commands swap the two forms at the working tree boundary:
+//!
+//! - **clean** (`add`, `write-tree`, and hashing in `status` and `diff`):
+//!   working tree content becomes a pointer, and `add` and `write-tree`
+//!   copy the content into the store
+//! - **smudge** (`checkout`): a pointer blob is replaced by the content
+//!   from the store; if the content is missing the pointer is written
+//!   instead, with a warning
+//!
+//! ## Example
+//!
+//! ```
+//! use rit::lfs::Pointer;
+//!
+//! let pointer = Pointer::for_content(b"hello\n");
+//! assert_eq!(pointer.size, 6);
+//! assert_eq!(Pointer::parse(&pointer.to_blob()), Some(pointer));
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+
+use crate::Repository;
+use crate::atomic::AtomicFile;
+use crate::commands::hash_object;
+use crate::hash::ObjectFormat;
+use crate::ignore::IgnoreRules;
+
+/// Name of the attributes file in the repository root
+pub const ATTRIBUTES_FILE: &str = ".ritattributes";
+
+/// First line of every pointer blob
+pub const POINTER_VERSION: &str = "https://git-lfs.github.com/spec/v1";
+
+/// Pointer blobs larger than this are never treated as pointers
+const MAX_POINTER_SIZE: usize = 1024;
+
+/// Chunk size for streaming file content into the store
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A pointer to content kept in the LFS store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pointer {
+    /// SHA-256 of the content, in hex
+    pub oid: String,
+    /// Size of the content in bytes
+    pub size: u64,
+}
+
+impl Pointer {
+    /// The pointer for some content
+    pub fn for_content(content: &[u8]) -> Self {
+        Self {
+            oid: hex::encode(ObjectFormat::Sha256.digest(content)),
+            size: content.len() as u64,
+        }
+    }
+
+    /// Parse a pointer blob
+    ///
+    /// # Returns
+    ///
+    /// `None` if the blob is not a pointer
+    pub fn parse(blob: &[u8]) -> Option<Self> {
+        if blob.len() > MAX_POINTER_SIZE {
+            return None;
+        }
+        let text = std::str::from_utf8(blob).ok()?;
+        let mut lines = text.lines();
+
+        if lines.next()? != format!("version {}", POINTER_VERSION) {
+            return None;
+        }
+
+        let mut oid = None;
+        let mut size = None;
+        for line in lines {
+            match line.split_once(' ')? {
+                ("oid", value) => oid = Some(value.strip_prefix("sha256:")?.to_string()),
+                ("size", value) => size = Some(value.parse().ok()?),
+                // Extension keys (ext-0-foo ...) are allowed but unused
+                _ => {}
+            }
+        }
+
+        let oid = oid.filter(|oid| ObjectFormat::Sha256.is_valid_hex(oid))?;
+        Some(Self { oid, size: size? })
+    }
+
+    /// The pointer blob's contents
+    pub fn to_blob(&self) -> Vec<u8> {
+        format!("version {}\noid sha256:{}\nsize {}\n", POINTER_VERSION, self.oid, self.size)
+            .into_bytes()
+    }
+}
+
+/// Which paths are stored in LFS, from `.ritattributes`
+#[derive(Debug, Clone, Default)]
+pub struct LfsAttributes {
+    rules: IgnoreRules,
+}
+
+impl LfsAttributes {
+    /// Parse the contents of an attributes file
+    ///
+    /// Each line is a pattern followed by attributes. `filter=lfs` marks
+    /// matching paths as LFS files; `-filter`, `!filter` or any other
+    /// filter value unmarks them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rit::lfs::LfsAttributes;
+    ///
+    /// let attributes = LfsAttributes::parse("*.bin filter=lfs\nkeep.bin -filter\n");
+    /// assert!(attributes.is_tracked("data/model.bin"));
+    /// assert!(!attributes.is_tracked("keep.bin"));
+    /// assert!(!attributes.is_tracked("notes.txt"));
+    /// ```
+    pub fn parse(content: &str) -> Self {
+        let mut patterns = String::new();
+
+        for line in content.lines() {
+            let mut words = line.split_whitespace();
+            let Some(pattern) = words.next().filter(|p| !p.starts_with('#')) else {
+                continue;
+            };
+
+            for attribute in words {
+                match attribute {
+                    "filter=lfs" => patterns.push_str(&format!("{}\n", pattern)),
+                    "-filter" | "!filter" => patterns.push_str(&format!("!{}\n", pattern)),
+                    other if other.starts_with("filter=") => patterns.push_str(&format!("!{}\n", pattern)),
+                    _ => {}
+                }
+            }
+        }
+
+        Self { rules: IgnoreRules::parse(&patterns) }
+    }
+
+    /// Load `.ritattributes` from the repository root (missing file: nothing is tracked)
+    pub fn load(repo_root: &Path) -> Result<Self> {
+        let path = repo_root.join(ATTRIBUTES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .context(format!("Failed to read {}", path.display()))?;
+        Ok(Self::parse(&content))
+    }
+
+    /// Whether a path (relative to the repository root) is stored in LFS
+    pub fn is_tracked(&self, path: &str) -> bool {
+        self.rules.is_ignored(&path.replace('\\', "/"), false)
+    }
+}
+
+/// The LFS content store, `.rit/lfs/objects`
+pub fn objects_dir(repo: &Repository) -> PathBuf {
+    repo.rit_dir.join("lfs").join("objects")
+}
+
+/// Where the content for an oid is stored
+pub fn object_path(repo: &Repository, oid: &str) -> PathBuf {
+    objects_dir(repo).join(&oid[..2]).join(&oid[2..4]).join(oid)
+}
+
+/// Hash a file's content with SHA-256, copying it to `out` as it is read
+fn stream_content(path: &Path, out: &mut dyn Write) -> Result<Pointer> {
+    let mut file = File::open(path)
+        .context(format!("Failed to read file: {}", path.display()))?;
+    let mut hasher = ObjectFormat::Sha256.hasher();
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    let mut size = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        out.write_all(&buffer[..read])?;
+        size += read as u64;
+    }
+
+    Ok(Pointer { oid: hasher.finalize_hex(), size })
+}
+
+/// Read a file that already holds a pointer blob
+///
+/// A checkout writes the pointer itself when the content is missing from
+/// the store. Only the first bytes are read, since anything bigger than a
+/// pointer can't be one.
+fn read_pointer_file(path: &Path) -> Result<Option<Vec<u8>>> {
+    let file = File::open(path)
+        .context(format!("Failed to read file: {}", path.display()))?;
+    let mut head = Vec::new();
+    file.take(MAX_POINTER_SIZE as u64 + 1).read_to_end(&mut head)
+        .context(format!("Failed to read file: {}", path.display()))?;
+    Ok(Pointer::parse(&head).map(|_| head))
+}
+
+/// Copy a working tree file into the store
+///
+/// The file is streamed, so it is never held in memory. Content that is
+/// already stored isn't written again.
+pub fn store_content(repo: &Repository, path: &Path) -> Result<Pointer> {
+    let tmp_dir = repo.rit_dir.join("lfs").join("tmp");
+    let mut tmp = AtomicFile::new(&tmp_dir)?;
+    let pointer = stream_content(path, &mut tmp)?;
+
+    let object_path = object_path(repo, &pointer.oid);
+    if !object_path.exists() {
+        tmp.persist(&object_path)
+            .context(format!("Failed to store LFS object {}", pointer.oid))?;
+    }
+
+    Ok(pointer)
+}
+
+/// Copy stored content to a file, replacing it atomically
+///
+/// The content is streamed, so it is never held in memory.
+///
+/// # Returns
+///
+/// `false` if the store doesn't have it
+pub fn copy_content(repo: &Repository, pointer: &Pointer, dest: &Path) -> Result<bool> {
+    let path = object_path(repo, &pointer.oid);
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let mut source = File::open(&path)
+        .context(format!("Failed to read LFS object {}", pointer.oid))?;
+    let mut tmp = AtomicFile::new(&repo.rit_dir.join("lfs").join("tmp"))?;
+    io::copy(&mut source, &mut tmp)
+        .context(format!("Failed to read LFS object {}", pointer.oid))?;
+    tmp.persist(dest)?;
+    Ok(true)
+}
+
+/// Check that stored content matches its pointer
+///
+/// # Returns
+///
+/// `None` if it's missing, otherwise whether the hash and size match
+pub fn verify_content(repo: &Repository, pointer: &Pointer) -> Result<Option<bool>> {
+    let path = object_path(repo, &pointer.oid);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let actual = stream_content(&path, &mut io::sink())?;
+    Ok(Some(actual == *pointer))
+}
+
+/// Store a working tree file as a blob, as a pointer if it's an LFS path
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `attributes` - The repository's LFS attributes
+/// * `relative_path` - The file's path relative to the repository root
+/// * `path` - The file on disk
+///
+/// # Returns
+///
+/// The blob hash
+pub fn store_file(
+    repo: &Repository,
+    attributes: &LfsAttributes,
+    relative_path: &str,
+    path: &Path,
+) -> Result<String> {
+    if !attributes.is_tracked(relative_path) {
+        return hash_object::store_file(repo, path);
+    }
+
+    // A pointer is kept as it is rather than wrapped in another pointer
+    if let Some(blob) = read_pointer_file(path)? {
+        return hash_object::store_object(repo, "blob", &blob);
+    }
+
+    let pointer = store_content(repo, path)?;
+    hash_object::store_object(repo, "blob", &pointer.to_blob())
+}
+
+/// The blob hash [`store_file`] would produce, without storing anything
+pub fn hash_file(
+    repo: &Repository,
+    attributes: &LfsAttributes,
+    relative_path: &str,
+    path: &Path,
+) -> Result<String> {
+    if !attributes.is_tracked(relative_path) {
+        return hash_object::hash_file(repo.object_format(), path);
+    }
+
+    if let Some(blob) = read_pointer_file(path)? {
+        return Ok(repo.object_format().hash_object("blob", &blob));
+    }
+
+    let pointer = stream_content(path, &mut io::sink())?;
+    Ok(repo.object_format().hash_object("blob", &pointer.to_blob()))
+}
+
+/// Working tree content as it would be stored: a pointer for LFS paths
+pub fn clean(attributes: &LfsAttributes, relative_path: &str, content: Vec<u8>) -> Vec<u8> {
+    if attributes.is_tracked(relative_path) && Pointer::parse(&content).is_none() {
+        Pointer::for_content(&content).to_blob()
+    } else {
+        content
+    }
+}
+
+/// Write a blob to the working tree file `dest`
+///
+/// Pointer blobs are replaced with the stored content. If the store
+/// doesn't have it, the pointer itself is written and a warning printed.
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `path` - The file's path relative to the repository root, for messages
+/// * `blob` - The blob's content
+/// * `dest` - The file to write
+pub fn smudge(repo: &Repository, path: &str, blob: &[u8], dest: &Path) -> Result<()> {
+    if let Some(pointer) = Pointer::parse(blob) {
+        if copy_content(repo, &pointer, dest)? {
+            return Ok(());
+        }
+        eprintln!("warning: {}: LFS object {} is missing; writing the pointer", path, pointer.oid);
+    }
+
+    fs::write(dest, blob)
+        .context(format!("Failed to write file: {}", dest.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_pointer() {
+        let oid = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+        let blob = format!("version {}\noid sha256:{}\nsize 12345\n", POINTER_VERSION, oid);
+        assert_eq!(
+            Pointer::parse(blob.as_bytes()),
+            Some(Pointer { oid: oid.to_string(), size: 12345 })
+        );
+
+        assert_eq!(Pointer::parse(b"hello\n"), None);
+        assert_eq!(Pointer::parse(format!("version {}\noid sha256:abc\nsize 1\n", POINTER_VERSION).as_bytes()), None);
+        assert_eq!(Pointer::parse(format!("version {}\noid sha256:{}\n", POINTER_VERSION, oid).as_bytes()), None);
+    }
+
+    #[test]
+    fn test_store_and_smudge() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let attributes = LfsAttributes::parse("*.bin filter=lfs\n");
+
+        let path = temp.path().join("model.bin");
+        fs::write(&path, b"\x00\x01 big binary").unwrap();
+
+        let blob_hash = store_file(&repo, &attributes, "model.bin", &path).unwrap();
+        assert_eq!(hash_file(&repo, &attributes, "model.bin", &path).unwrap(), blob_hash);
+
+        let blob = repo.objects.read(&blob_hash).unwrap().content;
+        let pointer = Pointer::parse(&blob).unwrap();
+        assert_eq!(pointer, Pointer::for_content(b"\x00\x01 big binary"));
+        assert_eq!(verify_content(&repo, &pointer).unwrap(), Some(true));
+        assert_eq!(clean(&attributes, "model.bin", b"\x00\x01 big binary".to_vec()), blob);
+
+        let out = temp.path().join("out");
+        smudge(&repo, "model.bin", &blob, &out).unwrap();
+        assert_eq!(fs::read(&out).unwrap(), b"\x00\x01 big binary");
+        smudge(&repo, "a.txt", b"plain\n", &out).unwrap();
+        assert_eq!(fs::read(&out).unwrap(), b"plain\n");
+
+        // Missing content leaves the pointer in place
+        fs::remove_file(object_path(&repo, &pointer.oid)).unwrap();
+        assert_eq!(verify_content(&repo, &pointer).unwrap(), None);
+        smudge(&repo, "model.bin", &blob, &out).unwrap();
+        assert_eq!(fs::read(&out).unwrap(), blob);
+
+        // Untracked paths are stored as they are
+        let text = temp.path().join("a.txt");
+        fs::write(&text, b"plain\n").unwrap();
+        let hash = store_file(&repo, &attributes, "a.txt", &text).unwrap();
+        assert_eq!(repo.objects.read(&hash).unwrap().content, b"plain\n");
+    }
+
+    #[test]
+    fn test_store_file_keeps_pointer() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let attributes = LfsAttributes::parse("*.bin filter=lfs\n");
+
+        // What a checkout leaves behind when the content is missing
+        let pointer = Pointer::for_content(&[7u8; 1000]);
+        let path = temp.path().join("model.bin");
+        fs::write(&path, pointer.to_blob()).unwrap();
+
+        let blob_hash = store_file(&repo, &attributes, "model.bin", &path).unwrap();
+        assert_eq!(hash_file(&repo, &attributes, "model.bin", &path).unwrap(), blob_hash);
+        assert_eq!(repo.objects.read(&blob_hash).unwrap().content, pointer.to_blob());
+        assert!(!object_path(&repo, &Pointer::for_content(&pointer.to_blob()).oid).exists());
+    }
+}
@@ -10,7 +10,8 @@
 //! ## Architecture Overview
 //!
 //! Git (and Rit) is built on a simple but powerful concept: a content-addressable filesystem.
-//! Every piece of data is stored as an "object" identified by its SHA-1 hash.
+//! Every piece of data is stored as an "object" identified by its SHA-1 hash
+//! (or SHA-256, for repositories created with `--object-format=sha256`).
 //!
 //! ### Object Types
 //!
@@ -23,9 +24,11 @@
 //! ```text
 //! .rit/
 //! ├── HEAD            # Points to current branch (e.g., "ref: refs/heads/main")
+//! ├── config          # Repository settings (e.g., the object format)
 //! ├── objects/        # Object database
 //! │   ├── ab/         # First 2 chars of hash
 //! │   │   └── cdef... # Remaining hash chars (zlib compressed)
+//! │   ├── pack/       # Packfiles (.pack + .idx pairs)
 //! │   └── ...
 //! ├── refs/
 //! │   ├── heads/      # Branch pointers
@@ -51,13 +54,52 @@ pub mod commands;
 pub mod objects;
 pub mod index;
 pub mod ignore;
+pub mod pack;
+pub mod atomic;
+pub mod lockfile;
+pub mod config;
+pub mod hash;
+pub mod cache;
+pub mod refs;
+pub mod commit_graph;
+pub mod lfs;
 
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use anyhow::{Context, Result};
 
+use crate::cache::ObjectCache;
+use crate::commit_graph::CommitGraph;
+use crate::config::Config;
+use crate::hash::ObjectFormat;
+use crate::objects::{Commit, LooseObjectStore, MemoryObjectStore, ObjectStore, Tree};
+
 /// The name of the rit directory (like .git)
 pub const RIT_DIR: &str = ".rit";
 
+/// The name of a Git repository directory, used when there is no `.rit`
+pub const GIT_DIR: &str = ".git";
+
+/// Repository directory set with `--git-dir`, used by [`Repository::find`]
+static GIT_DIR_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Numbers the scratch directories of in-memory repositories
+static SCRATCH_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A private directory standing in for the root of an in-memory repository
+///
+/// Nothing creates it up front; whatever was written there is removed when
+/// the repository is dropped.
+#[derive(Debug)]
+struct ScratchDir(PathBuf);
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
 /// Represents a Rit repository
 #[derive(Debug)]
 pub struct Repository {
@@ -65,11 +107,23 @@ pub struct Repository {
     pub root: PathBuf,
     /// The .rit directory path
     pub rit_dir: PathBuf,
+    /// The object database
+    pub objects: Box<dyn ObjectStore>,
+    /// Parsed commits and trees, for repeated lookups during walks
+    pub cache: ObjectCache,
+    /// The commit-graph file, loaded on first use
+    commit_graph: OnceLock<Option<CommitGraph>>,
+    /// The root of an in-memory repository, cleaned up on drop
+    scratch: Option<ScratchDir>,
 }
 
 impl Repository {
     /// Find the repository root by walking up from the current directory
     ///
+    /// If a repository directory was given with [`Repository::set_git_dir`]
+    /// (the `--git-dir` option), that is opened instead, with the current
+    /// directory as the working tree.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -80,20 +134,39 @@ impl Repository {
     /// ```
     pub fn find() -> Result<Self> {
         let current_dir = std::env::current_dir()?;
+
+        let git_dir = GIT_DIR_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(git_dir) = git_dir {
+            return Self::open_git_dir(&current_dir.join(git_dir), &current_dir);
+        }
+
         Self::find_from(&current_dir)
     }
 
+    /// Use this repository directory for every later [`Repository::find`]
+    ///
+    /// Relative paths are resolved against the current directory when
+    /// `find` is called.
+    pub fn set_git_dir(git_dir: &Path) {
+        *GIT_DIR_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner()) = Some(git_dir.to_path_buf());
+    }
+
     /// Find repository starting from a specific path
+    ///
+    /// At each level a `.rit` directory is preferred; failing that, an
+    /// existing Git repository (`.git`) is opened, so rit can read projects
+    /// that were never converted.
     pub fn find_from(start: &Path) -> Result<Self> {
         let mut current = start.to_path_buf();
 
         loop {
             let rit_dir = current.join(RIT_DIR);
             if rit_dir.is_dir() {
-                return Ok(Self {
-                    root: current,
-                    rit_dir,
-                });
+                return Self::open(current, rit_dir);
+            }
+
+            if let Some(git_dir) = Self::git_dir_at(&current)? {
+                return Self::open_git_dir(&git_dir, &current);
             }
 
             if !current.pop() {
@@ -102,12 +175,89 @@ impl Repository {
         }
     }
 
+    /// Open a Git repository directory (such as `project/.git`)
+    ///
+    /// # Arguments
+    ///
+    /// * `git_dir` - The repository directory, holding `objects/`, `refs/`
+    ///   and `HEAD`
+    /// * `work_tree` - The working tree that goes with it
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rit::Repository;
+    /// use std::path::Path;
+    ///
+    /// let repo = Repository::open_git_dir(Path::new("project/.git"), Path::new("project")).unwrap();
+    /// ```
+    pub fn open_git_dir(git_dir: &Path, work_tree: &Path) -> Result<Self> {
+        if !git_dir.join("objects").is_dir() || !git_dir.join("HEAD").is_file() {
+            anyhow::bail!("fatal: not a git repository: '{}'", git_dir.display());
+        }
+        Self::open(work_tree.to_path_buf(), git_dir.to_path_buf())
+    }
+
+    /// Look for a Git repository directly in `dir`
+    ///
+    /// `.git` is usually a directory, but in submodules it is a file
+    /// holding `gitdir: <path>`.
+    fn git_dir_at(dir: &Path) -> Result<Option<PathBuf>> {
+        let dot_git = dir.join(GIT_DIR);
+
+        if dot_git.is_dir() {
+            return Ok(Some(dot_git));
+        }
+
+        if dot_git.is_file() {
+            let content = std::fs::read_to_string(&dot_git)
+                .context(format!("Failed to read {}", dot_git.display()))?;
+            let target = content.trim().strip_prefix("gitdir:")
+                .context(format!("fatal: invalid gitfile format: {}", dot_git.display()))?;
+            return Ok(Some(dir.join(target.trim())));
+        }
+
+        Ok(None)
+    }
+
+    /// Open an existing repository directory with the working tree `root`
+    fn open(root: PathBuf, rit_dir: PathBuf) -> Result<Self> {
+        let format = Self::read_object_format(&rit_dir)?;
+        let objects = Box::new(LooseObjectStore::with_format(rit_dir.join("objects"), format));
+        Ok(Self { root, rit_dir, objects, cache: ObjectCache::default(), commit_graph: OnceLock::new(), scratch: None })
+    }
+
+    /// Read the object format recorded in a `.rit/config` (or `.git/config`)
+    ///
+    /// Repositories without a config (or without `extensions.objectformat`)
+    /// use SHA-1.
+    pub fn read_object_format(rit_dir: &Path) -> Result<ObjectFormat> {
+        let config = Config::load(&rit_dir.join("config"))?;
+
+        let version: u32 = config.get("core.repositoryformatversion")
+            .unwrap_or("0")
+            .parse()
+            .context("Invalid core.repositoryformatversion")?;
+        if version > 1 {
+            anyhow::bail!("fatal: unknown repository format version: {}", version);
+        }
+
+        match config.get("extensions.objectformat") {
+            Some(name) => ObjectFormat::from_name(name),
+            None => Ok(ObjectFormat::Sha1),
+        }
+    }
+
     /// Initialize a new repository at the given path
     ///
     /// Creates the `.rit` directory structure:
     /// - `.rit/objects/` - Object database
     /// - `.rit/refs/heads/` - Branch references
     /// - `.rit/HEAD` - Current branch pointer
+    /// - `.rit/config` - Repository settings
+    ///
+    /// Objects are named with SHA-1; see [`Repository::init_with_format`]
+    /// for SHA-256.
     ///
     /// # Example
     ///
@@ -118,12 +268,43 @@ impl Repository {
     /// let repo = Repository::init(Path::new(".")).expect("Failed to init");
     /// ```
     pub fn init(path: &Path) -> Result<Self> {
+        Self::init_repository(path, None)
+    }
+
+    /// Initialize a new repository that uses the given object format
+    ///
+    /// Reinitializing an existing repository with a different format is an
+    /// error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rit::Repository;
+    /// use rit::hash::ObjectFormat;
+    /// use std::path::Path;
+    ///
+    /// let repo = Repository::init_with_format(Path::new("."), ObjectFormat::Sha256).unwrap();
+    /// assert_eq!(repo.object_format(), ObjectFormat::Sha256);
+    /// ```
+    pub fn init_with_format(path: &Path, format: ObjectFormat) -> Result<Self> {
+        Self::init_repository(path, Some(format))
+    }
+
+    /// Shared implementation of [`Repository::init`] and [`Repository::init_with_format`]
+    fn init_repository(path: &Path, requested: Option<ObjectFormat>) -> Result<Self> {
         let root = path.to_path_buf();
         let rit_dir = root.join(RIT_DIR);
 
-        if rit_dir.exists() {
+        let format = if rit_dir.exists() {
+            let existing = Self::read_object_format(&rit_dir)?;
+            if requested.is_some_and(|f| f != existing) {
+                anyhow::bail!("fatal: attempt to reinitialize repository with different hash");
+            }
             println!("Reinitialized existing rit repository in {}", rit_dir.display());
+            existing
         } else {
+            let format = requested.unwrap_or_default();
+
             // Create directory structure
             std::fs::create_dir(&rit_dir)
                 .context("Failed to create .rit directory")?;
@@ -137,13 +318,152 @@ impl Repository {
                 .context("Failed to create refs/tags directory")?;
 
             // Create HEAD file pointing to main branch
-            std::fs::write(rit_dir.join("HEAD"), "ref: refs/heads/main\n")
+            atomic::write(&rit_dir.join("HEAD"), &rit_dir, b"ref: refs/heads/main\n")
                 .context("Failed to create HEAD file")?;
 
+            // Anything but SHA-1 needs format version 1 and an extension
+            let mut config = Config::new();
+            let version = if format == ObjectFormat::Sha1 { "0" } else { "1" };
+            config.set("core.repositoryformatversion", version);
+            config.set("core.bare", "false");
+            if format != ObjectFormat::Sha1 {
+                config.set("extensions.objectformat", format.name());
+            }
+            config.save(&rit_dir.join("config"))?;
+
             println!("Initialized empty rit repository in {}", rit_dir.display());
+            format
+        };
+
+        let objects = Box::new(LooseObjectStore::with_format(rit_dir.join("objects"), format));
+        Ok(Self { root, rit_dir, objects, cache: ObjectCache::default(), commit_graph: OnceLock::new(), scratch: None })
+    }
+
+    /// Open a repository at `root` with a custom object store
+    ///
+    /// Refs, HEAD and the index are still read from `root/.rit`; only
+    /// object storage goes through `objects`.
+    pub fn with_object_store(root: PathBuf, objects: Box<dyn ObjectStore>) -> Self {
+        let rit_dir = root.join(RIT_DIR);
+        // The commit-graph lives in the objects directory, which a custom
+        // store doesn't have
+        let commit_graph = OnceLock::from(None);
+        Self { root, rit_dir, objects, cache: ObjectCache::default(), commit_graph, scratch: None }
+    }
+
+    /// Create a repository whose objects live only in memory
+    ///
+    /// Useful for unit tests and ephemeral repositories: blobs, trees and
+    /// commits can be written and read back without touching the disk.
+    ///
+    /// Only the object store is in memory. Refs, HEAD and the index are
+    /// still files, under a private root in the system temp directory that
+    /// doesn't exist until something is written there and is removed when
+    /// the repository is dropped. They never resolve to the current
+    /// directory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rit::Repository;
+    /// use rit::objects::Blob;
+    ///
+    /// let repo = Repository::in_memory();
+    /// let hash = Blob::new(b"Hello!".to_vec()).store(&repo).unwrap();
+    /// assert!(repo.objects.exists(&hash));
+    /// ```
+    pub fn in_memory() -> Self {
+        Self::in_memory_with_format(ObjectFormat::Sha1)
+    }
+
+    /// Create an in-memory repository that uses the given object format
+    pub fn in_memory_with_format(format: ObjectFormat) -> Self {
+        let id = SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let root = std::env::temp_dir().join(format!("rit-memory-{}-{}", std::process::id(), id));
+        let mut repo = Self::with_object_store(root.clone(), Box::new(MemoryObjectStore::with_format(format)));
+        repo.scratch = Some(ScratchDir(root));
+        repo
+    }
+
+    /// The hash algorithm used to name objects in this repository
+    pub fn object_format(&self) -> ObjectFormat {
+        self.objects.object_format()
+    }
+
+    /// Read and parse a commit, going through the object cache
+    ///
+    /// `hash` may be abbreviated. Fails if the object is missing or is not
+    /// a commit.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rit::Repository;
+    ///
+    /// let repo = Repository::find().unwrap();
+    /// let commit = repo.read_commit("abc1234").unwrap();
+    /// println!("{}", commit.message);
+    /// ```
+    pub fn read_commit(&self, hash: &str) -> Result<Arc<Commit>> {
+        let hash = self.full_hash(hash)?;
+        self.cache.commit(&hash, || {
+            let object = self.objects.read(&hash)?;
+            if object.object_type != "commit" {
+                anyhow::bail!("object {} is a {}, not a commit", hash, object.object_type);
+            }
+            Commit::parse(&String::from_utf8_lossy(&object.content))
+                .context(format!("Invalid commit: {}", hash))
+        })
+    }
+
+    /// Read and parse a tree, going through the object cache
+    ///
+    /// `hash` may be abbreviated. Fails if the object is missing or is not
+    /// a tree.
+    pub fn read_tree(&self, hash: &str) -> Result<Arc<Tree>> {
+        let hash = self.full_hash(hash)?;
+        self.cache.tree(&hash, || {
+            let object = self.objects.read(&hash)?;
+            if object.object_type != "tree" {
+                anyhow::bail!("object {} is a {}, not a tree", hash, object.object_type);
+            }
+            Tree::parse(&object.content, self.object_format())
+                .context(format!("Invalid tree: {}", hash))
+        })
+    }
+
+    /// The repository's commit-graph, if it has one
+    ///
+    /// Read on first use. A commit-graph that can't be parsed is reported
+    /// on stderr and ignored, since everything in it can be recomputed
+    /// from the commits themselves.
+    pub fn commit_graph(&self) -> Option<&CommitGraph> {
+        self.commit_graph.get_or_init(|| {
+            match CommitGraph::open(&self.objects_dir(), self.object_format()) {
+                Ok(graph) => graph,
+                Err(e) => {
+                    eprintln!("warning: ignoring commit-graph: {:#}", e);
+                    None
+                }
+            }
+        }).as_ref()
+    }
+
+    /// Expand an abbreviated hash so cache keys are always full IDs
+    fn full_hash(&self, hash: &str) -> Result<String> {
+        if self.object_format().is_valid_hex(hash) {
+            Ok(hash.to_string())
+        } else {
+            commands::cat_file::resolve_object(self, hash)
         }
+    }
 
-        Ok(Self { root, rit_dir })
+    /// Open the on-disk loose object store (with packs) directly
+    ///
+    /// Maintenance commands (`fsck`, `prune`, `repack`) need to see loose
+    /// files and packs separately, which the [`ObjectStore`] trait hides.
+    pub fn loose_store(&self) -> LooseObjectStore {
+        LooseObjectStore::with_format(self.objects_dir(), self.object_format())
     }
 
     /// Get the path to the objects directory
@@ -161,10 +481,57 @@ impl Repository {
         self.rit_dir.join("HEAD")
     }
 
+    /// Get the path to the config file
+    pub fn config_path(&self) -> PathBuf {
+        self.rit_dir.join("config")
+    }
+
     /// Get the path to the index file
     pub fn index_path(&self) -> PathBuf {
         self.rit_dir.join("index")
     }
+
+    /// Turn a path given on the command line into one relative to the
+    /// repository root, with `/` separators (`""` for the root itself)
+    ///
+    /// Relative paths start from the current directory. `.` and `..` are
+    /// resolved without touching the filesystem, so the path need not exist.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use rit::Repository;
+    ///
+    /// let repo = Repository::find().unwrap();
+    /// // Run from <root>/src
+    /// assert_eq!(repo.relative_path(Path::new("../README.md")).unwrap(), "README.md");
+    /// ```
+    pub fn relative_path(&self, path: &Path) -> Result<String> {
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir()?.join(path)
+        };
+
+        let mut normalized = PathBuf::new();
+        for component in absolute.components() {
+            match component {
+                std::path::Component::CurDir => {}
+                std::path::Component::ParentDir => {
+                    normalized.pop();
+                }
+                other => normalized.push(other),
+            }
+        }
+
+        let relative = normalized.strip_prefix(&self.root)
+            .map_err(|_| anyhow::anyhow!(
+                "fatal: {}: '{}' is outside repository at '{}'",
+                path.display(), path.display(), self.root.display()
+            ))?;
+        Ok(relative.to_string_lossy().replace('\\', "/"))
+    }
 }
 
 #[cfg(test)]
@@ -181,6 +548,81 @@ mod tests {
         assert!(repo.objects_dir().exists());
         assert!(repo.refs_dir().exists());
         assert!(repo.head_path().exists());
+        assert_eq!(repo.object_format(), ObjectFormat::Sha1);
     }
-}
 
+    #[test]
+    fn test_init_sha256_records_format() {
+        let temp = tempdir().unwrap();
+        Repository::init_with_format(temp.path(), ObjectFormat::Sha256).unwrap();
+
+        let config = Config::load(&temp.path().join(".rit/config")).unwrap();
+        assert_eq!(config.get("extensions.objectformat"), Some("sha256"));
+
+        // Reopening picks the format up from config
+        let repo = Repository::find_from(temp.path()).unwrap();
+        assert_eq!(repo.object_format(), ObjectFormat::Sha256);
+
+        // Plain reinit keeps it; switching formats is refused
+        assert_eq!(Repository::init(temp.path()).unwrap().object_format(), ObjectFormat::Sha256);
+        assert!(Repository::init_with_format(temp.path(), ObjectFormat::Sha1).is_err());
+    }
+
+    #[test]
+    fn test_read_commit_and_tree_are_cached() {
+        use crate::objects::commit::Author;
+
+        let repo = Repository::in_memory();
+        let tree_hash = repo.objects.write("tree", &Tree::new().serialize().unwrap()).unwrap();
+        let author = Author::with_timestamp("Test", "test@test.com", 1000, "+0000");
+        let commit = Commit::simple(&tree_hash, None, author, "Initial");
+        let commit_hash = repo.objects.write("commit", commit.serialize().as_bytes()).unwrap();
+
+        let first = repo.read_commit(&commit_hash).unwrap();
+        let again = repo.read_commit(&commit_hash[..7]).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        repo.read_tree(&first.tree).unwrap();
+
+        let stats = repo.cache.stats();
+        assert_eq!((stats.commit_hits, stats.commit_misses, stats.tree_misses), (1, 1, 1));
+
+        // Wrong type is an error either way
+        assert!(repo.read_tree(&commit_hash).is_err());
+        assert!(repo.read_commit(&tree_hash).is_err());
+    }
+
+    #[test]
+    fn test_in_memory_has_private_root() {
+        let repo = Repository::in_memory();
+        assert!(repo.rit_dir.is_absolute());
+        assert!(!repo.head_path().exists());
+        assert_ne!(Repository::in_memory().root, repo.root);
+
+        // Files written next to the objects go away with the repository
+        std::fs::create_dir_all(repo.refs_dir()).unwrap();
+        let root = repo.root.clone();
+        drop(repo);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_find_falls_back_to_git_dir() {
+        let temp = tempdir().unwrap();
+        Repository::init(temp.path()).unwrap();
+        std::fs::rename(temp.path().join(RIT_DIR), temp.path().join(GIT_DIR)).unwrap();
+        std::fs::create_dir(temp.path().join("sub")).unwrap();
+
+        let repo = Repository::find_from(&temp.path().join("sub")).unwrap();
+        assert_eq!(repo.rit_dir, temp.path().join(GIT_DIR));
+        assert_eq!(repo.root, temp.path());
+
+        // A gitfile (as in submodules) points elsewhere
+        let module = temp.path().join("module");
+        std::fs::create_dir(&module).unwrap();
+        std::fs::write(module.join(GIT_DIR), "gitdir: ../.git\n").unwrap();
+        let repo = Repository::find_from(&module).unwrap();
+        assert_eq!(repo.rit_dir, module.join("../.git"));
+
+        assert!(Repository::open_git_dir(&module, &module).is_err());
+    }
+}
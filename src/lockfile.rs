@@ -0,0 +1,187 @@
+//! # Lock Files
+//!
+//! A file that is read, modified and written back (like the index) needs
+//! more than an atomic rename: two processes that both load it and both
+//! save it would silently drop one side's changes. Writers therefore take
+//! `<file>.lock` first, created with `O_CREAT | O_EXCL` so only one process
+//! can hold it. The new content is written to the lock file, which is then
+//! renamed over the target, releasing the lock in the same step.
+//!
+//! If the lock already exists, acquiring fails at once with the lock's age,
+//! so a lock left behind by a crashed process is easy to recognise and
+//! remove.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use std::path::Path;
+//! use rit::lockfile::LockFile;
+//!
+//! let mut lock = LockFile::acquire(Path::new(".rit/index")).unwrap();
+//! lock.write_all(b"new content").unwrap();
+//! lock.commit().unwrap();
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{Context, Result};
+use crate::atomic;
+
+/// Suffix added to the locked file's name
+pub const LOCK_SUFFIX: &str = ".lock";
+
+/// An exclusive lock on a file, holding its replacement content
+///
+/// Dropping it without calling [`LockFile::commit`] deletes the lock file
+/// and leaves the target untouched.
+#[derive(Debug)]
+pub struct LockFile {
+    /// The file being replaced
+    path: PathBuf,
+    /// `<path>.lock`
+    lock_path: PathBuf,
+    /// The open lock file (taken when committing)
+    writer: Option<BufWriter<File>>,
+}
+
+impl LockFile {
+    /// Take the lock for `path` by creating `<path>.lock`
+    ///
+    /// Fails without waiting if another process holds the lock.
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let mut lock_name = path.as_os_str().to_owned();
+        lock_name.push(LOCK_SUFFIX);
+        let lock_path = PathBuf::from(lock_name);
+
+        let file = match File::options().write(true).create_new(true).open(&lock_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                anyhow::bail!("{}", lock_held_message(&lock_path));
+            }
+            Err(e) => {
+                return Err(e).context(format!("Unable to create '{}'", lock_path.display()));
+            }
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            lock_path,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Path of the lock file
+    pub fn lock_path(&self) -> &Path {
+        &self.lock_path
+    }
+
+    /// Rename the lock file over the target, which also releases the lock
+    pub fn commit(mut self) -> Result<()> {
+        let writer = self.writer.take().expect("lock already committed");
+        let file = writer.into_inner()
+            .map_err(|e| e.into_error())
+            .context(format!("Failed to write {}", self.lock_path.display()))?;
+
+        if atomic::fsync_enabled() {
+            file.sync_all()
+                .context(format!("Failed to sync {}", self.lock_path.display()))?;
+        }
+        drop(file);
+
+        fs::rename(&self.lock_path, &self.path)
+            .context(format!("Failed to move {} into place", self.path.display()))
+    }
+}
+
+impl Write for LockFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.as_mut().expect("lock already committed").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().expect("lock already committed").flush()
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // Not committed: release the lock without touching the target
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+/// Explain that a lock is held, including how long ago it was taken
+fn lock_held_message(lock_path: &Path) -> String {
+    let age = fs::metadata(lock_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .map(|d| format!("\nThe lock file was created {} ago.", describe_age(d.as_secs())))
+        .unwrap_or_default();
+
+    format!(
+        "fatal: Unable to create '{}': File exists.\n\n\
+         Another rit process seems to be running in this repository.{}\n\
+         Make sure all rit processes have finished, then try again. If it\n\
+         still fails, a rit process may have crashed here earlier:\n\
+         remove the file manually to continue.",
+        lock_path.display(),
+        age
+    )
+}
+
+/// Describe a number of seconds in the largest whole unit
+fn describe_age(secs: u64) -> String {
+    let (value, unit) = match secs {
+        0..=119 => (secs, "second"),
+        120..=7199 => (secs / 60, "minute"),
+        7200..=172_799 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_is_exclusive_and_released() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("index");
+        fs::write(&path, b"old").unwrap();
+
+        let mut lock = LockFile::acquire(&path).unwrap();
+        assert!(temp.path().join("index.lock").exists());
+
+        let err = LockFile::acquire(&path).unwrap_err().to_string();
+        assert!(err.contains("Another rit process seems to be running"));
+        assert!(err.contains("seconds ago") || err.contains("second ago"));
+
+        lock.write_all(b"new").unwrap();
+        lock.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!temp.path().join("index.lock").exists());
+
+        // Dropping without committing keeps the old content
+        let mut lock = LockFile::acquire(&path).unwrap();
+        lock.write_all(b"discarded").unwrap();
+        drop(lock);
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(LockFile::acquire(&path).is_ok());
+    }
+
+    #[test]
+    fn test_describe_age() {
+        assert_eq!(describe_age(1), "1 second");
+        assert_eq!(describe_age(45), "45 seconds");
+        assert_eq!(describe_age(600), "10 minutes");
+        assert_eq!(describe_age(3 * 86400), "3 days");
+    }
+}
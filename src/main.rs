@@ -20,6 +20,11 @@ use rit::commands;
 #[command(version = "0.1.0")]
 #[command(about = "A Git implementation in Rust", long_about = None)]
 struct Cli {
+    /// Path to the repository directory (e.g. an existing .git), instead
+    /// of searching upward for .rit or .git
+    #[arg(long, global = true, value_name = "PATH")]
+    git_dir: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -32,7 +37,12 @@ enum Commands {
     /// - objects/ (object database)
     /// - refs/heads/ (branch references)
     /// - HEAD (current branch pointer)
-    Init,
+    /// - config (repository settings)
+    Init {
+        /// Hash algorithm for object IDs: sha1 (default) or sha256
+        #[arg(long, value_name = "FORMAT")]
+        object_format: Option<String>,
+    },
 
     /// Compute object ID and optionally store it
     ///
@@ -55,11 +65,35 @@ enum Commands {
     #[command(name = "cat-file")]
     CatFile {
         /// Pretty-print the object contents
-        #[arg(short = 'p')]
+        #[arg(short = 'p', group = "cat_mode")]
         pretty_print: bool,
 
-        /// The object hash to read
-        object_hash: String,
+        /// Show the object type
+        #[arg(short = 't', group = "cat_mode")]
+        show_type: bool,
+
+        /// Show the object size in bytes
+        #[arg(short = 's', group = "cat_mode")]
+        show_size: bool,
+
+        /// Exit with status 0 if the object exists and 1 if not, printing nothing
+        #[arg(short = 'e', group = "cat_mode")]
+        exists: bool,
+
+        /// Read object names from stdin; print a header and the contents
+        /// of each (optionally with a custom header format)
+        #[arg(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true,
+              default_missing_value = "", conflicts_with_all = ["batch_check", "object_hash", "cat_mode"])]
+        batch: Option<String>,
+
+        /// Like --batch, but print only the header
+        #[arg(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true,
+              default_missing_value = "", conflicts_with_all = ["object_hash", "cat_mode"])]
+        batch_check: Option<String>,
+
+        /// The object to read: a hash, ref, or `<rev>:<path>`
+        #[arg(required_unless_present_any = ["batch", "batch_check"])]
+        object_hash: Option<String>,
     },
 
     /// Create a tree object from the current directory
@@ -114,11 +148,36 @@ enum Commands {
         #[arg(short, long)]
         message: String,
 
-        /// Automatically stage files that have been modified (not yet implemented)
-        #[arg(short = 'a')]
+        /// Stage modified and deleted tracked files before committing
+        #[arg(short = 'a', long = "all")]
         auto_add: bool,
     },
 
+    /// Reset the current branch, index or working tree to a commit
+    ///
+    /// With paths, only resets those index entries, which unstages them.
+    Reset {
+        /// Commit to reset to (default HEAD), optionally followed by paths
+        #[arg(value_name = "COMMIT")]
+        args: Vec<String>,
+
+        /// Paths to reset in the index
+        #[arg(last = true, value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// Only move the branch; keep the index and working tree
+        #[arg(long, conflicts_with_all = ["mixed", "hard"])]
+        soft: bool,
+
+        /// Move the branch and reset the index, keeping the working tree (default)
+        #[arg(long, conflicts_with = "hard")]
+        mixed: bool,
+
+        /// Move the branch and reset the index and working tree, discarding changes
+        #[arg(long)]
+        hard: bool,
+    },
+
     /// Show commit logs
     ///
     /// Displays the commit history starting from HEAD.
@@ -138,6 +197,57 @@ enum Commands {
     Add {
         /// Files or directories to stage
         paths: Vec<String>,
+
+        /// Choose hunks to stage interactively, answering on stdin
+        #[arg(short, long)]
+        patch: bool,
+
+        /// Stage modifications and deletions of tracked files only
+        #[arg(short, long, conflicts_with = "all")]
+        update: bool,
+
+        /// Stage all changes, including new and deleted files
+        #[arg(short = 'A', long)]
+        all: bool,
+    },
+
+    /// Remove files from the working tree and the index
+    ///
+    /// Refuses to remove files with uncommitted changes unless -f is given.
+    Rm {
+        /// Files or directories to remove
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Only remove from the index, keeping the working tree files
+        #[arg(long)]
+        cached: bool,
+
+        /// Remove even if the files have staged or unstaged changes
+        #[arg(short, long)]
+        force: bool,
+
+        /// Remove directories and everything tracked under them
+        #[arg(short = 'r')]
+        recursive: bool,
+    },
+
+    /// Move or rename a file or directory
+    ///
+    /// Moves the files on disk and updates their paths in the index.
+    /// With several sources, the destination must be a directory.
+    Mv {
+        /// Files or directories to move, followed by the destination
+        #[arg(required = true, num_args = 2.., value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// Overwrite an existing destination file
+        #[arg(short, long)]
+        force: bool,
+
+        /// Only show what would be moved
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
     },
 
     /// List, create, or delete branches
@@ -224,22 +334,125 @@ enum Commands {
         #[arg(short, long)]
         output: Option<String>,
     },
+
+    /// Pack reachable objects into a packfile
+    ///
+    /// Writes a Git-compatible .pack and .idx pair with delta compression.
+    Repack {
+        /// Remove loose objects and old packs made redundant by the new pack
+        #[arg(short = 'd')]
+        delete_redundant: bool,
+    },
+
+    /// Clean up and optimize the object database
+    ///
+    /// Repacks all reachable objects and removes redundant loose files.
+    Gc {
+        /// Also delete unreachable objects
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Verify the connectivity and validity of objects
+    ///
+    /// Exits non-zero if any object is corrupt or missing.
+    Fsck {
+        /// Don't report dangling objects
+        #[arg(long)]
+        no_dangling: bool,
+    },
+
+    /// Remove unreachable loose objects
+    ///
+    /// Only objects older than the expiry (default two weeks) are removed.
+    Prune {
+        /// Only list the objects that would be removed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Prune objects older than this (e.g. "now", "2.weeks.ago", "never")
+        #[arg(long)]
+        expire: Option<String>,
+    },
+
+    /// Write and use the commit-graph file
+    ///
+    /// The commit-graph stores parents, trees, dates and generation numbers
+    /// of reachable commits so history walks don't parse every commit.
+    #[command(name = "commit-graph")]
+    CommitGraph {
+        #[command(subcommand)]
+        action: CommitGraphAction,
+    },
+
+    /// Manage large files stored outside the object database
+    ///
+    /// Paths marked `filter=lfs` in .ritattributes are committed as
+    /// pointers; their content lives in .rit/lfs/objects.
+    Lfs {
+        #[command(subcommand)]
+        action: LfsAction,
+    },
+}
+
+/// Subcommands of `rit commit-graph`
+#[derive(Subcommand)]
+enum CommitGraphAction {
+    /// Write a commit-graph for all commits reachable from refs and HEAD
+    Write,
+}
+
+/// Subcommands of `rit lfs`
+#[derive(Subcommand)]
+enum LfsAction {
+    /// List LFS files in the index ('*' = content stored, '-' = missing)
+    #[command(name = "ls-files")]
+    LsFiles,
+
+    /// Check that every reachable LFS object is stored and intact
+    Fsck,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Some(git_dir) = &cli.git_dir {
+        rit::Repository::set_git_dir(git_dir);
+    }
+
     match &cli.command {
-        Commands::Init => {
-            commands::init::run(None)?;
+        Commands::Init { object_format } => {
+            commands::init::run(None, object_format.as_deref())?;
         }
 
         Commands::HashObject { file, write } => {
             commands::hash_object::run(file, *write)?;
         }
 
-        Commands::CatFile { pretty_print, object_hash } => {
-            commands::cat_file::run(object_hash, *pretty_print)?;
+        Commands::CatFile { pretty_print, show_type, show_size, exists, batch, batch_check, object_hash } => {
+            use commands::cat_file::Mode;
+
+            if let Some(format) = batch.as_ref().or(batch_check.as_ref()) {
+                let format = Some(format.as_str()).filter(|f| !f.is_empty());
+                commands::cat_file::run_batch(format, batch.is_some())?;
+            } else if let Some(object_hash) = object_hash {
+                if *exists {
+                    if !commands::cat_file::run_exists(object_hash)? {
+                        std::process::exit(1);
+                    }
+                } else {
+                    let mode = if *show_type {
+                        Mode::Type
+                    } else if *show_size {
+                        Mode::Size
+                    } else if *pretty_print {
+                        Mode::Pretty
+                    } else {
+                        Mode::Raw
+                    };
+                    commands::cat_file::run(object_hash, mode)?;
+                }
+            }
         }
 
         Commands::WriteTree => {
@@ -258,12 +471,39 @@ fn main() -> Result<()> {
             commands::commit::run(message, *auto_add)?;
         }
 
+        Commands::Reset { args, paths, soft, mixed: _, hard } => {
+            let mode = if *soft {
+                commands::reset::ResetMode::Soft
+            } else if *hard {
+                commands::reset::ResetMode::Hard
+            } else {
+                commands::reset::ResetMode::Mixed
+            };
+            commands::reset::run(args.clone(), paths.clone(), mode)?;
+        }
+
         Commands::Log { oneline, graph } => {
             commands::log::run(*oneline, *graph)?;
         }
 
-        Commands::Add { paths } => {
-            commands::add::run(paths.clone())?;
+        Commands::Add { paths, patch, update, all } => {
+            if *patch {
+                commands::add_patch::run(paths.clone())?;
+            } else {
+                let options = commands::add::AddOptions { update: *update, all: *all };
+                commands::add::run(paths.clone(), options)?;
+            }
+        }
+
+        Commands::Rm { paths, cached, force, recursive } => {
+            let options = commands::rm::RmOptions { cached: *cached, force: *force, recursive: *recursive };
+            commands::rm::run(paths.clone(), options)?;
+        }
+
+        Commands::Mv { paths, force, dry_run } => {
+            let (destination, sources) = paths.split_last().expect("clap requires two paths");
+            let options = commands::mv::MvOptions { force: *force, dry_run: *dry_run };
+            commands::mv::run(sources.to_vec(), destination, options)?;
         }
 
         Commands::Branch { branch_name, delete, force } => {
@@ -289,6 +529,31 @@ fn main() -> Result<()> {
         Commands::Graph { format, output } => {
             commands::graph::run(format, output.clone())?;
         }
+
+        Commands::Repack { delete_redundant } => {
+            commands::repack::run(*delete_redundant)?;
+        }
+
+        Commands::Gc { prune } => {
+            commands::gc::run(*prune)?;
+        }
+
+        Commands::CommitGraph { action: CommitGraphAction::Write } => {
+            commands::commit_graph::run_write()?;
+        }
+
+        Commands::Lfs { action } => match action {
+            LfsAction::LsFiles => commands::lfs::run_ls_files()?,
+            LfsAction::Fsck => commands::lfs::run_fsck()?,
+        },
+
+        Commands::Fsck { no_dangling } => {
+            commands::fsck::run(!*no_dangling)?;
+        }
+
+        Commands::Prune { dry_run, expire } => {
+            commands::prune::run(expire.as_deref(), *dry_run)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,156 @@
+//! # Alternate Object Stores
+//!
+//! A repository can borrow objects from other object directories listed in
+//! `objects/info/alternates`, one path per line:
+//!
+//! ```text
+//! # Share history with the main checkout
+//! /srv/build/project/.rit/objects
+//! ../../shared/objects
+//! ```
+//!
+//! Relative paths are resolved against the objects directory that holds the
+//! `info/alternates` file. Alternates are read-only: objects are looked up
+//! in them after the repository's own loose objects and packs, but new
+//! objects are always written locally, and `fsck`, `prune` and `repack`
+//! never delete anything from them.
+//!
+//! An alternate may list alternates of its own. They are followed up to
+//! [`MAX_DEPTH`] levels deep; a directory that has already been seen
+//! (including the repository's own) is skipped, so cycles are harmless.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// How many levels of alternates-of-alternates are followed
+pub const MAX_DEPTH: usize = 5;
+
+/// The path of the alternates file inside an objects directory
+pub fn alternates_path(objects_dir: &Path) -> PathBuf {
+    objects_dir.join("info").join("alternates")
+}
+
+/// Parse the contents of an alternates file
+///
+/// Blank lines and `#` comments are skipped; relative entries are joined
+/// to `objects_dir`.
+pub fn parse(content: &str, objects_dir: &Path) -> Vec<PathBuf> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| objects_dir.join(line))
+        .collect()
+}
+
+/// Collect every alternate object directory reachable from `objects_dir`
+///
+/// The result is flattened in lookup order (breadth first) and never
+/// contains `objects_dir` itself or the same directory twice. Problems
+/// with individual entries are reported on stderr and the entry is
+/// skipped, as Git does, so a stale alternate never makes the repository
+/// unusable.
+pub fn resolve(objects_dir: &Path) -> Vec<PathBuf> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    seen.insert(canonical(objects_dir));
+
+    let mut resolved = Vec::new();
+    let mut level = vec![objects_dir.to_path_buf()];
+
+    for depth in 0..=MAX_DEPTH {
+        let mut next = Vec::new();
+
+        for dir in &level {
+            let path = alternates_path(dir);
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    eprintln!("warning: unable to read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            if depth == MAX_DEPTH {
+                eprintln!("error: {}: ignoring alternate object stores, nesting too deep", dir.display());
+                continue;
+            }
+
+            for alternate in parse(&content, dir) {
+                if !alternate.is_dir() {
+                    eprintln!(
+                        "error: object directory {} does not exist; check {}",
+                        alternate.display(),
+                        path.display()
+                    );
+                    continue;
+                }
+                if seen.insert(canonical(&alternate)) {
+                    resolved.push(alternate.clone());
+                    next.push(alternate);
+                }
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        level = next;
+    }
+
+    resolved
+}
+
+/// Normalize a directory for duplicate detection
+fn canonical(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn link(from: &Path, content: &str) {
+        fs::create_dir_all(from.join("info")).unwrap();
+        fs::write(alternates_path(from), content).unwrap();
+    }
+
+    #[test]
+    fn test_parse_skips_comments() {
+        let dirs = parse("# shared\n\n/abs/objects\n../other/objects\n", Path::new("/repo/objects"));
+        assert_eq!(dirs, vec![
+            PathBuf::from("/abs/objects"),
+            PathBuf::from("/repo/objects/../other/objects"),
+        ]);
+    }
+
+    #[test]
+    fn test_resolve_follows_chains_and_cycles() {
+        let temp = tempdir().unwrap();
+        let (a, b, c) = (temp.path().join("a"), temp.path().join("b"), temp.path().join("c"));
+        for dir in [&a, &b, &c] {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        // a -> b (relative) -> c -> a, plus a missing entry
+        link(&a, "../b\n/does/not/exist\n");
+        link(&b, &format!("{}\n", c.display()));
+        link(&c, &format!("{}\n", a.display()));
+
+        assert_eq!(resolve(&a), vec![a.join("../b"), c.clone()]);
+        assert_eq!(resolve(&c), vec![a.clone(), a.join("../b")]);
+    }
+
+    #[test]
+    fn test_resolve_limits_depth() {
+        let temp = tempdir().unwrap();
+        let dirs: Vec<PathBuf> = (0..MAX_DEPTH + 3).map(|i| temp.path().join(i.to_string())).collect();
+        for pair in dirs.windows(2) {
+            fs::create_dir_all(&pair[1]).unwrap();
+            link(&pair[0], &format!("{}\n", pair[1].display()));
+        }
+
+        assert_eq!(resolve(&dirs[0]).len(), MAX_DEPTH);
+    }
+}
@@ -16,9 +16,11 @@
 //! blob 13\0Hello, World!
 //! ```
 
+use std::path::Path;
 use anyhow::Result;
 use crate::Repository;
 use crate::commands::hash_object;
+use crate::hash::ObjectFormat;
 
 /// Represents a blob object
 #[derive(Debug, Clone)]
@@ -44,6 +46,9 @@ impl Blob {
 
     /// Create a blob from a file
     ///
+    /// This reads the whole file into memory. To hash or store a file
+    /// without loading it, use [`Blob::hash_file`] or [`Blob::store_file`].
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -58,6 +63,8 @@ impl Blob {
 
     /// Compute the SHA-1 hash of this blob
     ///
+    /// Use [`Blob::hash_with`] for repositories with another object format.
+    ///
     /// # Example
     ///
     /// ```
@@ -71,11 +78,16 @@ impl Blob {
         hash_object::hash_content("blob", &self.content)
     }
 
+    /// Compute the hash of this blob in the given object format
+    pub fn hash_with(&self, format: ObjectFormat) -> String {
+        format.hash_object("blob", &self.content)
+    }
+
     /// Store this blob in the repository's object database
     ///
     /// # Returns
     ///
-    /// The SHA-1 hash of the stored object
+    /// The hash of the stored object
     ///
     /// # Example
     ///
@@ -88,7 +100,21 @@ impl Blob {
     /// println!("Stored blob: {}", hash);
     /// ```
     pub fn store(&self, repo: &Repository) -> Result<String> {
-        hash_object::store_object(repo, "blob", &self.content)
+        repo.objects.write("blob", &self.content)
+    }
+
+    /// Compute the blob hash of a file, reading it in chunks
+    pub fn hash_file(format: ObjectFormat, path: &Path) -> Result<String> {
+        hash_object::hash_file(format, path)
+    }
+
+    /// Store a file as a blob, reading it in chunks
+    ///
+    /// # Returns
+    ///
+    /// The hash of the stored object
+    pub fn store_file(repo: &Repository, path: &Path) -> Result<String> {
+        hash_object::store_file(repo, path)
     }
 
     /// Get the size of the blob content in bytes
@@ -125,5 +151,27 @@ mod tests {
         let binary_blob = Blob::new(vec![0xFF, 0xFE, 0x00]);
         assert!(binary_blob.as_text().is_none());
     }
-}
 
+    #[test]
+    fn test_blob_store_in_memory() {
+        let repo = Repository::in_memory();
+        let blob = Blob::new(b"test content\n".to_vec());
+
+        let hash = blob.store(&repo).unwrap();
+        assert_eq!(hash, blob.hash());
+        assert_eq!(repo.objects.read(&hash).unwrap().content, blob.content);
+    }
+
+    #[test]
+    fn test_blob_store_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("file.txt");
+        std::fs::write(&path, b"test content\n").unwrap();
+
+        let repo = Repository::in_memory();
+        let hash = Blob::store_file(&repo, &path).unwrap();
+        assert_eq!(hash, "d670460b4b4aece5915caf5c68d12f560a9fe3e4");
+        assert_eq!(Blob::hash_file(ObjectFormat::Sha1, &path).unwrap(), hash);
+        assert_eq!(repo.objects.read(&hash).unwrap().content, b"test content\n");
+    }
+}
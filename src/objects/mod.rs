@@ -39,12 +39,36 @@
 //!
 //! <commit message>
 //! ```
+//!
+//! ### Tag
+//! An annotated tag names another object and carries a message.
+//!
+//! ```text
+//! tag <size>\0
+//! object <hash>
+//! type <object-type>
+//! tag <tag-name>
+//! tagger <name> <email> <timestamp>
+//!
+//! <tag message>
+//! ```
+//!
+//! ## Storage
+//!
+//! Objects are read and written through the [`ObjectStore`] trait, with an
+//! on-disk backend ([`LooseObjectStore`]) and an in-memory one
+//! ([`MemoryObjectStore`]).
 
 pub mod blob;
 pub mod tree;
 pub mod commit;
+pub mod tag;
+pub mod store;
+pub mod alternates;
 
 pub use blob::Blob;
 pub use tree::{Tree, TreeEntry};
 pub use commit::Commit;
+pub use tag::Tag;
+pub use store::{GitObject, ObjectStore, LooseObjectStore, MemoryObjectStore};
 
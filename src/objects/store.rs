@@ -0,0 +1,576 @@
+//! # Object Stores
+//!
+//! Where objects live. Every read and write of a blob, tree, commit or tag
+//! goes through the [`ObjectStore`] trait, so the same code can run against
+//! a `.rit/objects` directory on disk or a plain in-memory map.
+//!
+//! ## Backends
+//!
+//! - [`LooseObjectStore`]: zlib-compressed files at `objects/ab/cdef...`,
+//!   falling back to packfiles in `objects/pack/` and then to any
+//!   read-only alternates (see [`super::alternates`]) for reads
+//! - [`MemoryObjectStore`]: a `HashMap` for tests and ephemeral repositories
+//!
+//! ## Example
+//!
+//! ```
+//! use rit::objects::{ObjectStore, MemoryObjectStore};
+//!
+//! let store = MemoryObjectStore::new();
+//! let hash = store.write("blob", b"Hello, World!").unwrap();
+//! assert!(store.exists(&hash));
+//! assert_eq!(store.read(&hash).unwrap().content, b"Hello, World!");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use anyhow::{Context, Result};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::commands::hash_object;
+use crate::atomic::{self, AtomicFile};
+use crate::hash::ObjectFormat;
+use crate::pack;
+use super::alternates;
+
+/// Represents a parsed git object
+#[derive(Debug)]
+pub struct GitObject {
+    /// The type of object ("blob", "tree", "commit", "tag")
+    pub object_type: String,
+    /// The size of the content in bytes
+    pub size: usize,
+    /// The raw content (after header)
+    pub content: Vec<u8>,
+}
+
+/// A place objects can be read from and written to
+///
+/// Hashes passed to these methods are always full-length; prefix expansion
+/// is done by [`ObjectStore::find_prefix`] and
+/// [`crate::commands::cat_file::resolve_object`].
+pub trait ObjectStore: Debug + Send + Sync {
+    /// Read an object by its full hash
+    fn read(&self, hash: &str) -> Result<GitObject>;
+
+    /// Store an object and return its hash
+    ///
+    /// Objects are immutable, so writing an existing object is a no-op.
+    fn write(&self, object_type: &str, content: &[u8]) -> Result<String>;
+
+    /// Store an object whose content is read from a stream
+    ///
+    /// `size` must be the exact number of bytes the reader yields. The
+    /// default implementation buffers the content and calls
+    /// [`ObjectStore::write`]; backends that can do better override it.
+    fn write_stream(&self, object_type: &str, size: u64, reader: &mut dyn Read) -> Result<String> {
+        let mut content = Vec::with_capacity(size as usize);
+        reader.read_to_end(&mut content)
+            .context("Failed to read object content")?;
+        if content.len() as u64 != size {
+            anyhow::bail!("Size mismatch: expected {} bytes but read {}", size, content.len());
+        }
+        self.write(object_type, &content)
+    }
+
+    /// Check if an object exists
+    fn exists(&self, hash: &str) -> bool;
+
+    /// The hash algorithm this store names objects with
+    fn object_format(&self) -> ObjectFormat;
+
+    /// Iterate over the hashes of all objects in the store
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = String> + '_>>;
+
+    /// Find all object hashes starting with a hex prefix
+    fn find_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut matches: Vec<String> = self.iter()?
+            .filter(|hash| hash.starts_with(prefix))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        Ok(matches)
+    }
+
+    /// Forget anything cached about the store's files
+    ///
+    /// Call this after packs were written or deleted without going through
+    /// the store (as `repack` does), so later lookups see them.
+    fn refresh(&self) {}
+}
+
+/// Object store backed by a `.rit/objects` directory
+#[derive(Debug)]
+pub struct LooseObjectStore {
+    /// The objects directory
+    objects_dir: PathBuf,
+    /// How objects are named
+    format: ObjectFormat,
+    /// Read-only stores from `info/alternates`, flattened in lookup order
+    alternates: Vec<LooseObjectStore>,
+    /// The parsed packs in `pack/`, read on first use
+    packs: RwLock<Option<Arc<Vec<pack::Pack>>>>,
+}
+
+impl LooseObjectStore {
+    /// Create a store for an objects directory of a SHA-1 repository
+    pub fn new(objects_dir: PathBuf) -> Self {
+        Self::with_format(objects_dir, ObjectFormat::Sha1)
+    }
+
+    /// Create a store for an objects directory using the given object format
+    ///
+    /// The directory's `info/alternates` file, if any, is read (recursively)
+    /// here.
+    pub fn with_format(objects_dir: PathBuf, format: ObjectFormat) -> Self {
+        let alternates = alternates::resolve(&objects_dir)
+            .into_iter()
+            .map(|dir| Self::local(dir, format))
+            .collect();
+        Self { objects_dir, format, alternates, packs: RwLock::new(None) }
+    }
+
+    /// Create a store for just this directory, ignoring its alternates
+    fn local(objects_dir: PathBuf, format: ObjectFormat) -> Self {
+        Self { objects_dir, format, alternates: Vec::new(), packs: RwLock::new(None) }
+    }
+
+    /// The alternate stores objects are also looked up in
+    ///
+    /// These are read-only: nothing should be written to or deleted from
+    /// them.
+    pub fn alternates(&self) -> &[LooseObjectStore] {
+        &self.alternates
+    }
+
+    /// The hashes of every object available from alternates
+    pub fn alternate_hashes(&self) -> Result<HashSet<String>> {
+        let mut hashes = HashSet::new();
+        for alternate in &self.alternates {
+            hashes.extend(alternate.iter()?);
+        }
+        Ok(hashes)
+    }
+
+    /// Check if an object is in this directory itself (loose or packed)
+    ///
+    /// Unlike [`ObjectStore::exists`], alternates are not consulted.
+    pub fn contains_local(&self, hash: &str) -> bool {
+        if self.object_path(hash).exists() {
+            return true;
+        }
+        self.packs()
+            .map(|packs| packs.iter().any(|p| p.contains(hash)))
+            .unwrap_or(false)
+    }
+
+    /// Read an object from this directory's loose files or packs only
+    fn read_local(&self, hash: &str) -> Result<Option<GitObject>> {
+        if self.object_path(hash).exists() {
+            return self.read_loose(hash).map(Some);
+        }
+
+        // Not loose - it may have been packed
+        for pack in self.packs()?.iter() {
+            if let Some(object) = pack.read_object(self, hash)? {
+                return Ok(Some(object));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The packs in this store's `pack/` directory
+    ///
+    /// The `.idx` files are parsed once and kept until
+    /// [`ObjectStore::refresh`] is called.
+    pub fn packs(&self) -> Result<Arc<Vec<pack::Pack>>> {
+        if let Some(packs) = self.packs.read().expect("pack list lock poisoned").as_ref() {
+            return Ok(Arc::clone(packs));
+        }
+        let packs = Arc::new(pack::find_packs(&self.objects_dir, self.format)?);
+        *self.packs.write().expect("pack list lock poisoned") = Some(Arc::clone(&packs));
+        Ok(packs)
+    }
+
+    /// Get the objects directory
+    pub fn objects_dir(&self) -> &Path {
+        &self.objects_dir
+    }
+
+    /// Get the path where a loose object is (or would be) stored
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        self.objects_dir.join(&hash[..2]).join(&hash[2..])
+    }
+
+    /// List the hashes of all loose objects (excluding packed ones)
+    pub fn list_loose(&self) -> Result<Vec<String>> {
+        let mut hashes = Vec::new();
+
+        if !self.objects_dir.is_dir() {
+            return Ok(hashes);
+        }
+
+        for entry in std::fs::read_dir(&self.objects_dir).context("Failed to read objects directory")? {
+            let entry = entry?;
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+
+            // Fan-out directories are exactly two hex characters
+            if dir_name.len() != 2 || !dir_name.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+
+            for object in std::fs::read_dir(entry.path())? {
+                let rest = object?.file_name().to_string_lossy().to_string();
+                if rest.chars().all(|c| c.is_ascii_hexdigit()) {
+                    hashes.push(format!("{}{}", dir_name, rest));
+                }
+            }
+        }
+
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// Read and parse a loose object file, ignoring packs
+    ///
+    /// The hash is only used to locate the file; it is not verified against
+    /// the content.
+    pub fn read_loose(&self, hash: &str) -> Result<GitObject> {
+        // Read and decompress
+        let compressed = std::fs::read(self.object_path(hash))
+            .context("Failed to read object file")?;
+
+        let mut decoder = ZlibDecoder::new(&compressed[..]);
+        let mut decompressed = Vec::new();
+        decoder.read_to_end(&mut decompressed)
+            .context("Failed to decompress object")?;
+
+        // Parse header
+        let null_pos = decompressed.iter()
+            .position(|&b| b == 0)
+            .context("Invalid object format: no null byte found")?;
+
+        let header = String::from_utf8_lossy(&decompressed[..null_pos]);
+        let mut parts = header.split(' ');
+
+        let object_type = parts.next()
+            .context("Invalid object format: no type")?
+            .to_string();
+
+        let size: usize = parts.next()
+            .context("Invalid object format: no size")?
+            .parse()
+            .context("Invalid object format: size not a number")?;
+
+        let content = decompressed[null_pos + 1..].to_vec();
+
+        // Verify size
+        if content.len() != size {
+            anyhow::bail!("Size mismatch: header says {} but content is {} bytes",
+                size, content.len());
+        }
+
+        Ok(GitObject {
+            object_type,
+            size,
+            content,
+        })
+    }
+}
+
+impl ObjectStore for LooseObjectStore {
+    fn read(&self, hash: &str) -> Result<GitObject> {
+        if let Some(object) = self.read_local(hash)? {
+            return Ok(object);
+        }
+
+        for alternate in &self.alternates {
+            if let Some(object) = alternate.read_local(hash)? {
+                return Ok(object);
+            }
+        }
+
+        anyhow::bail!("object not found: {}", hash);
+    }
+
+    fn write(&self, object_type: &str, content: &[u8]) -> Result<String> {
+        let hash = self.format.hash_object(object_type, content);
+
+        // Object file path (first 2 chars / remaining chars)
+        let object_path = self.object_path(&hash);
+
+        // Don't overwrite if already exists (objects are immutable)
+        if !object_path.exists() {
+            // Compress with zlib
+            let header = format!("{} {}\0", object_type, content.len());
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(header.as_bytes())?;
+            encoder.write_all(content)?;
+            let compressed = encoder.finish()?;
+
+            // Write to a temporary file and rename it into place
+            atomic::write(&object_path, &self.objects_dir, &compressed)
+                .context("Failed to write object file")?;
+        }
+
+        Ok(hash)
+    }
+
+    /// Compress and hash in a single pass into a temporary file
+    ///
+    /// The hash is only known once the whole stream has been read, so the
+    /// object is written to a temporary file in `objects/` and renamed into
+    /// place afterwards (or discarded if the object already exists).
+    fn write_stream(&self, object_type: &str, size: u64, reader: &mut dyn Read) -> Result<String> {
+        let mut encoder = ZlibEncoder::new(AtomicFile::new(&self.objects_dir)?, Compression::default());
+        let hash = hash_object::stream_object(self.format, object_type, size, reader, &mut encoder)?;
+        let file = encoder.finish()?;
+
+        let object_path = self.object_path(&hash);
+        if !object_path.exists() {
+            file.persist(&object_path)
+                .context("Failed to write object file")?;
+        }
+
+        Ok(hash)
+    }
+
+    fn exists(&self, hash: &str) -> bool {
+        self.contains_local(hash) || self.alternates.iter().any(|a| a.contains_local(hash))
+    }
+
+    fn object_format(&self) -> ObjectFormat {
+        self.format
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = String> + '_>> {
+        let mut hashes = self.list_loose()?;
+        for pack in self.packs()?.iter() {
+            hashes.extend(pack.index.hashes());
+        }
+        for alternate in &self.alternates {
+            hashes.extend(alternate.iter()?);
+        }
+        hashes.sort();
+        hashes.dedup();
+        Ok(Box::new(hashes.into_iter()))
+    }
+
+    fn find_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut matches = Vec::new();
+
+        // Loose objects: only the matching fan-out directory needs scanning
+        let fanout_dir = self.objects_dir.join(&prefix[..2]);
+        if fanout_dir.is_dir() {
+            for entry in std::fs::read_dir(&fanout_dir).context("Failed to read objects directory")? {
+                let rest = entry?.file_name().to_string_lossy().to_string();
+                let hash = format!("{}{}", &prefix[..2], rest);
+                if hash.starts_with(prefix) && rest.chars().all(|c| c.is_ascii_hexdigit()) {
+                    matches.push(hash);
+                }
+            }
+        }
+
+        for pack in self.packs()?.iter() {
+            matches.extend(pack.index.find_prefix(prefix));
+        }
+
+        for alternate in &self.alternates {
+            matches.extend(alternate.find_prefix(prefix)?);
+        }
+
+        matches.sort();
+        matches.dedup();
+        Ok(matches)
+    }
+
+    fn refresh(&self) {
+        *self.packs.write().expect("pack list lock poisoned") = None;
+        for alternate in &self.alternates {
+            alternate.refresh();
+        }
+    }
+}
+
+/// Object store that keeps everything in memory
+///
+/// Nothing touches the filesystem, which makes it suitable for unit tests
+/// and throwaway repositories.
+#[derive(Debug, Default)]
+pub struct MemoryObjectStore {
+    /// Map of hash to (type, content)
+    objects: RwLock<HashMap<String, (String, Vec<u8>)>>,
+    /// How objects are named
+    format: ObjectFormat,
+}
+
+impl MemoryObjectStore {
+    /// Create an empty in-memory store using SHA-1
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty in-memory store using the given object format
+    pub fn with_format(format: ObjectFormat) -> Self {
+        Self { format, ..Self::default() }
+    }
+}
+
+impl ObjectStore for MemoryObjectStore {
+    fn read(&self, hash: &str) -> Result<GitObject> {
+        let objects = self.objects.read().expect("object store lock poisoned");
+        let (object_type, content) = objects.get(hash)
+            .ok_or_else(|| anyhow::anyhow!("object not found: {}", hash))?;
+
+        Ok(GitObject {
+            object_type: object_type.clone(),
+            size: content.len(),
+            content: content.clone(),
+        })
+    }
+
+    fn write(&self, object_type: &str, content: &[u8]) -> Result<String> {
+        let hash = self.format.hash_object(object_type, content);
+        self.objects.write()
+            .expect("object store lock poisoned")
+            .entry(hash.clone())
+            .or_insert_with(|| (object_type.to_string(), content.to_vec()));
+        Ok(hash)
+    }
+
+    fn exists(&self, hash: &str) -> bool {
+        self.objects.read()
+            .expect("object store lock poisoned")
+            .contains_key(hash)
+    }
+
+    fn object_format(&self) -> ObjectFormat {
+        self.format
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = String> + '_>> {
+        let hashes: Vec<String> = self.objects.read()
+            .expect("object store lock poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        Ok(Box::new(hashes.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Exercise the common contract of every backend
+    fn check_store(store: &dyn ObjectStore) {
+        let hash = store.write("blob", b"test content\n").unwrap();
+        assert_eq!(hash, "d670460b4b4aece5915caf5c68d12f560a9fe3e4");
+        assert!(store.exists(&hash));
+        assert!(!store.exists(&"0".repeat(40)));
+
+        // Writing again is a no-op
+        assert_eq!(store.write("blob", b"test content\n").unwrap(), hash);
+
+        let object = store.read(&hash).unwrap();
+        assert_eq!(object.object_type, "blob");
+        assert_eq!(object.content, b"test content\n");
+
+        store.write("blob", b"other").unwrap();
+        assert_eq!(store.iter().unwrap().count(), 2);
+        assert_eq!(store.find_prefix("d670").unwrap(), vec![hash]);
+        assert!(store.read(&"0".repeat(40)).is_err());
+    }
+
+    #[test]
+    fn test_loose_store() {
+        let temp = tempdir().unwrap();
+        let store = LooseObjectStore::new(temp.path().join("objects"));
+        check_store(&store);
+        assert_eq!(store.list_loose().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&MemoryObjectStore::new());
+    }
+
+    #[test]
+    fn test_sha256_store() {
+        let temp = tempdir().unwrap();
+        let store = LooseObjectStore::with_format(temp.path().join("objects"), ObjectFormat::Sha256);
+
+        let hash = store.write("blob", b"test content\n").unwrap();
+        assert_eq!(hash, "13b7e821533d3fe3728a3c4560606a65aab99f4390b9df0714f9075c0ef4c2d6");
+        assert_eq!(store.read(&hash).unwrap().content, b"test content\n");
+
+        let content = b"test content\n";
+        let streamed = store.write_stream("blob", content.len() as u64, &mut &content[..]).unwrap();
+        assert_eq!(streamed, hash);
+    }
+
+    #[test]
+    fn test_write_stream_matches_write() {
+        // Several chunks plus a partial one, compressible and not
+        let content: Vec<u8> = (0..(hash_object::STREAM_CHUNK_SIZE * 3 + 123))
+            .map(|i| if i % 7 == 0 { (i * 31 % 251) as u8 } else { b'a' })
+            .collect();
+
+        let temp = tempdir().unwrap();
+        let buffered = LooseObjectStore::new(temp.path().join("buffered"));
+        let streamed = LooseObjectStore::new(temp.path().join("streamed"));
+
+        let hash = buffered.write("blob", &content).unwrap();
+        let streamed_hash = streamed
+            .write_stream("blob", content.len() as u64, &mut &content[..])
+            .unwrap();
+        assert_eq!(streamed_hash, hash);
+
+        // Loose files are byte-identical and no temporary file is left behind
+        assert_eq!(
+            std::fs::read(buffered.object_path(&hash)).unwrap(),
+            std::fs::read(streamed.object_path(&hash)).unwrap()
+        );
+        assert_eq!(std::fs::read_dir(streamed.objects_dir()).unwrap().count(), 1);
+
+        // A stream that doesn't match the declared size is rejected
+        assert!(streamed.write_stream("blob", 10, &mut &b"short"[..]).is_err());
+        assert!(streamed.write_stream("blob", 2, &mut &b"too long"[..]).is_err());
+        assert_eq!(std::fs::read_dir(streamed.objects_dir()).unwrap().count(), 1);
+
+        let memory = MemoryObjectStore::new();
+        assert_eq!(memory.write_stream("blob", content.len() as u64, &mut &content[..]).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_alternates_are_searched_read_only() {
+        let temp = tempdir().unwrap();
+        let shared = LooseObjectStore::new(temp.path().join("shared"));
+        let shared_hash = shared.write("blob", b"shared\n").unwrap();
+
+        let local_dir = temp.path().join("local");
+        std::fs::create_dir_all(local_dir.join("info")).unwrap();
+        std::fs::write(alternates::alternates_path(&local_dir), "../shared\n").unwrap();
+
+        let store = LooseObjectStore::new(local_dir);
+        assert_eq!(store.alternates().len(), 1);
+        assert!(store.exists(&shared_hash));
+        assert!(!store.contains_local(&shared_hash));
+        assert_eq!(store.read(&shared_hash).unwrap().content, b"shared\n");
+        assert_eq!(store.find_prefix(&shared_hash[..6]).unwrap(), vec![shared_hash.clone()]);
+
+        // New objects go to the local directory only
+        let local_hash = store.write("blob", b"local\n").unwrap();
+        assert!(store.contains_local(&local_hash));
+        assert!(!shared.exists(&local_hash));
+        assert_eq!(store.iter().unwrap().count(), 2);
+        assert_eq!(store.list_loose().unwrap(), vec![local_hash]);
+    }
+}
@@ -0,0 +1,146 @@
+//! # Tag Object
+//!
+//! An annotated tag points at another object (usually a commit) and adds a
+//! name, a tagger and a message.
+//!
+//! ## Format
+//!
+//! ```text
+//! object <sha1>
+//! type <object-type>
+//! tag <tag-name>
+//! tagger <name> <email> <timestamp> <timezone>
+//!
+//! <tag message>
+//! ```
+//!
+//! ## Example
+//!
+//! ```text
+//! object a1b2c3d4e5f6789...
+//! type commit
+//! tag v1.0.0
+//! tagger John Doe <john@example.com> 1234567890 +0000
+//!
+//! Release 1.0.0
+//! ```
+
+use anyhow::Result;
+
+use super::commit::Author;
+
+/// Represents an annotated tag object
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// Hash of the tagged object
+    pub object: String,
+    /// Type of the tagged object ("commit", "tree", "blob", "tag")
+    pub object_type: String,
+    /// Name of the tag
+    pub name: String,
+    /// Who created the tag (very old tags have none)
+    pub tagger: Option<Author>,
+    /// Tag message
+    pub message: String,
+}
+
+impl Tag {
+    /// Create a new tag pointing at a commit
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rit::objects::{commit::Author, Tag};
+    ///
+    /// let tagger = Author::new("John Doe", "john@example.com");
+    /// let tag = Tag::new("abc123", "v1.0", tagger, "Release 1.0");
+    /// assert_eq!(tag.object_type, "commit");
+    /// ```
+    pub fn new(object: &str, name: &str, tagger: Author, message: &str) -> Self {
+        Self {
+            object: object.to_string(),
+            object_type: "commit".to_string(),
+            name: name.to_string(),
+            tagger: Some(tagger),
+            message: message.to_string(),
+        }
+    }
+
+    /// Serialize to Git format
+    pub fn serialize(&self) -> String {
+        let mut lines = Vec::new();
+
+        lines.push(format!("object {}", self.object));
+        lines.push(format!("type {}", self.object_type));
+        lines.push(format!("tag {}", self.name));
+        if let Some(tagger) = &self.tagger {
+            lines.push(format!("tagger {}", tagger.serialize()));
+        }
+        lines.push(String::new()); // Empty line before message
+        lines.push(self.message.clone());
+
+        lines.join("\n")
+    }
+
+    /// Parse a tag from raw content
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut object = None;
+        let mut object_type = None;
+        let mut name = None;
+        let mut tagger = None;
+
+        let mut lines = content.lines();
+
+        // Parse headers
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break; // End of headers
+            }
+
+            if let Some(hash) = line.strip_prefix("object ") {
+                object = Some(hash.to_string());
+            } else if let Some(kind) = line.strip_prefix("type ") {
+                object_type = Some(kind.to_string());
+            } else if let Some(tag) = line.strip_prefix("tag ") {
+                name = Some(tag.to_string());
+            } else if let Some(rest) = line.strip_prefix("tagger ") {
+                tagger = Some(Author::parse(rest)?);
+            }
+        }
+
+        // Rest is the message
+        let message: String = lines.collect::<Vec<_>>().join("\n");
+
+        Ok(Self {
+            object: object.ok_or_else(|| anyhow::anyhow!("Missing object"))?,
+            object_type: object_type.ok_or_else(|| anyhow::anyhow!("Missing type"))?,
+            name: name.ok_or_else(|| anyhow::anyhow!("Missing tag name"))?,
+            tagger,
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_roundtrip() {
+        let tagger = Author::with_timestamp("Test", "test@test.com", 1000, "+0000");
+        let tag = Tag::new(&"a".repeat(40), "v1.0", tagger, "Release");
+
+        let parsed = Tag::parse(&tag.serialize()).unwrap();
+
+        assert_eq!(parsed.object, "a".repeat(40));
+        assert_eq!(parsed.object_type, "commit");
+        assert_eq!(parsed.name, "v1.0");
+        assert_eq!(parsed.tagger.unwrap().timestamp, 1000);
+        assert_eq!(parsed.message, "Release");
+    }
+
+    #[test]
+    fn test_tag_missing_header() {
+        assert!(Tag::parse("object abc\ntag v1.0\n\nmessage").is_err());
+    }
+}
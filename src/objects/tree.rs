@@ -13,6 +13,8 @@
 //! ```
 //!
 //! Note: The SHA-1 is stored as raw bytes (20 bytes), not hex (40 chars).
+//! In SHA-256 repositories each hash is 32 raw bytes instead, so parsing
+//! needs to know the repository's [`ObjectFormat`].
 //!
 //! ## Modes
 //!
@@ -24,6 +26,8 @@
 
 use anyhow::Result;
 
+use crate::hash::ObjectFormat;
+
 /// File mode for a regular file
 pub const MODE_FILE: &str = "100644";
 /// File mode for an executable file
@@ -40,7 +44,7 @@ pub struct TreeEntry {
     pub mode: String,
     /// Filename (just the name, not full path)
     pub name: String,
-    /// SHA-1 hash of the blob or subtree (40 hex chars)
+    /// Hash of the blob or subtree (40 hex chars, or 64 for SHA-256)
     pub hash: String,
 }
 
@@ -77,6 +81,19 @@ impl TreeEntry {
         self.mode == MODE_DIR || self.mode == "040000"
     }
 
+    /// The type of object this entry points to, judging by its mode
+    ///
+    /// Gitlinks (mode `160000`, used for submodules) point to commits.
+    pub fn object_type(&self) -> &'static str {
+        if self.is_tree() {
+            "tree"
+        } else if self.mode == "160000" {
+            "commit"
+        } else {
+            "blob"
+        }
+    }
+
     /// Check if this entry is a regular file (blob)
     pub fn is_blob(&self) -> bool {
         self.mode == MODE_FILE || self.mode == MODE_EXEC
@@ -84,7 +101,7 @@ impl TreeEntry {
 
     /// Serialize this entry to binary format
     ///
-    /// Format: `<mode> <name>\0<20-byte-hash>`
+    /// Format: `<mode> <name>\0<raw hash>`
     pub fn serialize(&self) -> Result<Vec<u8>> {
         let mut data = Vec::new();
 
@@ -94,7 +111,7 @@ impl TreeEntry {
         data.extend_from_slice(self.name.as_bytes());
         data.push(0); // null byte
 
-        // 20-byte hash (convert from hex)
+        // Raw hash bytes (convert from hex)
         let hash_bytes = hex::decode(&self.hash)?;
         data.extend_from_slice(&hash_bytes);
 
@@ -150,7 +167,10 @@ impl Tree {
     }
 
     /// Parse a tree from raw content bytes
-    pub fn parse(content: &[u8]) -> Result<Self> {
+    ///
+    /// `format` decides how many raw bytes each entry's hash takes.
+    pub fn parse(content: &[u8], format: ObjectFormat) -> Result<Self> {
+        let hash_len = format.raw_len();
         let mut entries = Vec::new();
         let mut pos = 0;
 
@@ -171,12 +191,12 @@ impl Tree {
             let name = String::from_utf8_lossy(&content[pos..pos + null_pos]).to_string();
             pos += null_pos + 1;
 
-            // Read 20-byte hash
-            if pos + 20 > content.len() {
+            // Read the raw hash
+            if pos + hash_len > content.len() {
                 anyhow::bail!("Truncated tree entry");
             }
-            let hash = hex::encode(&content[pos..pos + 20]);
-            pos += 20;
+            let hash = hex::encode(&content[pos..pos + hash_len]);
+            pos += hash_len;
 
             entries.push(TreeEntry { mode, name, hash });
         }
@@ -219,5 +239,19 @@ mod tests {
         assert_eq!(tree.entries[1].name, "m");
         assert_eq!(tree.entries[2].name, "z.txt");
     }
+
+    #[test]
+    fn test_tree_roundtrip_sha256() {
+        let mut tree = Tree::new();
+        tree.add_entry(TreeEntry::file("a.txt".to_string(), "a".repeat(64)));
+        tree.add_entry(TreeEntry::directory("src".to_string(), "b".repeat(64)));
+
+        let data = tree.serialize().unwrap();
+        let parsed = Tree::parse(&data, ObjectFormat::Sha256).unwrap();
+        assert_eq!(parsed.entries, tree.entries);
+
+        // Read with the wrong width, the data doesn't line up
+        assert!(Tree::parse(&data, ObjectFormat::Sha1).is_err());
+    }
 }
 
@@ -0,0 +1,972 @@
+//! # Packfiles
+//!
+//! Reading objects stored in Git packfiles (`objects/pack/*.pack`).
+//!
+//! Loose objects are convenient but wasteful: every object is its own zlib
+//! file. `git gc` bundles objects into a single `.pack` file, with a
+//! companion `.idx` file that makes lookups by hash fast.
+//!
+//! ## Index Format (version 2)
+//!
+//! ```text
+//! \377tOc              # magic
+//! 00000002             # version
+//! fanout[256]          # 4-byte counts: objects whose first byte <= i
+//! hashes[N]            # 20-byte object IDs, sorted (32 bytes for SHA-256)
+//! crc32[N]             # 4-byte CRC of each packed entry
+//! offsets[N]           # 4-byte pack offsets (MSB set = index into large table)
+//! large_offsets[M]     # 8-byte offsets for packs over 2GB
+//! <pack checksum> <index checksum>
+//! ```
+//!
+//! Checksums use the repository's hash algorithm too (see
+//! [`crate::hash::ObjectFormat`]).
+//!
+//! ## Pack Format
+//!
+//! ```text
+//! PACK 00000002 <object count>
+//! <entry> <entry> ...
+//! <checksum>
+//! ```
+//!
+//! Each entry starts with a variable-length header holding the type (3 bits)
+//! and the inflated size, followed by zlib-compressed data. Delta entries
+//! store instructions to rebuild an object from a base:
+//!
+//! - `OFS_DELTA` (6): base is found at a relative offset earlier in the pack
+//! - `REF_DELTA` (7): base is named by its object ID
+//!
+//! ## Writing Packs
+//!
+//! [`write_pack`] produces a pack + idx pair. Blobs are sorted by path name
+//! and size so that versions of the same file end up next to each other,
+//! then each blob is delta-compressed against the best of the previous few
+//! blobs (a sliding window, like Git's `--window`).
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use flate2::bufread::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+
+use crate::atomic;
+use crate::hash::ObjectFormat;
+use crate::objects::store::{GitObject, ObjectStore};
+
+/// Magic bytes at the start of a version 2 index file
+const IDX_MAGIC: [u8; 4] = [0xff, b't', b'O', b'c'];
+
+/// Packed object type: commit
+pub const OBJ_COMMIT: u8 = 1;
+/// Packed object type: tree
+pub const OBJ_TREE: u8 = 2;
+/// Packed object type: blob
+pub const OBJ_BLOB: u8 = 3;
+/// Packed object type: annotated tag
+pub const OBJ_TAG: u8 = 4;
+/// Packed object type: delta against a base at a relative offset
+pub const OBJ_OFS_DELTA: u8 = 6;
+/// Packed object type: delta against a base named by hash
+pub const OBJ_REF_DELTA: u8 = 7;
+
+/// Maximum delta chain depth we are willing to follow
+const MAX_DELTA_DEPTH: usize = 1000;
+
+/// Number of previous blobs to try as delta bases
+const DELTA_WINDOW: usize = 10;
+
+/// Maximum delta chain depth when writing packs
+const MAX_WRITE_DEPTH: usize = 50;
+
+/// Block size used to find matches between a delta base and target
+const DELTA_BLOCK: usize = 16;
+
+/// Map a packed object type number to its name
+pub fn type_name(type_num: u8) -> Result<&'static str> {
+    match type_num {
+        OBJ_COMMIT => Ok("commit"),
+        OBJ_TREE => Ok("tree"),
+        OBJ_BLOB => Ok("blob"),
+        OBJ_TAG => Ok("tag"),
+        _ => anyhow::bail!("Invalid packed object type: {}", type_num),
+    }
+}
+
+/// Map an object type name to its packed type number
+pub fn type_number(object_type: &str) -> Result<u8> {
+    match object_type {
+        "commit" => Ok(OBJ_COMMIT),
+        "tree" => Ok(OBJ_TREE),
+        "blob" => Ok(OBJ_BLOB),
+        "tag" => Ok(OBJ_TAG),
+        _ => anyhow::bail!("Unknown object type: {}", object_type),
+    }
+}
+
+/// A parsed `.idx` file
+#[derive(Debug, Clone)]
+pub struct PackIndex {
+    /// Cumulative object counts by first hash byte
+    fanout: Vec<u32>,
+    /// Sorted raw object IDs
+    hashes: Vec<Vec<u8>>,
+    /// Pack offset of each object (same order as `hashes`)
+    offsets: Vec<u64>,
+    /// The object format the IDs are in
+    format: ObjectFormat,
+}
+
+impl PackIndex {
+    /// Parse a version 2 index file whose object IDs are in `format`
+    pub fn parse(data: &[u8], format: ObjectFormat) -> Result<Self> {
+        let hash_len = format.raw_len();
+
+        if data.len() < 8 + 256 * 4 || data[..4] != IDX_MAGIC {
+            anyhow::bail!("Unsupported pack index: only version 2 is supported");
+        }
+
+        let version = read_u32(data, 4);
+        if version != 2 {
+            anyhow::bail!("Unsupported pack index version: {}", version);
+        }
+
+        let mut fanout = Vec::with_capacity(256);
+        for i in 0..256 {
+            fanout.push(read_u32(data, 8 + i * 4));
+        }
+        // Lookups slice the hash table between neighbouring entries
+        if fanout.windows(2).any(|w| w[0] > w[1]) {
+            anyhow::bail!("Corrupt pack index: fanout is not sorted");
+        }
+        let count = fanout[255] as usize;
+
+        let hashes_start = 8 + 256 * 4;
+        let crc_start = hashes_start + count * hash_len;
+        let offsets_start = crc_start + count * 4;
+        let large_start = offsets_start + count * 4;
+
+        if data.len() < large_start {
+            anyhow::bail!("Truncated pack index");
+        }
+
+        let mut hashes = Vec::with_capacity(count);
+        for i in 0..count {
+            let start = hashes_start + i * hash_len;
+            hashes.push(data[start..start + hash_len].to_vec());
+        }
+
+        let mut offsets = Vec::with_capacity(count);
+        for i in 0..count {
+            let offset = read_u32(data, offsets_start + i * 4);
+            if offset & 0x8000_0000 != 0 {
+                // Offset lives in the 8-byte large offset table
+                let large_index = (offset & 0x7fff_ffff) as usize;
+                let pos = large_start + large_index * 8;
+                if data.len() < pos + 8 {
+                    anyhow::bail!("Truncated pack index: large offset out of range");
+                }
+                offsets.push(u64::from_be_bytes(data[pos..pos + 8].try_into()?));
+            } else {
+                offsets.push(offset as u64);
+            }
+        }
+
+        Ok(Self { fanout, hashes, offsets, format })
+    }
+
+    /// Number of objects in the pack
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Check if the index has no objects
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Find the pack offset of an object by its hex hash
+    pub fn find_offset(&self, hash: &str) -> Option<u64> {
+        let raw = hex::decode(hash).ok()?;
+        if raw.len() != self.format.raw_len() {
+            return None;
+        }
+
+        // The fanout table narrows the search to hashes sharing the first byte
+        let first = raw[0] as usize;
+        let start = if first == 0 { 0 } else { self.fanout[first - 1] as usize };
+        let end = self.fanout[first] as usize;
+
+        self.hashes[start..end]
+            .binary_search_by(|h| h[..].cmp(&raw[..]))
+            .ok()
+            .map(|i| self.offsets[start + i])
+    }
+
+    /// Find all object hashes starting with a hex prefix
+    pub fn find_prefix(&self, prefix: &str) -> Vec<String> {
+        let first = match prefix.get(..2).and_then(|b| u8::from_str_radix(b, 16).ok()) {
+            Some(first) => first as usize,
+            None => return Vec::new(),
+        };
+        let start = if first == 0 { 0 } else { self.fanout[first - 1] as usize };
+        let end = self.fanout[first] as usize;
+
+        self.hashes[start..end]
+            .iter()
+            .map(hex::encode)
+            .filter(|h| h.starts_with(prefix))
+            .collect()
+    }
+
+    /// Iterate over all object hashes in the index (hex-encoded)
+    pub fn hashes(&self) -> impl Iterator<Item = String> + '_ {
+        self.hashes.iter().map(hex::encode)
+    }
+}
+
+/// An open packfile together with its index
+#[derive(Debug, Clone)]
+pub struct Pack {
+    /// Path to the `.pack` file
+    pub pack_path: PathBuf,
+    /// The parsed `.idx` file
+    pub index: PackIndex,
+}
+
+impl Pack {
+    /// Open a pack from the path of its `.idx` file
+    pub fn open(idx_path: &Path, format: ObjectFormat) -> Result<Self> {
+        let data = std::fs::read(idx_path)
+            .context(format!("Failed to read pack index: {}", idx_path.display()))?;
+        let index = PackIndex::parse(&data, format)
+            .context(format!("Invalid pack index: {}", idx_path.display()))?;
+
+        Ok(Self {
+            pack_path: idx_path.with_extension("pack"),
+            index,
+        })
+    }
+
+    /// Check if the pack contains an object
+    pub fn contains(&self, hash: &str) -> bool {
+        self.index.find_offset(hash).is_some()
+    }
+
+    /// Read an object by hash, resolving delta chains
+    ///
+    /// `REF_DELTA` bases are looked up through `store`, so they may live in
+    /// another pack or as loose objects.
+    pub fn read_object(&self, store: &dyn ObjectStore, hash: &str) -> Result<Option<GitObject>> {
+        let offset = match self.index.find_offset(hash) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+
+        let file = File::open(&self.pack_path)
+            .context(format!("Failed to open pack: {}", self.pack_path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let (object_type, content) = self.read_at(store, &mut reader, offset, 0)
+            .context(format!("Failed to read packed object: {}", hash))?;
+
+        Ok(Some(GitObject {
+            object_type,
+            size: content.len(),
+            content,
+        }))
+    }
+
+    /// Read and fully resolve the entry at `offset`
+    fn read_at(
+        &self,
+        store: &dyn ObjectStore,
+        reader: &mut BufReader<File>,
+        offset: u64,
+        depth: usize,
+    ) -> Result<(String, Vec<u8>)> {
+        if depth > MAX_DELTA_DEPTH {
+            anyhow::bail!("Delta chain too deep at offset {}", offset);
+        }
+
+        reader.seek(SeekFrom::Start(offset))?;
+        let (type_num, size) = read_entry_header(reader)?;
+
+        match type_num {
+            OBJ_OFS_DELTA => {
+                let distance = read_offset_delta(reader)?;
+                let base_offset = offset.checked_sub(distance)
+                    .context("Invalid OFS_DELTA: base offset before start of pack")?;
+                let delta = inflate(reader, size)?;
+                let (base_type, base) = self.read_at(store, reader, base_offset, depth + 1)?;
+                Ok((base_type, apply_delta(&base, &delta)?))
+            }
+            OBJ_REF_DELTA => {
+                let mut raw = vec![0u8; self.index.format.raw_len()];
+                reader.read_exact(&mut raw)?;
+                let delta = inflate(reader, size)?;
+                let base_hash = hex::encode(raw);
+                let base = store.read(&base_hash)
+                    .context(format!("Missing REF_DELTA base: {}", base_hash))?;
+                Ok((base.object_type, apply_delta(&base.content, &delta)?))
+            }
+            _ => {
+                let object_type = type_name(type_num)?.to_string();
+                Ok((object_type, inflate(reader, size)?))
+            }
+        }
+    }
+}
+
+/// Find all packs in an objects directory's `pack/` subdirectory
+pub fn find_packs(objects_dir: &Path, format: ObjectFormat) -> Result<Vec<Pack>> {
+    let pack_dir = objects_dir.join("pack");
+    let mut packs = Vec::new();
+
+    if !pack_dir.is_dir() {
+        return Ok(packs);
+    }
+
+    let mut idx_paths: Vec<_> = std::fs::read_dir(&pack_dir)
+        .context("Failed to read objects/pack")?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|ext| ext == "idx").unwrap_or(false))
+        .collect();
+    idx_paths.sort();
+
+    for idx_path in idx_paths {
+        // An index without its pack is useless (e.g. an interrupted repack)
+        if !idx_path.with_extension("pack").exists() {
+            continue;
+        }
+        packs.push(Pack::open(&idx_path, format)?);
+    }
+
+    Ok(packs)
+}
+
+/// An object to be written into a pack
+#[derive(Debug, Clone)]
+pub struct PackEntry {
+    /// Hex-encoded object ID
+    pub hash: String,
+    /// The type of object ("blob", "tree", "commit", "tag")
+    pub object_type: String,
+    /// The raw object content (without header)
+    pub content: Vec<u8>,
+    /// File name the object was reached through (used to group similar blobs)
+    pub name_hint: String,
+}
+
+/// Write a pack and its index into `pack_dir`
+///
+/// Files are written under temporary names and renamed into place, `.pack`
+/// first, so readers never see an index without its pack.
+///
+/// # Returns
+///
+/// The path of the new `.idx` file
+pub fn write_pack(pack_dir: &Path, mut entries: Vec<PackEntry>, format: ObjectFormat) -> Result<PathBuf> {
+    std::fs::create_dir_all(pack_dir)
+        .context("Failed to create objects/pack directory")?;
+
+    // Non-blobs first, then blobs grouped by name with the largest first,
+    // since deltas that remove data are smaller than ones that add it
+    entries.sort_by(|a, b| {
+        (a.object_type == "blob").cmp(&(b.object_type == "blob"))
+            .then_with(|| a.name_hint.cmp(&b.name_hint))
+            .then_with(|| b.content.len().cmp(&a.content.len()))
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+
+    let mut pack = b"PACK".to_vec();
+    pack.extend_from_slice(&2u32.to_be_bytes());
+    pack.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+
+    // (raw hash, crc32, offset) for the index
+    let mut index: Vec<(Vec<u8>, u32, u64)> = Vec::with_capacity(entries.len());
+    let mut offsets: Vec<u64> = Vec::with_capacity(entries.len());
+    let mut depths: Vec<usize> = Vec::with_capacity(entries.len());
+
+    for (i, entry) in entries.iter().enumerate() {
+        let offset = pack.len() as u64;
+
+        let delta = if entry.object_type == "blob" {
+            find_best_delta(&entries, &depths, i)
+        } else {
+            None
+        };
+
+        let mut data = Vec::new();
+        match delta {
+            Some((base, delta)) => {
+                data.extend(encode_entry_header(OBJ_OFS_DELTA, delta.len()));
+                data.extend(encode_offset_delta(offset - offsets[base]));
+                data.extend(deflate(&delta)?);
+                depths.push(depths[base] + 1);
+            }
+            None => {
+                let type_num = type_number(&entry.object_type)?;
+                data.extend(encode_entry_header(type_num, entry.content.len()));
+                data.extend(deflate(&entry.content)?);
+                depths.push(0);
+            }
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&data);
+        index.push((hex::decode(&entry.hash)?, crc.sum(), offset));
+        offsets.push(offset);
+        pack.extend(data);
+    }
+
+    let pack_checksum = format.digest(&pack);
+    pack.extend_from_slice(&pack_checksum);
+
+    index.sort();
+    let idx = encode_index(&index, &pack_checksum, format);
+
+    let name = format!("pack-{}", hex::encode(&pack_checksum));
+    let pack_path = pack_dir.join(format!("{}.pack", name));
+    let idx_path = pack_dir.join(format!("{}.idx", name));
+
+    // The pack goes first: an index is only ever found next to a whole pack
+    atomic::write(&pack_path, pack_dir, &pack).context("Failed to write pack file")?;
+    atomic::write(&idx_path, pack_dir, &idx).context("Failed to write pack index")?;
+
+    Ok(idx_path)
+}
+
+/// Pick the smallest useful delta for `entries[i]` among the previous blobs
+fn find_best_delta(
+    entries: &[PackEntry],
+    depths: &[usize],
+    i: usize,
+) -> Option<(usize, Vec<u8>)> {
+    let target = &entries[i].content;
+    let mut best: Option<(usize, Vec<u8>)> = None;
+
+    for base in (i.saturating_sub(DELTA_WINDOW)..i).rev() {
+        if entries[base].object_type != "blob" || depths[base] >= MAX_WRITE_DEPTH {
+            continue;
+        }
+
+        let delta = create_delta(&entries[base].content, target);
+
+        // Only worth it if the delta is well under the size of the object
+        let limit = best.as_ref().map(|(_, d)| d.len()).unwrap_or(target.len() / 2);
+        if delta.len() < limit {
+            best = Some((base, delta));
+        }
+    }
+
+    best
+}
+
+/// Create a Git delta that rebuilds `target` from `base`
+///
+/// Every 16-byte block of the base is indexed; the target is then scanned
+/// for blocks that appear in the index, and each match is extended as far as
+/// possible and emitted as a copy instruction. Everything else becomes
+/// literal insert instructions.
+pub fn create_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    delta.extend(encode_delta_size(base.len()));
+    delta.extend(encode_delta_size(target.len()));
+
+    let mut blocks: HashMap<&[u8], usize> = HashMap::new();
+    if base.len() >= DELTA_BLOCK {
+        for start in (0..=base.len() - DELTA_BLOCK).step_by(DELTA_BLOCK) {
+            blocks.entry(&base[start..start + DELTA_BLOCK]).or_insert(start);
+        }
+    }
+
+    let mut pending: Vec<u8> = Vec::new();
+    let mut pos = 0;
+
+    while pos < target.len() {
+        let found = if pos + DELTA_BLOCK <= target.len() {
+            blocks.get(&target[pos..pos + DELTA_BLOCK]).copied()
+        } else {
+            None
+        };
+
+        match found {
+            Some(base_start) => {
+                let mut len = DELTA_BLOCK;
+                while base_start + len < base.len()
+                    && pos + len < target.len()
+                    && base[base_start + len] == target[pos + len]
+                    && len < 0xff_ffff
+                {
+                    len += 1;
+                }
+
+                flush_insert(&mut delta, &mut pending);
+                encode_copy(&mut delta, base_start, len);
+                pos += len;
+            }
+            None => {
+                pending.push(target[pos]);
+                pos += 1;
+            }
+        }
+    }
+
+    flush_insert(&mut delta, &mut pending);
+    delta
+}
+
+/// Emit pending literal bytes as insert instructions (at most 127 each)
+fn flush_insert(delta: &mut Vec<u8>, pending: &mut Vec<u8>) {
+    for chunk in pending.chunks(0x7f) {
+        delta.push(chunk.len() as u8);
+        delta.extend_from_slice(chunk);
+    }
+    pending.clear();
+}
+
+/// Emit a copy instruction, omitting zero offset/size bytes
+fn encode_copy(delta: &mut Vec<u8>, offset: usize, size: usize) {
+    let mut op = 0x80u8;
+    let mut args = Vec::new();
+
+    for i in 0..4 {
+        let byte = (offset >> (8 * i)) as u8;
+        if byte != 0 {
+            op |= 1 << i;
+            args.push(byte);
+        }
+    }
+    for i in 0..3 {
+        let byte = (size >> (8 * i)) as u8;
+        if byte != 0 {
+            op |= 0x10 << i;
+            args.push(byte);
+        }
+    }
+
+    delta.push(op);
+    delta.extend(args);
+}
+
+/// Encode a little-endian base-128 size for a delta header
+fn encode_delta_size(mut size: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            out.push(byte);
+            return out;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Encode a pack entry header (inverse of `read_entry_header`)
+fn encode_entry_header(type_num: u8, size: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut byte = (type_num << 4) | (size & 0x0f) as u8;
+    let mut rest = size >> 4;
+    while rest > 0 {
+        out.push(byte | 0x80);
+        byte = (rest & 0x7f) as u8;
+        rest >>= 7;
+    }
+    out.push(byte);
+    out
+}
+
+/// Encode an `OFS_DELTA` base distance (inverse of `read_offset_delta`)
+fn encode_offset_delta(mut distance: u64) -> Vec<u8> {
+    let mut out = vec![(distance & 0x7f) as u8];
+    distance >>= 7;
+    while distance > 0 {
+        distance -= 1;
+        out.push(0x80 | (distance & 0x7f) as u8);
+        distance >>= 7;
+    }
+    out.reverse();
+    out
+}
+
+/// Compress data with zlib
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Build a version 2 `.idx` file from sorted (hash, crc, offset) triples
+fn encode_index(index: &[(Vec<u8>, u32, u64)], pack_checksum: &[u8], format: ObjectFormat) -> Vec<u8> {
+    let mut idx = IDX_MAGIC.to_vec();
+    idx.extend_from_slice(&2u32.to_be_bytes());
+
+    let mut fanout = [0u32; 256];
+    for (hash, _, _) in index {
+        fanout[hash[0] as usize] += 1;
+    }
+    let mut total = 0;
+    for count in fanout.iter_mut() {
+        total += *count;
+        *count = total;
+    }
+    for count in fanout {
+        idx.extend_from_slice(&count.to_be_bytes());
+    }
+
+    for (hash, _, _) in index {
+        idx.extend_from_slice(hash);
+    }
+    for (_, crc, _) in index {
+        idx.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    let mut large_offsets = Vec::new();
+    for (_, _, offset) in index {
+        if *offset < 0x8000_0000 {
+            idx.extend_from_slice(&(*offset as u32).to_be_bytes());
+        } else {
+            let slot = 0x8000_0000 | (large_offsets.len() / 8) as u32;
+            idx.extend_from_slice(&slot.to_be_bytes());
+            large_offsets.extend_from_slice(&offset.to_be_bytes());
+        }
+    }
+    idx.extend(large_offsets);
+
+    idx.extend_from_slice(pack_checksum);
+    let idx_checksum = format.digest(&idx);
+    idx.extend(idx_checksum);
+    idx
+}
+
+/// Read a big-endian u32 at `pos`
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+/// Read a single byte from a reader
+fn read_byte<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Read a pack entry header: 3-bit type and variable-length size
+///
+/// The first byte holds `MSB | type (3 bits) | size (4 bits)`; each following
+/// byte contributes 7 more bits of size while the MSB is set.
+fn read_entry_header<R: Read>(reader: &mut R) -> Result<(u8, usize)> {
+    let mut byte = read_byte(reader)?;
+    let type_num = (byte >> 4) & 0x07;
+    let mut size = (byte & 0x0f) as usize;
+    let mut shift = 4;
+
+    while byte & 0x80 != 0 {
+        byte = read_byte(reader)?;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+    }
+
+    Ok((type_num, size))
+}
+
+/// Read the base distance of an `OFS_DELTA` entry
+///
+/// This encoding adds one before each continuation so that every offset has
+/// exactly one representation.
+fn read_offset_delta<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut byte = read_byte(reader)?;
+    let mut offset = (byte & 0x7f) as u64;
+
+    while byte & 0x80 != 0 {
+        byte = read_byte(reader)?;
+        offset = ((offset + 1) << 7) | (byte & 0x7f) as u64;
+    }
+
+    Ok(offset)
+}
+
+/// Inflate a zlib stream of known size from the current reader position
+fn inflate(reader: &mut BufReader<File>, size: usize) -> Result<Vec<u8>> {
+    let mut decoder = ZlibDecoder::new(reader);
+    let mut data = Vec::with_capacity(size);
+    decoder.read_to_end(&mut data)
+        .context("Failed to decompress packed object")?;
+
+    if data.len() != size {
+        anyhow::bail!("Size mismatch: entry says {} but inflated {} bytes", size, data.len());
+    }
+
+    Ok(data)
+}
+
+/// Read a little-endian base-128 size from delta data
+fn read_delta_size(delta: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut size = 0usize;
+    let mut shift = 0;
+
+    loop {
+        let byte = *delta.get(*pos).context("Truncated delta header")?;
+        *pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+/// Apply a Git delta to a base object
+///
+/// Delta format:
+/// ```text
+/// <base size> <result size>
+/// 1xxxxxxx <offset bytes> <size bytes>   # copy from base
+/// 0xxxxxxx <data>                        # insert xxxxxxx literal bytes
+/// ```
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut pos = 0;
+    let base_size = read_delta_size(delta, &mut pos)?;
+    let result_size = read_delta_size(delta, &mut pos)?;
+
+    if base_size != base.len() {
+        anyhow::bail!("Delta base size mismatch: expected {} but base is {} bytes",
+            base_size, base.len());
+    }
+
+    let mut result = Vec::with_capacity(result_size);
+
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+
+        if op & 0x80 != 0 {
+            // Copy: bits 0-3 select offset bytes, bits 4-6 select size bytes
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos).context("Truncated delta copy")? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (*delta.get(pos).context("Truncated delta copy")? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+
+            let end = offset.checked_add(size)
+                .filter(|&end| end <= base.len())
+                .context("Delta copy out of range")?;
+            result.extend_from_slice(&base[offset..end]);
+        } else if op != 0 {
+            // Insert literal bytes
+            let end = pos + op as usize;
+            if end > delta.len() {
+                anyhow::bail!("Truncated delta insert");
+            }
+            result.extend_from_slice(&delta[pos..end]);
+            pos = end;
+        } else {
+            anyhow::bail!("Invalid delta opcode 0");
+        }
+    }
+
+    if result.len() != result_size {
+        anyhow::bail!("Delta result size mismatch: expected {} but got {} bytes",
+            result_size, result.len());
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::Repository;
+    use crate::commands::{cat_file, hash_object};
+
+    /// Write a pack + idx pair from pre-encoded entries
+    fn write_raw_pack(dir: &Path, entries: &[(String, Vec<u8>)]) {
+        let mut pack = b"PACK".to_vec();
+        pack.extend_from_slice(&2u32.to_be_bytes());
+        pack.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+
+        let mut index: Vec<(Vec<u8>, u32)> = Vec::new();
+        for (hash, data) in entries {
+            index.push((hex::decode(hash).unwrap(), pack.len() as u32));
+            pack.extend_from_slice(data);
+        }
+        pack.extend_from_slice(&[0u8; 20]);
+        index.sort();
+
+        let mut idx = IDX_MAGIC.to_vec();
+        idx.extend_from_slice(&2u32.to_be_bytes());
+        for i in 0..256 {
+            let count = index.iter().filter(|(h, _)| (h[0] as usize) <= i).count() as u32;
+            idx.extend_from_slice(&count.to_be_bytes());
+        }
+        for (hash, _) in &index {
+            idx.extend_from_slice(hash);
+        }
+        for _ in &index {
+            idx.extend_from_slice(&0u32.to_be_bytes());
+        }
+        for (_, offset) in &index {
+            idx.extend_from_slice(&offset.to_be_bytes());
+        }
+        idx.extend_from_slice(&[0u8; 40]);
+
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("pack-test.pack"), pack).unwrap();
+        std::fs::write(dir.join("pack-test.idx"), idx).unwrap();
+    }
+
+    #[test]
+    fn test_unsorted_fanout_rejected() {
+        let mut idx = IDX_MAGIC.to_vec();
+        idx.extend_from_slice(&2u32.to_be_bytes());
+        // Claims one object starting with 0x00, but none up to 0xff
+        idx.extend_from_slice(&1u32.to_be_bytes());
+        for _ in 1..256 {
+            idx.extend_from_slice(&0u32.to_be_bytes());
+        }
+        idx.extend_from_slice(&[0u8; 40]);
+
+        let err = PackIndex::parse(&idx, ObjectFormat::Sha1).unwrap_err();
+        assert!(err.to_string().contains("fanout"));
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let base = b"Hello, World!";
+        // base size 13, result size 12, copy 7 bytes from 0, insert "Rust!"
+        let mut delta = vec![13, 12];
+        delta.extend_from_slice(&[0x90, 7]);
+        delta.push(5);
+        delta.extend_from_slice(b"Rust!");
+
+        let result = apply_delta(base, &delta).unwrap();
+        assert_eq!(result, b"Hello, Rust!");
+    }
+
+    #[test]
+    fn test_read_packed_objects_with_deltas() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let base = b"Hello, World!".to_vec();
+        let target = b"Hello, Rust!".to_vec();
+        let base_hash = hash_object::hash_content("blob", &base);
+        let target_hash = hash_object::hash_content("blob", &target);
+
+        let mut delta = vec![13, 12, 0x90, 7, 5];
+        delta.extend_from_slice(b"Rust!");
+
+        // Base blob at offset 12, OFS_DELTA pointing back to it
+        let mut base_entry = encode_entry_header(OBJ_BLOB, base.len());
+        base_entry.extend(deflate(&base).unwrap());
+        let mut ofs_entry = encode_entry_header(OBJ_OFS_DELTA, delta.len());
+        ofs_entry.push(base_entry.len() as u8);
+        ofs_entry.extend(deflate(&delta).unwrap());
+
+        write_raw_pack(&repo.objects_dir().join("pack"), &[
+            (base_hash.clone(), base_entry),
+            (target_hash.clone(), ofs_entry),
+        ]);
+
+        let object = cat_file::read_object(&repo, &base_hash).unwrap();
+        assert_eq!(object.object_type, "blob");
+        assert_eq!(object.content, base);
+
+        let object = cat_file::read_object(&repo, &target_hash).unwrap();
+        assert_eq!(object.object_type, "blob");
+        assert_eq!(object.content, target);
+    }
+
+    #[test]
+    fn test_ref_delta_against_loose_base() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let base = b"Hello, World!".to_vec();
+        let target = b"Hello, Rust!".to_vec();
+        let base_hash = hash_object::store_object(&repo, "blob", &base).unwrap();
+        let target_hash = hash_object::hash_content("blob", &target);
+
+        let mut delta = vec![13, 12, 0x90, 7, 5];
+        delta.extend_from_slice(b"Rust!");
+
+        let mut ref_entry = encode_entry_header(OBJ_REF_DELTA, delta.len());
+        ref_entry.extend(hex::decode(&base_hash).unwrap());
+        ref_entry.extend(deflate(&delta).unwrap());
+
+        write_raw_pack(&repo.objects_dir().join("pack"), &[(target_hash.clone(), ref_entry)]);
+
+        let object = cat_file::read_object(&repo, &target_hash).unwrap();
+        assert_eq!(object.content, target);
+    }
+
+    #[test]
+    fn test_create_delta_roundtrip() {
+        let base: Vec<u8> = (0..2000u32).flat_map(|i| format!("line {}\n", i).into_bytes()).collect();
+        let mut target = base.clone();
+        target.splice(500..510, b"changed!".iter().copied());
+        target.extend_from_slice(b"appended\n");
+
+        let delta = create_delta(&base, &target);
+        assert!(delta.len() < target.len() / 10);
+        assert_eq!(apply_delta(&base, &delta).unwrap(), target);
+    }
+
+    #[test]
+    fn test_offset_delta_encoding() {
+        for distance in [1u64, 127, 128, 16511, 16512, 1 << 30] {
+            let encoded = encode_offset_delta(distance);
+            let decoded = read_offset_delta(&mut &encoded[..]).unwrap();
+            assert_eq!(decoded, distance);
+        }
+    }
+
+    #[test]
+    fn test_write_pack_roundtrip() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let v1: Vec<u8> = (0..500u32).flat_map(|i| format!("row {}\n", i).into_bytes()).collect();
+        let mut v2 = v1.clone();
+        v2.extend_from_slice(b"one more row\n");
+
+        let entries: Vec<PackEntry> = [v1, v2].into_iter().map(|content| PackEntry {
+            hash: hash_object::hash_content("blob", &content),
+            object_type: "blob".to_string(),
+            content,
+            name_hint: "data.txt".to_string(),
+        }).collect();
+
+        let idx_path = write_pack(&repo.objects_dir().join("pack"), entries.clone(), ObjectFormat::Sha1).unwrap();
+        let pack = Pack::open(&idx_path, ObjectFormat::Sha1).unwrap();
+        assert_eq!(pack.index.len(), 2);
+
+        // The second version should have been stored as a delta
+        let pack_size = std::fs::metadata(&pack.pack_path).unwrap().len() as usize;
+        assert!(pack_size < entries[0].content.len());
+
+        for entry in &entries {
+            let object = cat_file::read_object(&repo, &entry.hash).unwrap();
+            assert_eq!(object.content, entry.content);
+        }
+    }
+}
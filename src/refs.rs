@@ -0,0 +1,286 @@
+//! # References
+//!
+//! Named pointers to commits (branches, tags) live in two places:
+//!
+//! - **Loose refs**: one file per ref under `refs/`, e.g.
+//!   `refs/heads/main` containing a hash (or `ref: <other ref>` for a
+//!   symbolic ref)
+//! - **Packed refs**: a single `packed-refs` file that Git writes on
+//!   `clone` and `gc`, one `<hash> <name>` line per ref
+//!
+//! ```text
+//! # pack-refs with: peeled fully-peeled sorted
+//! 3f2a9c1e... refs/heads/main
+//! 91bc04d7... refs/tags/v1.0
+//! ^5e6f7a8b...
+//! ```
+//!
+//! A `^` line gives the commit an annotated tag above it points to. When a
+//! ref exists in both places, the loose one wins. rit writes loose refs
+//! only; packed refs are read so that repositories created by Git work.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use rit::{refs, Repository};
+//!
+//! let repo = Repository::find().unwrap();
+//! for (name, hash) in refs::list_refs(&repo, "refs/heads/").unwrap() {
+//!     println!("{} {}", hash, name);
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+use crate::Repository;
+use crate::atomic;
+
+/// Name of the packed refs file in the repository directory
+pub const PACKED_REFS: &str = "packed-refs";
+
+/// How many symbolic refs are followed before giving up
+const MAX_SYMREF_DEPTH: usize = 5;
+
+/// Path of the packed refs file
+fn packed_refs_path(repo: &Repository) -> PathBuf {
+    repo.rit_dir.join(PACKED_REFS)
+}
+
+/// Path of a loose ref file
+fn loose_ref_path(repo: &Repository, name: &str) -> PathBuf {
+    repo.rit_dir.join(name)
+}
+
+/// Parse `packed-refs` contents into `(name, hash)` pairs, in file order
+///
+/// Comment lines and `^` peeled lines are skipped.
+///
+/// # Example
+///
+/// ```
+/// use rit::refs::parse_packed_refs;
+///
+/// let refs = parse_packed_refs("# pack-refs with: peeled\nabc123 refs/tags/v1\n^def456\n");
+/// assert_eq!(refs, vec![("refs/tags/v1".to_string(), "abc123".to_string())]);
+/// ```
+pub fn parse_packed_refs(content: &str) -> Vec<(String, String)> {
+    content.lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .filter_map(|line| line.split_once(' '))
+        .map(|(hash, name)| (name.trim().to_string(), hash.trim().to_string()))
+        .collect()
+}
+
+/// Read the packed refs file; a missing file has no refs
+pub fn read_packed_refs(repo: &Repository) -> Result<Vec<(String, String)>> {
+    let path = packed_refs_path(repo);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .context("Failed to read packed-refs")?;
+    Ok(parse_packed_refs(&content))
+}
+
+/// Read a loose ref file's raw (trimmed) contents, if it exists
+fn read_loose(path: &Path) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read ref: {}", path.display()))?;
+    Ok(Some(content.trim().to_string()))
+}
+
+/// Resolve a ref like `refs/heads/main` to the hash it points to
+///
+/// Loose refs are checked first, then `packed-refs`. Symbolic refs
+/// (`ref: refs/heads/main`) are followed.
+///
+/// # Returns
+///
+/// `None` if the ref doesn't exist (e.g. the branch of a repository with
+/// no commits yet)
+pub fn read_ref(repo: &Repository, name: &str) -> Result<Option<String>> {
+    let mut name = name.to_string();
+
+    for _ in 0..MAX_SYMREF_DEPTH {
+        match read_loose(&loose_ref_path(repo, &name))? {
+            Some(content) => match content.strip_prefix("ref: ") {
+                Some(target) => name = target.trim().to_string(),
+                None if content.is_empty() => return Ok(None),
+                None => return Ok(Some(content)),
+            },
+            None => {
+                return Ok(read_packed_refs(repo)?
+                    .into_iter()
+                    .find(|(packed, _)| *packed == name)
+                    .map(|(_, hash)| hash));
+            }
+        }
+    }
+
+    anyhow::bail!("fatal: symbolic ref loop or too deep: {}", name);
+}
+
+/// Check whether a ref exists, loose or packed
+pub fn ref_exists(repo: &Repository, name: &str) -> Result<bool> {
+    if loose_ref_path(repo, name).is_file() {
+        return Ok(true);
+    }
+    Ok(read_packed_refs(repo)?.iter().any(|(packed, _)| packed == name))
+}
+
+/// List every ref whose name starts with `prefix`, resolved to hashes
+///
+/// Loose and packed refs are merged (loose wins) and sorted by name.
+/// Symbolic refs are resolved; ones that point nowhere are left out.
+///
+/// # Arguments
+///
+/// * `repo` - The repository
+/// * `prefix` - A name prefix such as `refs/heads/`, or `refs/` for all
+pub fn list_refs(repo: &Repository, prefix: &str) -> Result<Vec<(String, String)>> {
+    let mut refs: BTreeMap<String, String> = read_packed_refs(repo)?
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect();
+
+    let refs_dir = repo.refs_dir();
+    for entry in WalkDir::new(&refs_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.path().is_file() {
+            continue;
+        }
+
+        let name = entry.path()
+            .strip_prefix(&repo.rit_dir)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        if !name.starts_with(prefix) {
+            continue;
+        }
+
+        match read_ref(repo, &name)? {
+            Some(hash) => {
+                refs.insert(name, hash);
+            }
+            None => {
+                refs.remove(&name);
+            }
+        }
+    }
+
+    Ok(refs.into_iter().collect())
+}
+
+/// Delete a ref from both the loose refs and `packed-refs`
+///
+/// # Returns
+///
+/// The hash the ref pointed to, or `None` if it didn't exist
+pub fn delete_ref(repo: &Repository, name: &str) -> Result<Option<String>> {
+    let old = read_ref(repo, name)?;
+
+    let loose = loose_ref_path(repo, name);
+    if loose.is_file() {
+        fs::remove_file(&loose)
+            .context(format!("Failed to delete ref: {}", name))?;
+    }
+
+    let packed_path = packed_refs_path(repo);
+    if packed_path.exists() {
+        let content = fs::read_to_string(&packed_path)
+            .context("Failed to read packed-refs")?;
+
+        // Drop the ref's line and the peeled line that follows it
+        let mut kept = Vec::new();
+        let mut removed = false;
+        let mut skip_peeled = false;
+        for line in content.lines() {
+            if skip_peeled && line.starts_with('^') {
+                continue;
+            }
+            skip_peeled = false;
+            if line.split_once(' ').is_some_and(|(_, n)| n.trim() == name) && !line.starts_with('#') {
+                removed = true;
+                skip_peeled = true;
+                continue;
+            }
+            kept.push(line);
+        }
+
+        if removed {
+            let mut new_content = kept.join("\n");
+            new_content.push('\n');
+            atomic::write(&packed_path, &repo.rit_dir, new_content.as_bytes())
+                .context("Failed to write packed-refs")?;
+        }
+    }
+
+    Ok(old)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn repo_with_packed_refs() -> (tempfile::TempDir, Repository) {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(
+            repo.rit_dir.join(PACKED_REFS),
+            format!(
+                "# pack-refs with: peeled fully-peeled sorted\n{} refs/heads/main\n{} refs/tags/v1\n^{}\n",
+                "a".repeat(40), "b".repeat(40), "c".repeat(40)
+            ),
+        ).unwrap();
+        (temp, repo)
+    }
+
+    #[test]
+    fn test_read_packed_and_loose_refs() {
+        let (_temp, repo) = repo_with_packed_refs();
+
+        assert_eq!(read_ref(&repo, "refs/heads/main").unwrap(), Some("a".repeat(40)));
+        assert_eq!(read_ref(&repo, "refs/tags/v1").unwrap(), Some("b".repeat(40)));
+        assert_eq!(read_ref(&repo, "refs/heads/missing").unwrap(), None);
+
+        // A loose ref overrides the packed one
+        fs::write(repo.rit_dir.join("refs/heads/main"), format!("{}\n", "d".repeat(40))).unwrap();
+        assert_eq!(read_ref(&repo, "refs/heads/main").unwrap(), Some("d".repeat(40)));
+
+        // Symbolic refs are followed
+        fs::create_dir_all(repo.rit_dir.join("refs/remotes/origin")).unwrap();
+        fs::write(repo.rit_dir.join("refs/remotes/origin/HEAD"), "ref: refs/tags/v1\n").unwrap();
+
+        assert_eq!(list_refs(&repo, "refs/").unwrap(), vec![
+            ("refs/heads/main".to_string(), "d".repeat(40)),
+            ("refs/remotes/origin/HEAD".to_string(), "b".repeat(40)),
+            ("refs/tags/v1".to_string(), "b".repeat(40)),
+        ]);
+        assert_eq!(list_refs(&repo, "refs/tags/").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_delete_packed_ref() {
+        let (_temp, repo) = repo_with_packed_refs();
+
+        assert_eq!(delete_ref(&repo, "refs/tags/v1").unwrap(), Some("b".repeat(40)));
+        assert!(!ref_exists(&repo, "refs/tags/v1").unwrap());
+        assert!(ref_exists(&repo, "refs/heads/main").unwrap());
+
+        let content = fs::read_to_string(repo.rit_dir.join(PACKED_REFS)).unwrap();
+        assert!(content.starts_with("# pack-refs"));
+        assert!(!content.contains('^'));
+
+        assert_eq!(delete_ref(&repo, "refs/tags/v1").unwrap(), None);
+    }
+}
@@ -6,6 +6,7 @@ const sidebars = {
     'intro',
     'architecture',
     'ritignore',
+    'git-repositories',
     {
       type: 'category',
       label: 'Commands',
@@ -18,13 +19,22 @@ const sidebars = {
         'commands/commit-tree',
         'commands/log',
         'commands/add',
+        'commands/rm',
+        'commands/mv',
         'commands/commit',
+        'commands/reset',
         'commands/branch',
         'commands/checkout',
         'commands/tag',
         'commands/status',
         'commands/diff',
         'commands/graph',
+        'commands/repack',
+        'commands/gc',
+        'commands/fsck',
+        'commands/prune',
+        'commands/commit-graph',
+        'commands/lfs',
       ],
     },
   ],